@@ -1,33 +1,73 @@
 import assert from 'node:assert/strict';
 import { readFile } from 'node:fs/promises';
+import { Readable } from 'node:stream';
 import { test } from 'node:test';
 
 import webviewjs from '../index.js';
 
-const { Application, BrowserWindow, Notification, SerializationError, TrayIcon, WebContext, Webview } = webviewjs;
+const {
+  Application,
+  BrowserWindow,
+  EvaluationError,
+  Notification,
+  SerializationError,
+  TrayIcon,
+  WebContext,
+  Webview,
+  WebviewChannel,
+} = webviewjs;
 
 const flush = () => new Promise((resolve) => setImmediate(resolve));
 
 function protocolWindow() {
   return {
     completed: [],
-    _registerProtocol(_name, callback) {
+    _registerProtocol(_name, callback, options) {
       this.callback = callback;
+      this.options = options;
+    },
+    _registerProtocolRouter(_name, routes, callback, options) {
+      this.routes = routes;
+      this.callback = callback;
+      this.options = options;
     },
     _completeProtocol(id, response) {
       this.completed.push([id, response]);
     },
+    files: [],
+    _completeProtocolFile(id, response) {
+      this.files.push([id, response]);
+    },
   };
 }
 
 function exposedWebview() {
   return {
-    scripts: [],
-    _exposeInternal(_name, _statics, _functions, callback) {
+    replies: [],
+    invoked: [],
+    _exposeInternal(_name, _statics, _functions, callback, origins) {
       this.callback = callback;
+      this.origins = origins;
+    },
+    _exposeReply(id, valueJson, error) {
+      this.replies.push({ id, valueJson, error });
+      return true;
+    },
+    _unexposeInternal(name) {
+      this.unexposed = name;
+      return true;
+    },
+    _exposeStream(id) {
+      this.streamed = id;
+      return 9;
     },
-    evaluateScript(script) {
-      this.scripts.push(script);
+    _invokeCallback(id, argsJson) {
+      this.invoked.push({ id, argsJson });
+      return !this.released?.includes(id);
+    },
+    _releaseCallback(id) {
+      (this.released ??= []).push(id);
+      return true;
     },
   };
 }
@@ -420,7 +460,7 @@ test('registerProtocol completes an asynchronous handler response', async () =>
     mimeType: 'text/plain',
   }));
 
-  win.callback(JSON.stringify({ id: 9, url: 'app://localhost/index.html', method: 'GET', headers: [], body: null }));
+  win.callback({ id: 9, url: 'app://localhost/index.html', method: 'GET', headers: [] });
   await flush();
 
   assert.deepEqual(win.completed, [
@@ -442,7 +482,7 @@ test('registerProtocol maps a rejected asynchronous handler to a text 500 respon
     throw new Error('read failed');
   });
 
-  win.callback(JSON.stringify({ id: 10, url: 'app://localhost/missing', method: 'GET', headers: [], body: null }));
+  win.callback({ id: 10, url: 'app://localhost/missing', method: 'GET', headers: [] });
   await flush();
 
   assert.equal(win.completed[0][0], 10);
@@ -451,6 +491,123 @@ test('registerProtocol maps a rejected asynchronous handler to a text 500 respon
   assert.equal(win.completed[0][1].body.toString(), 'read failed');
 });
 
+test('registerProtocol passes binary request bodies through as a Buffer', async () => {
+  const win = protocolWindow();
+  const upload = Buffer.from([0, 1, 2, 253, 254, 255]);
+  let received;
+
+  BrowserWindow.prototype.registerProtocol.call(win, 'app', async (request) => {
+    received = { type: request.headers.get('content-type'), body: Buffer.from(await request.arrayBuffer()) };
+    return { statusCode: 204, body: Buffer.alloc(0) };
+  });
+
+  win.callback({
+    id: 11,
+    url: 'app://localhost/upload',
+    method: 'POST',
+    headers: [{ key: 'content-type', value: 'application/octet-stream' }],
+    body: upload,
+  });
+  await flush();
+  await flush();
+
+  assert.equal(received.type, 'application/octet-stream');
+  assert.deepEqual(received.body, upload);
+  assert.equal(win.completed[0][0], 11);
+});
+
+test('registerProtocol forwards the timeout option to the native registration', () => {
+  const win = protocolWindow();
+
+  BrowserWindow.prototype.registerProtocol.call(win, 'app', () => new Response('ok'), { timeout: 5000 });
+
+  assert.deepEqual(win.options, { timeout: 5000 });
+});
+
+test('registerProtocol reads a streamed Response to its end before completing it', async () => {
+  const win = protocolWindow();
+  const encoder = new TextEncoder();
+
+  BrowserWindow.prototype.registerProtocol.call(win, 'app', () => {
+    const body = new ReadableStream({
+      start(controller) {
+        controller.enqueue(encoder.encode('<p>one</p>'));
+        controller.enqueue(encoder.encode('<p>two</p>'));
+        controller.close();
+      },
+    });
+    return new Response(body, { status: 201, headers: { 'content-type': 'text/html', 'x-test': '1' } });
+  });
+
+  win.callback({ id: 12, url: 'app://localhost/page', method: 'GET', headers: [] });
+  for (let i = 0; i < 10; i++) await flush();
+
+  assert.deepEqual(win.completed, [
+    [
+      12,
+      {
+        statusCode: 201,
+        body: Buffer.from('<p>one</p><p>two</p>'),
+        mimeType: 'text/html',
+        headers: [{ key: 'x-test', value: '1' }],
+      },
+    ],
+  ]);
+});
+
+test('registerProtocol hands file responses to the native side', async () => {
+  const win = protocolWindow();
+
+  BrowserWindow.prototype.registerProtocol.call(win, 'app', () => ({ path: '/media/clip.mp4', offset: 1024 }));
+
+  win.callback({ id: 14, url: 'app://localhost/clip', method: 'GET', headers: [{ key: 'range', value: 'bytes=0-' }] });
+  await flush();
+
+  assert.deepEqual(win.files, [[14, { path: '/media/clip.mp4', offset: 1024 }]]);
+  assert.deepEqual(win.completed, []);
+});
+
+test('registerProtocolRouter hands native routes over and numbers JS handlers', async () => {
+  const win = protocolWindow();
+
+  BrowserWindow.prototype.registerProtocolRouter.call(
+    win,
+    'app',
+    [
+      { path: '/api', handler: (req) => new Response(`api ${new URL(req.url).pathname}`) },
+      { host: 'docs', path: '/', archive: { path: './docs.zip' } },
+      { path: '/admin/*', handler: () => ({ statusCode: 403, body: Buffer.from('no'), mimeType: 'text/plain' }) },
+      { path: '/', directory: { root: './dist' } },
+    ],
+    { timeout: 1000 },
+  );
+
+  assert.deepEqual(win.routes, [
+    { path: '/api', handler: 0 },
+    { host: 'docs', path: '/', archive: { path: './docs.zip' } },
+    { path: '/admin/*', handler: 1 },
+    { path: '/', directory: { root: './dist' } },
+  ]);
+  assert.deepEqual(win.options, { timeout: 1000 });
+
+  win.callback({ id: 1, url: 'app://localhost/api/users', method: 'GET', headers: [], route: 0 });
+  win.callback({ id: 2, url: 'app://localhost/admin/x', method: 'GET', headers: [], route: 1 });
+  for (let i = 0; i < 10; i++) await flush();
+
+  const completed = new Map(win.completed);
+  assert.equal(completed.get(1).body.toString(), 'api /api/users');
+  assert.equal(completed.get(2).statusCode, 403);
+});
+
+test('registerProtocolRouter rejects a route handler that is not a function', () => {
+  const win = protocolWindow();
+
+  assert.throws(
+    () => BrowserWindow.prototype.registerProtocolRouter.call(win, 'app', [{ path: '/api', handler: 'nope' }]),
+    TypeError,
+  );
+});
+
 test('expose rejects circular static values with SerializationError', () => {
   const webview = exposedWebview();
   const circular = {};
@@ -467,6 +624,27 @@ test('expose rejects a duplicate namespace at registration time', () => {
   assert.throws(() => Webview.prototype.expose.call(webview, 'native', { second: true }), /already registered/);
 });
 
+test('unexpose frees the namespace for a later expose', () => {
+  const webview = exposedWebview();
+
+  Webview.prototype.expose.call(webview, 'native', { first: true });
+  assert.equal(Webview.prototype.unexpose.call(webview, 'native'), true);
+  assert.equal(webview.unexposed, 'native');
+
+  assert.doesNotThrow(() => Webview.prototype.expose.call(webview, 'native', { second: true }));
+});
+
+test('expose hands its origin allowlist to the native side', () => {
+  const webview = exposedWebview();
+
+  Webview.prototype.expose.call(webview, 'native', { ok: true }, { origins: ['app://localhost'] });
+  assert.deepEqual(webview.origins, ['app://localhost']);
+  Webview.prototype.expose.call(webview, 'other', { ok: true });
+  assert.equal(webview.origins, undefined);
+
+  assert.throws(() => Webview.prototype.expose.call(webview, 'bad', {}, { origins: 'app://localhost' }), TypeError);
+});
+
 test('expose resolves asynchronous Node functions in the page bridge', async () => {
   const webview = exposedWebview();
 
@@ -474,7 +652,7 @@ test('expose resolves asynchronous Node functions in the page bridge', async ()
   webview.callback({ ns: 'native', method: 'answer', id: 1, argsJson: '[]' });
   await flush();
 
-  assert.match(webview.scripts.at(-1), /resolve\(1,42\)/);
+  assert.deepEqual(webview.replies, [{ id: 1, valueJson: '42', error: undefined }]);
 });
 
 test('expose sends a SerializationError name to the page for non-serializable results', async () => {
@@ -484,7 +662,306 @@ test('expose sends a SerializationError name to the page for non-serializable re
   webview.callback({ ns: 'native', method: 'broken', id: 2, argsJson: '[]' });
   await flush();
 
-  assert.match(webview.scripts.at(-1), /SerializationError/);
+  assert.equal(webview.replies.at(-1).id, 2);
+  assert.equal(webview.replies.at(-1).error.name, 'SerializationError');
+});
+
+test('expose streams async iterables to the page one item per pull', async () => {
+  const webview = exposedWebview();
+  let finished = false;
+
+  Webview.prototype.expose.call(webview, 'logs', {
+    async *tail(prefix) {
+      try {
+        yield `${prefix}1`;
+        yield `${prefix}2`;
+      } finally {
+        finished = true;
+      }
+    },
+  });
+  webview.callback({ ns: 'logs', method: 'tail', id: 1, argsJson: '["line "]' });
+  await flush();
+  assert.equal(webview.streamed, 1);
+  assert.deepEqual(webview.replies, []);
+
+  webview.callback({ ns: 'logs', method: 'next', id: 2, argsJson: '[]', stream: 9 });
+  await flush();
+  assert.deepEqual(webview.replies, [{ id: 2, valueJson: '{"done":false,"value":"line 1"}', error: undefined }]);
+
+  webview.callback({ ns: 'logs', method: 'return', id: 0, argsJson: '[]', stream: 9 });
+  await flush();
+  assert.equal(finished, true);
+
+  webview.callback({ ns: 'logs', method: 'next', id: 3, argsJson: '[]', stream: 9 });
+  assert.deepEqual(webview.replies.at(-1).error, { name: 'Error', message: 'The stream is closed' });
+});
+
+test('expose streams Node readables until they end', async () => {
+  const webview = exposedWebview();
+
+  Webview.prototype.expose.call(webview, 'jobs', { progress: () => Readable.from([{ percent: 50 }]) });
+  webview.callback({ ns: 'jobs', method: 'progress', id: 1, argsJson: '[]' });
+  await flush();
+  for (const id of [2, 3]) {
+    webview.callback({ ns: 'jobs', method: 'next', id, argsJson: '[]', stream: 9 });
+    await flush();
+  }
+
+  const items = webview.replies.map((reply) => reply.valueJson);
+  assert.deepEqual(items, ['{"done":false,"value":{"percent":50}}', '{"done":true}']);
+});
+
+test('expose turns page function arguments into callable handles', async () => {
+  const webview = exposedWebview();
+  let onChange;
+
+  Webview.prototype.expose.call(webview, 'fs', {
+    watch(path, options) {
+      onChange = options.onChange;
+      return path;
+    },
+  });
+  webview.callback({
+    ns: 'fs',
+    method: 'watch',
+    id: 1,
+    argsJson: '["/tmp",{"onChange":{"__webviewjsCallback":5}}]',
+    callbacks: [5],
+  });
+  await flush();
+
+  assert.equal(onChange('created', { name: 'a.txt' }), true);
+  assert.deepEqual(webview.invoked, [{ id: 5, argsJson: '["created",{"name":"a.txt"}]' }]);
+  assert.equal(onChange.release(), true);
+  assert.equal(onChange('deleted'), false);
+  assert.throws(() => onChange(1n), { name: 'SerializationError' });
+});
+
+test('expose rejects calls to unknown methods through the native reply', () => {
+  const webview = exposedWebview();
+
+  Webview.prototype.expose.call(webview, 'native', { answer: () => 42 });
+  webview.callback({ ns: 'native', method: 'missing', id: 3, argsJson: '[]' });
+
+  assert.deepEqual(webview.replies, [
+    { id: 3, valueJson: undefined, error: { name: 'Error', message: 'No such method: missing' } },
+  ]);
+});
+
+test('expose releases the page functions of calls it rejects', () => {
+  const webview = exposedWebview();
+
+  Webview.prototype.expose.call(webview, 'native', { answer: () => 42 });
+  webview.callback({
+    ns: 'native',
+    method: 'missing',
+    id: 4,
+    argsJson: '[{"__webviewjsCallback":6}]',
+    callbacks: [6],
+  });
+  webview.callback({ ns: 'native', method: 'answer', id: 5, argsJson: '[', callbacks: [7] });
+
+  assert.deepEqual(webview.released, [6, 7]);
+  assert.deepEqual(webview.replies.map(({ error }) => error.name), ['Error', 'SerializationError']);
+});
+
+function evaluatingWebview() {
+  return {
+    handlers: [],
+    cancelled: [],
+    _evaluateInternal(script, handler) {
+      this.handlers.push({ script, handler });
+      return this.handlers.length;
+    },
+    _cancelEvaluation(id) {
+      this.cancelled.push(id);
+      return true;
+    },
+  };
+}
+
+test('evaluate resolves with decoded values and rejects with the page exception', async () => {
+  const webview = evaluatingWebview();
+
+  const value = Webview.prototype.evaluate.call(webview, 'fetch("/api").then((r) => r.json())');
+  const nothing = Webview.prototype.evaluate.call(webview, 'void 0');
+  const thrown = Webview.prototype.evaluate.call(webview, 'null.x');
+  const aborted = Webview.prototype.evaluate.call(webview, 'location.reload()');
+  const [first, second, third, fourth] = webview.handlers;
+  assert.equal(first.script, 'fetch("/api").then((r) => r.json())');
+  first.handler({ valueJson: '{"items":[1,2]}' });
+  second.handler({});
+  third.handler({
+    error: { name: 'TypeError', message: "Cannot read properties of null (reading 'x')", stack: 'TypeError: ...\n@1:5' },
+  });
+  fourth.handler({ aborted: 'The page navigated before the script completed' });
+
+  assert.deepEqual(await value, { items: [1, 2] });
+  assert.equal(await nothing, undefined);
+  await assert.rejects(thrown, (err) => {
+    assert.ok(err instanceof EvaluationError);
+    assert.equal(err.message, "Cannot read properties of null (reading 'x')");
+    assert.equal(err.pageName, 'TypeError');
+    assert.equal(err.pageStack, 'TypeError: ...\n@1:5');
+    return true;
+  });
+  await assert.rejects(aborted, { name: 'AbortError', message: 'The page navigated before the script completed' });
+  await assert.rejects(Webview.prototype.evaluate.call(webview, 42), TypeError);
+});
+
+test('evaluate stops waiting for the page after its timeout', async () => {
+  const webview = evaluatingWebview();
+
+  const pending = Webview.prototype.evaluate.call(webview, 'new Promise(() => {})', { timeout: 5 });
+  await assert.rejects(pending, { name: 'TimeoutError' });
+  assert.deepEqual(webview.cancelled, [1]);
+  await assert.rejects(Webview.prototype.evaluate.call(webview, '1', { timeout: -1 }), TypeError);
+});
+
+test('snapshot resolves with the PNG and rejects with the capture error', async () => {
+  const handlers = [];
+  const webview = {
+    _snapshotInternal(options, handler) {
+      handlers.push({ options, handler });
+    },
+  };
+
+  const captured = Webview.prototype.snapshot.call(webview, { rect: { x: 0, y: 0, width: 10, height: 10 }, scale: 0.5 });
+  const failed = Webview.prototype.snapshot.call(webview);
+  handlers[0].handler({ png: Buffer.from('png') });
+  handlers[1].handler({ error: 'Failed to capture the webview' });
+
+  assert.deepEqual(handlers[0].options, { rect: { x: 0, y: 0, width: 10, height: 10 }, scale: 0.5 });
+  assert.deepEqual(await captured, Buffer.from('png'));
+  await assert.rejects(failed, { message: 'Failed to capture the webview' });
+});
+
+test('printToPdf resolves with the PDF, or nothing once it was written to a file', async () => {
+  const handlers = [];
+  const webview = {
+    _printToPdfInternal(options, handler) {
+      handlers.push({ options, handler });
+    },
+  };
+
+  const buffered = Webview.prototype.printToPdf.call(webview, { pageSize: 'letter', printBackground: true });
+  await flush();
+  handlers[0].handler({ pdf: Buffer.from('%PDF-1.5') });
+  const written = Webview.prototype.printToPdf.call(webview, { path: 'invoice.pdf' });
+  await flush();
+  handlers[1].handler({});
+  const failed = Webview.prototype.printToPdf.call(webview);
+  await flush();
+  handlers[2].handler({ error: 'Failed to print: cancelled' });
+
+  assert.deepEqual(handlers[0].options, { pageSize: 'letter', printBackground: true });
+  assert.deepEqual(await buffered, Buffer.from('%PDF-1.5'));
+  assert.equal(await written, undefined);
+  await assert.rejects(failed, { message: 'Failed to print: cancelled' });
+});
+
+test('printToPdf prints one page at a time per webview', async () => {
+  const handlers = [];
+  const webview = {
+    _printToPdfInternal(options, handler) {
+      handlers.push({ options, handler });
+    },
+  };
+  const other = { _printToPdfInternal: webview._printToPdfInternal };
+
+  const failed = Webview.prototype.printToPdf.call(webview, { path: 'first.pdf' });
+  const second = Webview.prototype.printToPdf.call(webview, { path: 'second.pdf' });
+  const elsewhere = Webview.prototype.printToPdf.call(other, { path: 'other.pdf' });
+  await flush();
+  assert.deepEqual(handlers.map(({ options }) => options.path), ['first.pdf', 'other.pdf']);
+
+  handlers[0].handler({ error: 'Failed to print: cancelled' });
+  await assert.rejects(failed, { message: 'Failed to print: cancelled' });
+  await flush();
+  assert.equal(handlers[2].options.path, 'second.pdf');
+  handlers[2].handler({});
+  handlers[1].handler({});
+  assert.equal(await second, undefined);
+  assert.equal(await elsewhere, undefined);
+});
+
+function eventWebview() {
+  return {
+    emitted: [],
+    _emitInternal(event, payloadJson) {
+      this.emitted.push({ event, payloadJson });
+    },
+    _setPageEventHandler(handler) {
+      this.pageEventHandler = handler;
+    },
+  };
+}
+
+test('emitToPage serialises payloads before they reach the page', () => {
+  const webview = eventWebview();
+
+  Webview.prototype.emitToPage.call(webview, 'saved', { path: "');alert(1);//" });
+  Webview.prototype.emitToPage.call(webview, 'closed');
+  assert.deepEqual(webview.emitted, [
+    { event: 'saved', payloadJson: '{"path":"\');alert(1);//"}' },
+    { event: 'closed', payloadJson: undefined },
+  ]);
+
+  assert.throws(() => Webview.prototype.emitToPage.call(webview, 'bad', 1n), { name: 'SerializationError' });
+});
+
+test('broadcast serialises the payload once and returns the receipts', () => {
+  const calls = [];
+  const app = {
+    _broadcastInternal(event, payloadJson, options) {
+      calls.push({ event, payloadJson, options });
+      return [{ windowId: 1, webviewId: '2', url: 'app://localhost/' }];
+    },
+  };
+
+  const receipts = Application.prototype.broadcast.call(app, 'theme', { dark: true }, { windowIds: [1] });
+  Application.prototype.broadcast.call(app, 'ping');
+
+  assert.deepEqual(receipts, [{ windowId: 1, webviewId: '2', url: 'app://localhost/' }]);
+  assert.deepEqual(calls, [
+    { event: 'theme', payloadJson: '{"dark":true}', options: { windowIds: [1] } },
+    { event: 'ping', payloadJson: undefined, options: undefined },
+  ]);
+  assert.throws(() => Application.prototype.broadcast.call(app, 'bad', 1n), { name: 'SerializationError' });
+  assert.throws(() => Application.prototype.broadcast.call(app, 1), TypeError);
+});
+
+test('listen routes page events to named listeners until they are removed', () => {
+  const webview = eventWebview();
+  const received = [];
+
+  const unlisten = Webview.prototype.listen.call(webview, 'progress', (payload) => received.push(payload));
+  Webview.prototype.listen.call(webview, 'other', () => assert.fail('wrong event'));
+  webview.pageEventHandler({ event: 'progress', payloadJson: '{"done":1}' });
+  webview.pageEventHandler({ event: 'progress' });
+  unlisten();
+  webview.pageEventHandler({ event: 'progress', payloadJson: '2' });
+
+  assert.deepEqual(received, [{ done: 1 }, undefined]);
+});
+
+test('channel events decode relayed messages and report closing', () => {
+  const channel = {
+    _onChannelEvent(handler) {
+      this.handler = handler;
+    },
+  };
+  const events = [];
+  assert.equal(
+    WebviewChannel.prototype.on.call(channel, 'message', (message) => events.push(message)),
+    channel,
+  );
+  WebviewChannel.prototype.once.call(channel, 'close', () => events.push('close'));
+  channel.handler({ event: 'message', port: 1, dataJson: '{"text":"hi"}' });
+  channel.handler({ event: 'close' });
+
+  assert.deepEqual(events, [{ port: 1, data: { text: 'hi' } }, 'close']);
 });
 
 test('expose example uses an app protocol instead of a file origin for IPC', async () => {
@@ -0,0 +1,51 @@
+import assert from 'node:assert/strict';
+import { once } from 'node:events';
+import { test } from 'node:test';
+
+import webviewjs from '../index.js';
+
+const { Application } = webviewjs;
+
+// Find-in-page uses WebKitGTK's find controller, so this needs Linux and a display.
+const skip =
+  process.platform !== 'linux' || !(process.env.DISPLAY || process.env.WAYLAND_DISPLAY)
+    ? 'needs WebKitGTK and a display'
+    : false;
+
+async function found(webview, text, options) {
+  const event = once(webview, 'found-in-page');
+  webview.find(text, options);
+  const [payload] = await event;
+  return payload;
+}
+
+test('find counts the matches and reports them with found-in-page', { skip, timeout: 20_000 }, async () => {
+  const app = new Application();
+  try {
+    await app.whenReady();
+    const window = app.createBrowserWindow({ visible: false });
+    const webview = window.createWebview({ url: 'data:text/html,apple banana apple Apple' });
+    await once(webview, 'page-load-finished');
+
+    assert.equal(webview.findNext(), false);
+    assert.throws(() => webview.find(''), /must not be empty/);
+
+    const any = await found(webview, 'apple');
+    assert.equal(any.text, 'apple');
+    assert.equal(any.matches, 3);
+    assert.equal(any.count, undefined);
+    assert.equal(webview.findNext(), true);
+    assert.equal(webview.findPrevious(), true);
+
+    const exact = await found(webview, 'apple', { caseSensitive: true });
+    assert.equal(exact.matches, 2);
+
+    const none = await found(webview, 'pear');
+    assert.equal(none.text, 'pear');
+    assert.equal(none.matches, 0);
+
+    webview.stopFind();
+  } finally {
+    app.exit();
+  }
+});
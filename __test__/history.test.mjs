@@ -0,0 +1,54 @@
+import assert from 'node:assert/strict';
+import { once } from 'node:events';
+import { test } from 'node:test';
+
+import webviewjs from '../index.js';
+
+const { Application } = webviewjs;
+
+// Navigation history is read from WebKitGTK, so this needs Linux and a display.
+const skip =
+  process.platform !== 'linux' || !(process.env.DISPLAY || process.env.WAYLAND_DISPLAY)
+    ? 'needs WebKitGTK and a display'
+    : false;
+
+const page = (text) => `data:text/html,${text}`;
+
+async function historyChanged(webview, predicate) {
+  for (;;) {
+    const [event] = await once(webview, 'history-changed');
+    if (predicate(event)) return event;
+  }
+}
+
+test('navigating twice allows going back and reports history-changed', { skip, timeout: 20_000 }, async () => {
+  const app = new Application();
+  try {
+    await app.whenReady();
+    const window = app.createBrowserWindow({ visible: false });
+    const webview = window.createWebview({ url: page('first') });
+
+    const first = await historyChanged(webview, (event) => event.url === page('first'));
+    assert.equal(first.canGoBack, false);
+    assert.equal(first.canGoForward, false);
+    assert.equal(webview.canGoBack(), false);
+
+    webview.loadUrl(page('second'));
+    const second = await historyChanged(webview, (event) => event.url === page('second'));
+    assert.equal(second.canGoBack, true);
+    assert.equal(second.canGoForward, false);
+    assert.equal(webview.canGoBack(), true);
+    assert.equal(webview.canGoForward(), false);
+    const { entries, index } = webview.history();
+    assert.deepEqual(entries.map((entry) => entry.url), [page('first'), page('second')]);
+    assert.equal(index, 1);
+
+    assert.equal(webview.goBack(), true);
+    const back = await historyChanged(webview, (event) => event.url === page('first'));
+    assert.equal(back.canGoBack, false);
+    assert.equal(back.canGoForward, true);
+    assert.equal(webview.goBack(), false);
+  } finally {
+    app.exit();
+  }
+});
@@ -26,6 +26,9 @@ for (const name of [
   'download-completed',
   'navigation',
   'new-window',
+  'ipc-limit-exceeded',
+  'history-changed',
+  'found-in-page',
 ]) {
   webview.on(name, (event) => console.log(name, event));
 }
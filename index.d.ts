@@ -4,10 +4,61 @@ export class SerializationError extends Error {
   name: 'SerializationError';
 }
 
+/** Exception thrown in the page by a script run with `Webview.evaluate()`. */
+export class EvaluationError extends Error {
+  name: 'EvaluationError';
+  /** `Error.name` of the exception in the page. */
+  pageName: string;
+  /** `Error.stack` of the exception in the page, when it had one. */
+  pageStack?: string;
+}
+
+export interface EvaluateOptions {
+  /** Reject with a `TimeoutError` when the script has not completed after this many milliseconds. */
+  timeout?: number;
+}
+
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key: string]: JsonValue };
 
 export type ExposedTarget = Record<string, JsonValue | ((...args: any[]) => unknown | Promise<unknown>)>;
 
+/** A page function passed as an argument to an exposed function. */
+export interface PageCallback {
+  /**
+   * Run the page function with JSON arguments.  Returns `false` once the
+   * callback is released or its page has navigated away.
+   */
+  (...args: JsonValue[]): boolean;
+  /** Let the page drop the function.  Returns `false` if already released. */
+  release(): boolean;
+}
+
+export interface ExposeOptions {
+  /**
+   * Origin or URL patterns of the pages that get the namespace and may call
+   * it.  Defaults to the webview's `ipcOrigins`.
+   */
+  origins?: string[];
+}
+
+/** A protocol response whose body is produced incrementally. */
+export interface StreamedProtocolResponse extends Omit<import('./js-bindings').CustomProtocolResponse, 'body'> {
+  body: AsyncIterable<string | Uint8Array>;
+}
+
+export type ProtocolHandlerResult =
+  | Response
+  | import('./js-bindings').CustomProtocolResponse
+  | import('./js-bindings').ProtocolFileResponse
+  | StreamedProtocolResponse;
+
+export type ProtocolRequestHandler = (request: Request) => ProtocolHandlerResult | Promise<ProtocolHandlerResult>;
+
+/** A route of `registerProtocolRouter()` whose handler is a JS function. */
+export type ProtocolRouterRoute =
+  | (Omit<import('./js-bindings').ProtocolRoute, 'handler'> & { handler?: undefined })
+  | (Pick<import('./js-bindings').ProtocolRoute, 'host' | 'path'> & { handler: ProtocolRequestHandler });
+
 export type EventListener<TPayload> = (payload: TPayload) => void;
 
 /** Shared EventEmitter surface with payloads inferred from an event map. */
@@ -117,6 +168,17 @@ export interface TrayEventMap {
   leave: import('./js-bindings').TrayEventPayload;
 }
 
+export interface WebviewChannelMessageEvent {
+  /** 0 when the first webview posted the message, 1 for the second. */
+  port: number;
+  data: any;
+}
+
+export interface WebviewChannelEventMap {
+  message: WebviewChannelMessageEvent;
+  close: void;
+}
+
 export type ApplicationWhenReadyOptions =
   | {
       autoRun?: true;
@@ -160,6 +222,31 @@ export interface WebviewNewWindowEvent {
   url?: string;
 }
 
+export interface WebviewIpcLimitEvent {
+  event: number;
+  /** URL of the page that sent the last discarded message. */
+  url?: string;
+  reason: 'size' | 'rate';
+  /** Messages discarded since the last event for the same limit. */
+  count: number;
+}
+
+export interface WebviewHistoryChangedEvent {
+  event: number;
+  /** URL of the current entry. */
+  url?: string;
+  canGoBack: boolean;
+  canGoForward: boolean;
+}
+
+export interface WebviewFoundInPageEvent {
+  event: number;
+  /** Search text passed to `find()`. */
+  text: string;
+  /** Number of matches in the page. */
+  matches: number;
+}
+
 /** Maps Webview event names to their typed payloads. */
 export interface WebviewEventMap {
   'page-load-started': WebviewPageLoadEvent;
@@ -175,6 +262,18 @@ export interface WebviewEventMap {
    * separate WebView2 thread.
    */
   'new-window': WebviewNewWindowEvent;
+  /** Fired when page messages run into the webview's `ipcLimits`. */
+  'ipc-limit-exceeded': WebviewIpcLimitEvent;
+  /**
+   * Fired when the back-forward list or the current entry changes.  Only
+   * fired on Linux (WebKitGTK).
+   */
+  'history-changed': WebviewHistoryChangedEvent;
+  /**
+   * Fired when the matches of a `find()` search were counted.  Only fired on
+   * Linux (WebKitGTK).
+   */
+  'found-in-page': WebviewFoundInPageEvent;
 }
 
 export interface WindowMoveEvent {
@@ -276,6 +375,17 @@ declare module './js-bindings' {
     [Symbol.dispose](): void;
 
     whenReady(options?: ApplicationWhenReadyOptions): Promise<void>;
+
+    /**
+     * Dispatch a JSON payload to the `window.webviewjs.listen(event)` callbacks
+     * of every live webview, or of those `options` select.  Returns the
+     * webviews that received it.
+     */
+    broadcast(
+      event: string,
+      payload?: unknown,
+      options?: import('./js-bindings').BroadcastOptions,
+    ): import('./js-bindings').BroadcastReceipt[];
   }
 
   interface WebviewOptions {
@@ -303,6 +413,12 @@ declare module './js-bindings' {
      * global `Response` (compatible with Hono, itty-router, and any other
      * Fetch-API framework), or a legacy `CustomProtocolResponse` plain object.
      *
+     * Return a `ProtocolFileResponse` (`{ path }`) to have a file on disk
+     * served natively, with `Range` support, instead of reading it in JS.
+     *
+     * With `options.timeout`, requests left unanswered for that many
+     * milliseconds are answered with `504 Gateway Timeout`.
+     *
      * @example
      * ```ts
      * // With Hono:
@@ -315,19 +431,70 @@ declare module './js-bindings' {
      * });
      * ```
      */
-    registerProtocol(
-      name: string,
-      handler: (request: Request) => Response | CustomProtocolResponse | Promise<Response | CustomProtocolResponse>,
-    ): void;
+    registerProtocol(name: string, handler: ProtocolRequestHandler, options?: ProtocolOptions): void;
+
+    /**
+     * Register several handlers on one custom protocol, matched by host and
+     * path in order.  Directory, archive, proxy and redirect routes are
+     * answered natively; only routes with a `handler` call into JS.  Requests
+     * no route matches are answered with `404 Not Found`.
+     *
+     * @example
+     * ```ts
+     * win.registerProtocolRouter('app', [
+     *   { path: '/api', handler: (req) => honoApp.fetch(req) },
+     *   { path: '/old/*', redirect: '/new/*', status: 301 },
+     *   { path: '/', directory: { root: './dist' } },
+     * ]);
+     * ```
+     */
+    registerProtocolRouter(name: string, routes: ProtocolRouterRoute[], options?: ProtocolOptions): void;
   }
 
   interface Webview extends TypedEventEmitter<WebviewEventMap> {
     [Symbol.dispose](): void;
 
-    expose(name: string, target: ExposedTarget): void;
+    expose(name: string, target: ExposedTarget, options?: ExposeOptions): void;
+
+    /** Remove an exposed namespace.  Returns `false` if it was not exposed. */
+    unexpose(name: string): boolean;
+
+    /**
+     * Run `script` in the page and resolve with its JSON value, awaiting a
+     * returned promise.  Rejects with an `EvaluationError` when the script
+     * throws or its promise rejects.
+     */
+    evaluate<T = any>(script: string, options?: EvaluateOptions): Promise<T>;
+
+    /**
+     * Capture the viewport, or `options.rect` of it, as PNG.  Only supported
+     * on Linux (WebKitGTK).
+     */
+    snapshot(options?: import('./js-bindings').SnapshotOptions): Promise<Buffer>;
+
+    /**
+     * Print the page to PDF without a dialog.  Resolves with the PDF, or with
+     * `undefined` once it was written to `options.path`.  Only supported on
+     * Linux (WebKitGTK).
+     */
+    printToPdf(options: import('./js-bindings').PdfOptions & { path: string }): Promise<undefined>;
+    printToPdf(options?: Omit<import('./js-bindings').PdfOptions, 'path'>): Promise<Buffer>;
+
+    /** Dispatch a JSON payload to the page's `window.webviewjs.listen(event)` callbacks. */
+    emitToPage(event: string, payload?: unknown): void;
+
+    /**
+     * Listen for `window.webviewjs.emit(event, payload)` calls from the page.
+     * Returns a function that removes the listener.
+     */
+    listen(event: string, listener: (payload: any) => void): () => void;
   }
 
   interface WebContext {
     [Symbol.dispose](): void;
   }
+
+  interface WebviewChannel extends TypedEventEmitter<WebviewChannelEventMap> {
+    [Symbol.dispose](): void;
+  }
 }
@@ -360,6 +360,47 @@ function _getTrayEmitter(tray) {
   };
 });
 
+// ── WebviewChannel EventEmitter ──────────────────────────────────────────────
+// 'message' receives { port, data } for every relayed message, where port is 0
+// when the first webview posted it and 1 for the second; 'close' fires once.
+const _channelEmitters = new WeakMap();
+
+function _getChannelEmitter(channel) {
+  if (!_channelEmitters.has(channel)) {
+    const emitter = new EventEmitter();
+    _channelEmitters.set(channel, emitter);
+    channel._onChannelEvent(function ({ event, port, dataJson }) {
+      if (event === 'message') emitter.emit('message', { port, data: JSON.parse(dataJson) });
+      else emitter.emit(event);
+    });
+  }
+  return _channelEmitters.get(channel);
+}
+
+[
+  'on',
+  'once',
+  'off',
+  'addListener',
+  'removeListener',
+  'removeAllListeners',
+  'listenerCount',
+  'listeners',
+  'rawListeners',
+  'emit',
+  'eventNames',
+].forEach((method) => {
+  nativeBinding.WebviewChannel.prototype[method] = function (...args) {
+    const emitter = _getChannelEmitter(this);
+    const result = emitter[method](...args);
+    return result === emitter ? this : result;
+  };
+});
+
+nativeBinding.WebviewChannel.prototype[Symbol.dispose] = function dispose() {
+  this.close();
+};
+
 // ── Application EventEmitter ─────────────────────────────────────────────────
 // Maps WebviewApplicationEvent numeric values (from Rust enum order) to names.
 const _applicationEventNames = [
@@ -491,38 +532,66 @@ function _getWindowEmitter(win) {
 });
 
 // ── BrowserWindow.registerProtocol ───────────────────────────────────────────
-// Wraps the low-level `_registerProtocol(name, (payloadJson) => void)` native
+// Wraps the low-level `_registerProtocol(name, (request) => void)` native
 // API with a clean async handler: `(request: Request) => Promise<Response>`.
+// The native side hands over a `CustomProtocolRequest` whose body is a real
+// Buffer, so uploads are never JSON-encoded on the way in.
 // The handler receives a global `Request` object and should return a global
-// `Response` (or a legacy `CustomProtocolResponse` plain object for compat).
+// `Response` (or a legacy `CustomProtocolResponse` plain object for compat),
+// or a `ProtocolFileResponse` naming a file for the native side to serve.
 // This allows frameworks like Hono to be used directly:
 //   win.registerProtocol('app', (req) => honoApp.fetch(req));
-nativeBinding.BrowserWindow.prototype.registerProtocol = function registerProtocol(name, asyncHandler) {
-  const win = this;
-  win._registerProtocol(name, function (payloadJson) {
-    let parsed;
-    try {
-      parsed = JSON.parse(payloadJson);
-    } catch {
-      return;
+nativeBinding.BrowserWindow.prototype.registerProtocol = function registerProtocol(name, asyncHandler, options) {
+  this._registerProtocol(name, createProtocolRequestHandler(this, asyncHandler), options);
+};
+
+// ── BrowserWindow.registerProtocolRouter ─────────────────────────────────────
+// Routes with a `handler` function are numbered and handed to the native
+// router by index; every other route is answered natively without calling JS.
+//   win.registerProtocolRouter('app', [
+//     { path: '/api', handler: (req) => api.fetch(req) },
+//     { path: '/', directory: { root: './dist' } },
+//   ]);
+nativeBinding.BrowserWindow.prototype.registerProtocolRouter = function registerProtocolRouter(
+  name,
+  routes,
+  options,
+) {
+  const handlers = [];
+  const nativeRoutes = routes.map(({ handler, ...route }) => {
+    if (handler == null) return route;
+    if (typeof handler !== 'function') {
+      throw new TypeError(`Route ${route.path} of protocol ${name}: handler must be a function`);
     }
-    const { id, url, method, headers: rawHeaders, body: bodyArr } = parsed;
+    handlers.push(handler);
+    return { ...route, handler: handlers.length - 1 };
+  });
+  const dispatch = (request, { route }) => handlers[route](request);
+  this._registerProtocolRouter(name, nativeRoutes, createProtocolRequestHandler(this, dispatch), options);
+};
+
+// Builds the native callback of a JS-handled scheme: turns the native request
+// into a global `Request`, calls `asyncHandler(request, nativeRequest)` and
+// answers with whatever it resolves to.
+function createProtocolRequestHandler(win, asyncHandler) {
+  return function onRequest(nativeRequest) {
+    const { id, url, method, headers: rawHeaders, body } = nativeRequest;
 
     // Build a global Headers object
     const headersObj = new Headers();
     for (const { key, value } of rawHeaders ?? []) {
-      if (value != null) headersObj.set(key, value);
+      if (value != null) headersObj.append(key, value);
     }
 
     // Build a global Request — GET/HEAD cannot carry a body
     const canHaveBody = !['GET', 'HEAD'].includes(method.toUpperCase());
     const reqInit = { method, headers: headersObj };
-    if (canHaveBody && Array.isArray(bodyArr) && bodyArr.length > 0) {
-      reqInit.body = Buffer.from(bodyArr);
+    if (canHaveBody && body != null && body.length > 0) {
+      reqInit.body = body;
     }
     const request = new Request(url, reqInit);
 
-    Promise.resolve(asyncHandler(request))
+    Promise.resolve(asyncHandler(request, nativeRequest))
       .then(async (resp) => {
         // Accept a global Response object (from Hono / fetch-compatible handlers)
         if (typeof Response !== 'undefined' && resp instanceof Response) {
@@ -539,6 +608,10 @@ nativeBinding.BrowserWindow.prototype.registerProtocol = function registerProtoc
             headers: extraHeaders,
           });
         }
+        // File response: the native side reads the file, honouring Range requests
+        if (resp != null && typeof resp.path === 'string' && resp.body == null) {
+          return win._completeProtocolFile(id, resp);
+        }
         // Legacy CustomProtocolResponse plain object
         return win._completeProtocol(id, resp);
       })
@@ -549,8 +622,8 @@ nativeBinding.BrowserWindow.prototype.registerProtocol = function registerProtoc
           mimeType: 'text/plain',
         }),
       );
-  });
-};
+  };
+}
 
 // ── Webview EventEmitter ──────────────────────────────────────────────────────
 // Maps WebviewEventType numeric values (Rust enum order) to JS event names.
@@ -562,6 +635,9 @@ const _webviewEventNames = [
   'download-completed', // 4  DownloadCompleted
   'navigation', // 5  NavigationStarted
   'new-window', // 6  NewWindowRequested
+  'ipc-limit-exceeded', // 7  IpcLimitExceeded
+  'history-changed', // 8  HistoryChanged
+  'found-in-page', // 9  FoundInPage
 ];
 
 const _webviewEmitters = new WeakMap();
@@ -645,12 +721,30 @@ function jsonValue(value, context) {
 }
 
 function sendExposeError(webview, id, message, name = 'Error') {
-  webview.evaluateScript(
-    `window.__webviewjs__&&window.__webviewjs__.reject(${Number(id)},${JSON.stringify(String(message))},${JSON.stringify(name)})`,
-  );
+  webview._exposeReply(id, undefined, { name, message: String(message) });
+}
+
+// A page function passed as an argument. Calling it runs the page function
+// with JSON arguments and returns `false` once it is released or its page is
+// gone; `release()` lets the page drop it.
+function pageCallback(webview, id) {
+  const callback = (...args) => webview._invokeCallback(id, jsonValue(args, 'Callback arguments'));
+  callback.release = () => webview._releaseCallback(id);
+  return callback;
+}
+
+function isAsyncIterable(value) {
+  return value != null && typeof value[Symbol.asyncIterator] === 'function';
+}
+
+// Ends an iterator early, e.g. destroying a Readable. Errors are ignored.
+function closeIterator(iterator) {
+  Promise.resolve()
+    .then(() => iterator.return?.())
+    .catch(() => {});
 }
 
-nativeBinding.Webview.prototype.expose = function expose(name, target) {
+nativeBinding.Webview.prototype.expose = function expose(name, target, options = {}) {
   const self = this;
   if (!/^[A-Za-z_$][\w$]*$/u.test(name)) {
     throw new TypeError('expose(): name must be a valid JavaScript identifier');
@@ -658,6 +752,10 @@ nativeBinding.Webview.prototype.expose = function expose(name, target) {
   if (target === null || (typeof target !== 'object' && typeof target !== 'function')) {
     throw new TypeError('expose(): target must be an object');
   }
+  const { origins } = options ?? {};
+  if (origins !== undefined && (!Array.isArray(origins) || origins.some((origin) => typeof origin !== 'string'))) {
+    throw new TypeError('expose(): origins must be an array of strings');
+  }
 
   const namespaces = _exposedNamespaces.get(self) ?? new Set();
   if (namespaces.has(name)) {
@@ -677,30 +775,59 @@ nativeBinding.Webview.prototype.expose = function expose(name, target) {
     }
   }
 
-  self._exposeInternal(name, jsonValue(statics, 'expose(): static properties'), [...functions.keys()], function (call) {
-    const { ns: _ns, method, id, argsJson } = call;
+  // Iterators of the streams returned to the page, by native stream id.
+  const iterators = new Map();
+
+  function reviveCallback(_key, value) {
+    const isCallback =
+      value !== null && typeof value === 'object' && Object.keys(value).length === 1 && '__webviewjsCallback' in value;
+    return isCallback ? pageCallback(self, value.__webviewjsCallback) : value;
+  }
+
+  function handleCall(call) {
+    const { ns: _ns, method, id, argsJson, stream, callbacks } = call;
+    if (stream != null) {
+      handleStreamCall(method, id, stream);
+      return;
+    }
+    // Rejected calls never hand their page functions to `fn`, so the page
+    // can drop them.
+    const releaseCallbacks = () => callbacks?.forEach((callback) => self._releaseCallback(callback));
     const fn = functions.get(method);
     if (fn === undefined) {
+      releaseCallbacks();
       sendExposeError(self, id, `No such method: ${method}`);
       return;
     }
 
     let args;
     try {
-      args = JSON.parse(argsJson);
+      args = JSON.parse(argsJson, callbacks?.length ? reviveCallback : undefined);
     } catch {
+      releaseCallbacks();
       sendExposeError(self, id, 'Argument parse error', 'SerializationError');
       return;
     }
 
     Promise.resolve(fn.apply(target, args))
       .then((result) => {
+        // Async iterables, including Node streams, become async iterators in
+        // the page, which pulls one item per `next()`.
+        if (isAsyncIterable(result)) {
+          const iterator = result[Symbol.asyncIterator]();
+          const stream = self._exposeStream(id);
+          if (stream == null) closeIterator(iterator);
+          else iterators.set(stream, iterator);
+          return;
+        }
+        let resultJson;
         try {
-          const resultJson = jsonValue(result, 'Return value');
-          self.evaluateScript(`window.__webviewjs__&&window.__webviewjs__.resolve(${Number(id)},${resultJson})`);
+          resultJson = jsonValue(result, 'Return value');
         } catch {
           sendExposeError(self, id, 'Return value is not JSON-serialisable', 'SerializationError');
+          return;
         }
+        self._exposeReply(id, resultJson);
       })
       .catch((err) => {
         sendExposeError(
@@ -710,14 +837,216 @@ nativeBinding.Webview.prototype.expose = function expose(name, target) {
           err?.name === 'SerializationError' ? 'SerializationError' : 'Error',
         );
       });
-  });
+  }
+
+  function handleStreamCall(method, id, stream) {
+    const iterator = iterators.get(stream);
+    if (method === 'return') {
+      iterators.delete(stream);
+      if (iterator !== undefined) closeIterator(iterator);
+      return;
+    }
+    if (iterator === undefined) {
+      sendExposeError(self, id, 'The stream is closed');
+      return;
+    }
+
+    Promise.resolve(iterator.next())
+      .then(({ done, value }) => {
+        if (done) {
+          iterators.delete(stream);
+          self._exposeReply(id, '{"done":true}');
+          return;
+        }
+        let itemJson;
+        try {
+          itemJson = `{"done":false,"value":${jsonValue(value, 'Stream item')}}`;
+        } catch {
+          iterators.delete(stream);
+          closeIterator(iterator);
+          sendExposeError(self, id, 'Stream item is not JSON-serialisable', 'SerializationError');
+          return;
+        }
+        self._exposeReply(id, itemJson);
+      })
+      .catch((err) => {
+        iterators.delete(stream);
+        sendExposeError(
+          self,
+          id,
+          String(err?.message ?? err),
+          err?.name === 'SerializationError' ? 'SerializationError' : 'Error',
+        );
+      });
+  }
+
+  const staticsJson = jsonValue(statics, 'expose(): static properties');
+  self._exposeInternal(name, staticsJson, [...functions.keys()], handleCall, origins);
 
   namespaces.add(name);
   _exposedNamespaces.set(self, namespaces);
 };
 
+// Removes `window[name]` from the page, now and after later navigations.
+// Pending page calls on the namespace are rejected with an AbortError.
+nativeBinding.Webview.prototype.unexpose = function unexpose(name) {
+  _exposedNamespaces.get(this)?.delete(name);
+  return this._unexposeInternal(name);
+};
+
+// ── Webview.evaluate ──────────────────────────────────────────────────────────
+// Runs a script in the page and resolves with its JSON value, awaiting a
+// returned promise. Rejects with an EvaluationError carrying the page's
+// exception, a TimeoutError after `timeout` ms, or an AbortError when the
+// page navigates or the webview is disposed first.
+class EvaluationError extends Error {
+  constructor({ name, message, stack }) {
+    super(message);
+    this.name = 'EvaluationError';
+    this.pageName = name;
+    this.pageStack = stack;
+  }
+}
+
+nativeBinding.Webview.prototype.evaluate = function evaluate(script, options = {}) {
+  if (typeof script !== 'string') {
+    return Promise.reject(new TypeError('evaluate(): script must be a string'));
+  }
+  const { timeout } = options ?? {};
+  if (timeout !== undefined && !(typeof timeout === 'number' && timeout > 0 && Number.isFinite(timeout))) {
+    return Promise.reject(new TypeError('evaluate(): timeout must be a positive number of milliseconds'));
+  }
+
+  const self = this;
+  return new Promise((resolve, reject) => {
+    let timer;
+    const id = self._evaluateInternal(script, function ({ valueJson, error, aborted }) {
+      clearTimeout(timer);
+      if (aborted != null) {
+        const err = new Error(aborted);
+        err.name = 'AbortError';
+        reject(err);
+      } else if (error != null) {
+        reject(new EvaluationError(error));
+      } else {
+        try {
+          resolve(valueJson == null ? undefined : JSON.parse(valueJson));
+        } catch {
+          reject(new SerializationError('Result is not valid JSON'));
+        }
+      }
+    });
+    if (timeout !== undefined) {
+      timer = setTimeout(() => {
+        self._cancelEvaluation(id);
+        const err = new Error(`evaluate(): the script did not complete within ${timeout} ms`);
+        err.name = 'TimeoutError';
+        reject(err);
+      }, timeout);
+    }
+  });
+};
+
+// ── Webview.snapshot ──────────────────────────────────────────────────────────
+// Resolves with a PNG Buffer of the viewport, or of `options.rect` in it.
+// Only supported with WebKitGTK; elsewhere the promise rejects.
+nativeBinding.Webview.prototype.snapshot = function snapshot(options) {
+  return new Promise((resolve, reject) => {
+    this._snapshotInternal(options, function ({ png, error }) {
+      if (error != null) reject(new Error(error));
+      else resolve(png);
+    });
+  });
+};
+
+// ── Webview.printToPdf ────────────────────────────────────────────────────────
+// Prints the page to PDF without a dialog. Resolves with a Buffer, or with
+// undefined once the PDF was written to `options.path`. Only supported with
+// WebKitGTK; elsewhere the promise rejects. A webview prints one page at a
+// time, so each call waits for the previous one to settle.
+const _pdfPrints = new WeakMap();
+
+nativeBinding.Webview.prototype.printToPdf = function printToPdf(options) {
+  const previous = _pdfPrints.get(this) ?? Promise.resolve();
+  const print = previous.then(
+    () =>
+      new Promise((resolve, reject) => {
+        this._printToPdfInternal(options, function ({ pdf, error }) {
+          if (error != null) reject(new Error(error));
+          else resolve(pdf ?? undefined);
+        });
+      }),
+  );
+  _pdfPrints.set(this, print.catch(() => {}));
+  return print;
+};
+
+// ── Webview events ────────────────────────────────────────────────────────────
+// Named events between Node and the page, separate from raw IPC messages and
+// exposed calls. Payloads are JSON; the page side is
+// `window.webviewjs.emit(event, payload)` / `window.webviewjs.listen(event, cb)`.
+const _pageEventListeners = new WeakMap();
+
+nativeBinding.Webview.prototype.emitToPage = function emitToPage(event, payload) {
+  if (typeof event !== 'string') {
+    throw new TypeError('emitToPage(): event must be a string');
+  }
+  this._emitInternal(event, payload === undefined ? undefined : jsonValue(payload, 'emitToPage(): payload'));
+};
+
+// Returns a function that removes the listener.
+nativeBinding.Webview.prototype.listen = function listen(event, listener) {
+  if (typeof event !== 'string') {
+    throw new TypeError('listen(): event must be a string');
+  }
+  if (typeof listener !== 'function') {
+    throw new TypeError('listen(): listener must be a function');
+  }
+
+  let listeners = _pageEventListeners.get(this);
+  if (listeners === undefined) {
+    listeners = new Map();
+    _pageEventListeners.set(this, listeners);
+    this._setPageEventHandler(function ({ event, payloadJson }) {
+      const registered = listeners.get(event);
+      if (registered === undefined) return;
+      const payload = payloadJson == null ? undefined : JSON.parse(payloadJson);
+      for (const fn of [...registered]) {
+        try {
+          fn(payload);
+        } catch (err) {
+          queueMicrotask(() => {
+            throw err;
+          });
+        }
+      }
+    });
+  }
+
+  const registered = listeners.get(event) ?? new Set();
+  registered.add(listener);
+  listeners.set(event, registered);
+  return function unlisten() {
+    if (registered.delete(listener) && registered.size === 0 && listeners.get(event) === registered) {
+      listeners.delete(event);
+    }
+  };
+};
+
+// ── Application.broadcast ─────────────────────────────────────────────────────
+// Emits a named event to every live webview, like webview.emitToPage(), and
+// returns { windowId, webviewId, url } for each webview that received it.
+nativeBinding.Application.prototype.broadcast = function broadcast(event, payload, options) {
+  if (typeof event !== 'string') {
+    throw new TypeError('broadcast(): event must be a string');
+  }
+  const payloadJson = payload === undefined ? undefined : jsonValue(payload, 'broadcast(): payload');
+  return this._broadcastInternal(event, payloadJson, options);
+};
+
 module.exports = nativeBinding;
 module.exports.SerializationError = SerializationError;
+module.exports.EvaluationError = EvaluationError;
 module.exports.Notification = Notification;
 
 // Auto-generated exports by postbuild.js. Do not edit directly.
@@ -731,6 +1060,8 @@ module.exports.WebContext = nativeBinding.WebContext;
 module.exports.JsWebContext = nativeBinding.JsWebContext;
 module.exports.Webview = nativeBinding.Webview;
 module.exports.JsWebview = nativeBinding.JsWebview;
+module.exports.WebviewChannel = nativeBinding.WebviewChannel;
+module.exports.JsWebviewChannel = nativeBinding.JsWebviewChannel;
 module.exports.applyUriWorkAround = nativeBinding.applyUriWorkAround;
 module.exports.ControlFlow = nativeBinding.ControlFlow;
 module.exports.JsControlFlow = nativeBinding.JsControlFlow;
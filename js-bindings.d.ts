@@ -6,6 +6,19 @@ export declare class Application {
   bind(handler?: ((arg: ApplicationEvent) => void) | undefined | null): void;
   isReady(): boolean;
   exit(): void;
+  /**
+   * Low-level method used by the JS `broadcast()` wrapper.  Dispatches
+   * `event` to the `window.webviewjs.listen()` callbacks of every live
+   * webview `options` select, and returns those webviews.  `payload_json`
+   * must be valid JSON.
+   */
+  _broadcastInternal(
+    event: string,
+    payloadJson?: string | undefined | null,
+    options?: BroadcastOptions | undefined | null,
+  ): Array<BroadcastReceipt>;
+  /** Creates a channel relaying messages between the pages of two webviews. */
+  createChannel(): JsWebviewChannel;
   /** Creates a new WebContext with the given options. */
   createWebContext(options?: WebContextOptions | undefined | null): JsWebContext;
   createTrayIcon(options: TrayIconOptions): JsTrayIcon;
@@ -30,8 +43,45 @@ export declare class Application {
 }
 
 export declare class BrowserWindow {
-  _registerProtocol(name: string, handler: (arg: string) => void): void;
+  _registerProtocol(name: string, handler: (arg: CustomProtocolRequest) => void, options?: ProtocolOptions | undefined | null): void;
   _completeProtocol(id: number, response: CustomProtocolResponse): void;
+  /**
+   * Answer a pending request with a file, read and range-sliced natively on
+   * a worker thread.
+   */
+  _completeProtocolFile(id: number, response: ProtocolFileResponse): void;
+  /** List the custom-protocol requests still waiting for a JS response. */
+  pendingProtocolRequests(): Array<ProtocolRequestInfo>;
+  /**
+   * Answer an in-flight custom-protocol request with an error status
+   * (default `503`).  Returns `false` if the request is no longer pending.
+   */
+  cancelProtocolRequest(id: number, statusCode?: number | undefined | null): boolean;
+  /**
+   * Serve a custom protocol natively from a local directory.
+   *
+   * Requests are answered in Rust without a round-trip through JS.  Must be
+   * called before `createWebview()`.
+   */
+  registerStaticProtocol(name: string, options: StaticProtocolOptions, protocolOptions?: ProtocolOptions | undefined | null): void;
+  /**
+   * Serve a custom protocol natively from a zip, tar or tar.gz archive,
+   * loaded from a path or a `Buffer`.
+   *
+   * The archive is decompressed into memory once; nothing is unpacked to
+   * disk.  Must be called before `createWebview()`.
+   */
+  registerArchiveProtocol(name: string, options: ArchiveProtocolOptions, protocolOptions?: ProtocolOptions | undefined | null): void;
+  /**
+   * Forward a custom protocol to a local HTTP server, e.g. a dev server,
+   * so the page keeps its production origin during development.
+   *
+   * Method, headers and body are passed through and the response mapped
+   * back; redirects to the upstream are rewritten to the scheme.  Must be
+   * called before `createWebview()`.
+   */
+  registerProxyProtocol(name: string, options: ProxyProtocolOptions, protocolOptions?: ProtocolOptions | undefined | null): void;
+  _registerProtocolRouter(name: string, routes: Array<ProtocolRoute>, handler: (arg: CustomProtocolRequest) => void, options?: ProtocolOptions | undefined | null): void;
   createWebview(options?: WebviewOptions | undefined | null, webContext?: JsWebContext | undefined | null): JsWebview;
   _setPendingWebviewEventCallback(handler: (err: Error | null, arg: WebviewEventPayload) => any): void;
   _setPendingWebviewNavigationHandler(handler: (arg: string) => boolean): void;
@@ -183,23 +233,76 @@ export declare class Webview {
   onIpcMessage(handler?: ((arg: IpcMessage) => void) | undefined | null): void;
   dispose(): void;
   isDisposed(): boolean;
+  /** Identifier of the webview, unique within the application. */
+  id(): string;
   /**
    * Low-level method used by the JS `expose()` wrapper.
    *
-   * Injects a page script that creates `window[name]` as an object with:
+   * Creates `window[name]` in the page as an object with:
    * - static values from `statics_json` (a JSON object string)
    * - async function stubs for each name in `func_names`
    *
-   * When the page calls one of the stubs the call is routed back here via
-   * the internal IPC channel and dispatched to `handler`.  `handler` is
-   * responsible for calling `evaluateScript` to send the response.
+   * The namespace is installed again in every document the webview loads
+   * whose URL matches `origins`, which defaults to the webview's
+   * `ipcOrigins`.  When the page calls one of the stubs the call is routed
+   * back here via the internal IPC channel and dispatched to `handler`,
+   * which answers it with `_exposeReply`.
    */
   _exposeInternal(
     name: string,
     staticsJson: string,
     funcNames: Array<string>,
     handler: (arg: ExposeCallData) => void,
+    origins?: Array<string> | undefined | null,
   ): void;
+  /**
+   * Low-level method used by the JS `unexpose()` wrapper.  Deletes
+   * `window[name]` and rejects its pending calls; returns `false` when the
+   * namespace was not exposed.
+   */
+  _unexposeInternal(name: string): boolean;
+  /**
+   * Send a message to the page, dispatched as a `message` event on
+   * `window.webviewjs`.  Strings arrive as strings and binary data as an
+   * `ArrayBuffer`; messages arrive in the order they were sent.
+   */
+  postMessage(data: string | Uint8Array): void;
+  /**
+   * Answer exposed call `id` with a JSON value, or reject it with `error`.
+   *
+   * Returns `false` when the call is no longer pending, e.g. because the
+   * page navigated away.  Invalid `value_json` rejects the call with a
+   * `SerializationError` and throws.
+   */
+  _exposeReply(id: number, valueJson?: string | undefined | null, error?: ExposeCallError | undefined | null): boolean;
+  /**
+   * Answer exposed call `id` with an async iterator in the page.  Returns
+   * the id of the stream, which the page pulls from with `next` calls, or
+   * `null` when the call is no longer pending.
+   */
+  _exposeStream(id: number): number | null;
+  /**
+   * Run a page function passed to an exposed function, with `args_json`
+   * as its arguments.  Returns `false` once it was released or its page
+   * navigated away.
+   */
+  _invokeCallback(id: number, argsJson: string): boolean;
+  /**
+   * Let the page drop a function passed to an exposed function.  Returns
+   * `false` when it was already released.
+   */
+  _releaseCallback(id: number): boolean;
+  /**
+   * Low-level method used by the JS `emitToPage()` wrapper.  Dispatches
+   * `event` to the page's `window.webviewjs.listen()` callbacks, in order
+   * with messages.  `payload_json` must be valid JSON.
+   */
+  _emitInternal(event: string, payloadJson?: string | undefined | null): void;
+  /**
+   * Low-level method used by the JS `listen()` wrapper.  `handler`
+   * receives every event emitted with `window.webviewjs.emit()`.
+   */
+  _setPageEventHandler(handler: (arg: PageEventData) => void): void;
   print(): void;
   zoom(scaleFactor: number): void;
   setWebviewVisibility(visible: boolean): void;
@@ -210,9 +313,37 @@ export declare class Webview {
   loadHtml(html: string): void;
   evaluateScript(js: string): void;
   evaluateScriptWithCallback(js: string, callback: (err: Error | null, arg: string) => any): void;
+  /**
+   * Low-level method used by the JS `evaluate()` wrapper.  Runs `js` in
+   * the page and hands `handler` its JSON value, once any promise it
+   * returned settled, or the exception it ended with.  Returns the id of
+   * the evaluation.
+   */
+  _evaluateInternal(js: string, handler: (arg: EvaluationData) => void): number;
+  /**
+   * Stop waiting for evaluation `id`, e.g. after a timeout.  The script
+   * keeps running.  Returns `false` when it already ended.
+   */
+  _cancelEvaluation(id: number): boolean;
   reload(): void;
   /** The URL the webview is currently showing. */
   url(): string | null;
+  /** Go back one entry in the history.  Returns `false` when there is none. */
+  goBack(): boolean;
+  /**
+   * Go forward one entry in the history.  Returns `false` when there is
+   * none.
+   */
+  goForward(): boolean;
+  canGoBack(): boolean;
+  canGoForward(): boolean;
+  /** Stop loading the current page. */
+  stop(): void;
+  /**
+   * The back-forward list.  Only supported on Linux (WebKitGTK), like the
+   * other history methods.
+   */
+  history(): WebviewHistory;
   /** Webview width in logical pixels (same coordinate space as `set_bounds`). */
   get width(): number | null;
   /** Webview height in logical pixels (same coordinate space as `set_bounds`). */
@@ -223,6 +354,17 @@ export declare class Webview {
   get y(): number | null;
   /** Load `url` with additional HTTP request headers. */
   loadUrlWithHeaders(url: string, headers: Array<HeaderData>): void;
+  /**
+   * Highlight the matches of `text` and select the first one.  A
+   * `found-in-page` event reports their number.
+   */
+  find(text: string, options?: FindOptions | undefined | null): void;
+  /** Select the next match.  Returns `false` when no search is active. */
+  findNext(): boolean;
+  /** Select the previous match.  Returns `false` when no search is active. */
+  findPrevious(): boolean;
+  /** End the search and remove its highlights. */
+  stopFind(): void;
   /**
    * Return all cookies currently stored for `url`, or every cookie if `url`
    * is `null` / `undefined`.
@@ -253,9 +395,33 @@ export declare class Webview {
   focus(): void;
   /** Return focus to the parent/host window. */
   focusParent(): void;
+  /**
+   * Low-level method used by the JS `snapshot()` wrapper.  Captures the
+   * viewport, or `options.rect` of it, and hands `handler` the PNG.  Only
+   * WebKitGTK can capture; elsewhere this throws.
+   */
+  _snapshotInternal(options: SnapshotOptions | undefined | null, handler: (arg: SnapshotData) => void): void;
+  /**
+   * Low-level method used by the JS `printToPdf()` wrapper.  Prints the
+   * page without a dialog and hands `handler` the PDF, or nothing once it
+   * was written to `options.path`.  Only WebKitGTK can print to PDF;
+   * elsewhere this throws.
+   */
+  _printToPdfInternal(options: PdfOptions | undefined | null, handler: (arg: PdfData) => void): void;
 }
 export type JsWebview = Webview;
 
+export declare class WebviewChannel {
+  constructor();
+  /** Hand one port of the channel to each webview, both named `name`. */
+  connect(first: Webview, second: Webview, name: string): void;
+  /** Close the channel.  Both pages' ports receive a `close` event. */
+  close(): void;
+  isClosed(): boolean;
+  _onChannelEvent(handler?: ((arg: ChannelEventData) => void) | undefined | null): void;
+}
+export type JsWebviewChannel = WebviewChannel;
+
 export interface AndroidContentRect {
   left: number;
   top: number;
@@ -284,6 +450,50 @@ export interface ApplicationRunOptions {
 /** Converting `{protocol}://localhost/abc` to `{http_or_https}://{protocol}.localhost/abc` */
 export declare function applyUriWorkAround(uri: string, httpOrHttps: string, protocol: string): string;
 
+/** Options for `BrowserWindow.registerArchiveProtocol()`. */
+export interface ArchiveProtocolOptions {
+  /**
+   * Path of a `.zip`, `.tar` or `.tar.gz` file.  Exactly one of `path` and
+   * `data` must be set.
+   */
+  path?: string;
+  /** Archive contents, e.g. embedded in a single-file executable. */
+  data?: Buffer;
+  /**
+   * Directory inside the archive to serve, e.g. `"dist"`.  Defaults to the
+   * archive root.
+   */
+  root?: string;
+  /** File served for directory requests.  Defaults to `"index.html"`. */
+  index?: string;
+  /**
+   * Serve the index file for extension-less paths that do not exist, so
+   * client-side routers work.  Defaults to `true`.
+   */
+  spaFallback?: boolean;
+  /** Headers added to every response (e.g. a `Content-Security-Policy`). */
+  headers?: Array<HeaderData>;
+}
+
+/**
+ * Which webviews `Application.broadcast()` reaches.  Every live webview
+ * when both are omitted.
+ */
+export interface BroadcastOptions {
+  /** Only webviews of these windows, by `BrowserWindow.id()`. */
+  windowIds?: Array<number>;
+  /** Only pages matching one of these origin patterns, as in `ipcOrigins`. */
+  origins?: Array<string>;
+}
+
+/** A webview that received a broadcast event. */
+export interface BroadcastReceipt {
+  windowId: number;
+  webviewId: string;
+  /** URL of the page the event was dispatched to. */
+  url?: string;
+}
+
 export interface BrowserWindowOptions {
   menu?: MenuOptions;
   showMenu?: boolean;
@@ -329,6 +539,16 @@ export interface BrowserWindowOptions {
   iosPrefersStatusBarHidden?: boolean;
 }
 
+/** A message relayed by a `WebviewChannel`, or its closing. */
+export interface ChannelEventData {
+  /** `"message"` or `"close"`. */
+  event: string;
+  /** Which webview posted a message: 0 for the first, 1 for the second. */
+  port?: number;
+  /** JSON data of a message. */
+  dataJson?: string;
+}
+
 /** Kept for backward compat; no longer used internally. */
 export declare enum ControlFlow {
   Poll = 0,
@@ -384,10 +604,17 @@ export interface CustomMenuEvent {
 
 /** Incoming request delivered to a custom-protocol handler. */
 export interface CustomProtocolRequest {
+  /** Identifier passed back to `_completeProtocol`. */
+  id: number;
   url: string;
   method: string;
   headers: Array<HeaderData>;
   body?: Buffer;
+  /**
+   * Index of the matched JS route, for schemes registered with
+   * `registerProtocolRouter()`.
+   */
+  route?: number;
 }
 
 /** Response returned by a custom-protocol handler. */
@@ -407,12 +634,52 @@ export interface Dimensions {
   height: number;
 }
 
+/** How a script run with `Webview._evaluateInternal()` ended. */
+export interface EvaluationData {
+  /**
+   * JSON of the value it completed with; absent for `undefined` and
+   * errors.
+   */
+  valueJson?: string;
+  /** Set when it threw or its promise rejected. */
+  error?: EvaluationError;
+  /** Set when the page navigated or the webview was disposed first. */
+  aborted?: string;
+}
+
+/** An exception thrown in the page. */
+export interface EvaluationError {
+  /** `Error.name` in the page. */
+  name: string;
+  message: string;
+  /** `Error.stack` in the page, when there is one. */
+  stack?: string;
+}
+
 /** Data sent to the expose handler when the page calls a proxied function. */
 export interface ExposeCallData {
   ns: string;
   method: string;
+  /** Native call id, passed back to `Webview._exposeReply()`. */
   id: number;
   argsJson: string;
+  /**
+   * Set for `next` and `return` calls on a stream opened with
+   * `Webview._exposeStream()`; a `return` call needs no reply.
+   */
+  stream?: number;
+  /**
+   * Ids of the page functions in `args_json`, for
+   * `Webview._invokeCallback()`.
+   */
+  callbacks?: Array<number>;
+}
+
+/** Error an exposed call is rejected with in the page. */
+export interface ExposeCallError {
+  /** `Error.name` in the page.  Defaults to `"Error"`. */
+  name?: string;
+  message: string;
 }
 
 export interface FileDialogOptions {
@@ -427,6 +694,17 @@ export interface FileFilter {
   extensions: Array<string>;
 }
 
+/** Options of `Webview.find()`. */
+export interface FindOptions {
+  /** Defaults to `false`. */
+  caseSensitive?: boolean;
+  /**
+   * Continue from the other end of the page after the last match.
+   * Defaults to `true`.
+   */
+  wrap?: boolean;
+}
+
 export declare enum FullscreenType {
   Exclusive = 0,
   Borderless = 1,
@@ -439,17 +717,46 @@ export interface HeaderData {
   value?: string;
 }
 
+/** An entry of a webview's back-forward list. */
+export interface HistoryEntry {
+  url: string;
+  title?: string;
+}
+
 export declare enum IosValidOrientations {
   LandscapeAndPortrait = 0,
   Landscape = 1,
   Portrait = 2,
 }
 
+/** Limits on the IPC messages a webview's pages send to Node. */
+export interface IpcLimitOptions {
+  /** Largest message accepted, in bytes. */
+  maxMessageSize?: number;
+  /**
+   * Messages delivered per second.  Bursts of up to this many messages are
+   * delivered at once.
+   */
+  maxMessagesPerSecond?: number;
+  /** Messages over the rate that wait for delivery (default: 0). */
+  maxQueuedMessages?: number;
+  /**
+   * `"drop"` discards the oldest queued message when the queue is full,
+   * `"reject"` the new one (default: `"drop"`).
+   */
+  overflow?: string;
+}
+
 export interface IpcMessage {
   body: Buffer;
   method: string;
   headers: Array<HeaderData>;
   uri: string;
+  /**
+   * Whether the page posted binary data with `webviewjs.postMessage()`
+   * rather than a string.
+   */
+  binary: boolean;
 }
 
 /** If the URI is a work around URI for this protocol which starts with `{http_or_https}://{protocol}.` */
@@ -507,6 +814,69 @@ export interface NotificationEventPayload {
 /** Returns `{protocol}://` */
 export declare function originalUriPrefix(protocol: string): string;
 
+/** An event emitted by the page with `window.webviewjs.emit()`. */
+export interface PageEventData {
+  event: string;
+  /** JSON payload; absent when the page emitted `undefined`. */
+  payloadJson?: string;
+}
+
+/** How a `Webview._printToPdfInternal()` print ended. */
+export interface PdfData {
+  /** The PDF, unless it was written to `path`. */
+  pdf?: Buffer;
+  error?: string;
+}
+
+/**
+ * Page margins of `Webview.printToPdf()`, in millimetres.  Each defaults
+ * to 10.
+ */
+export interface PdfMargins {
+  top?: number;
+  right?: number;
+  bottom?: number;
+  left?: number;
+}
+
+/** Options of `Webview.printToPdf()`. */
+export interface PdfOptions {
+  /** File to write the PDF to.  Without it the PDF is returned. */
+  path?: string;
+  /**
+   * `"a3"`, `"a4"`, `"a5"`, `"letter"`, `"legal"` or `"tabloid"`.
+   * Defaults to `"a4"`.
+   */
+  pageSize?: string;
+  /** Paper width in millimetres, instead of `page_size`. */
+  pageWidth?: number;
+  /** Paper height in millimetres, instead of `page_size`. */
+  pageHeight?: number;
+  margins?: PdfMargins;
+  landscape?: boolean;
+  /** Between 0.1 and 2.  Defaults to 1. */
+  scale?: number;
+  /** Print background colours and images.  Defaults to `false`. */
+  printBackground?: boolean;
+  /**
+   * HTML repeated at the top of every page.  Elements with the class
+   * `title`, `url` or `date` are filled in.
+   */
+  headerTemplate?: string;
+  /** HTML repeated at the bottom of every page, like `header_template`. */
+  footerTemplate?: string;
+  /**
+   * Height of the header in millimetres, added to the top margin.
+   * Defaults to 10 with a `header_template`.
+   */
+  headerHeight?: number;
+  /**
+   * Height of the footer in millimetres, added to the bottom margin.
+   * Defaults to 10 with a `footer_template`.
+   */
+  footerHeight?: number;
+}
+
 export interface Position {
   x: number;
   y: number;
@@ -520,9 +890,180 @@ export declare enum ProgressBarState {
   Error = 4,
 }
 
+/** Cross-origin access rules of a custom protocol. */
+export interface ProtocolCorsOptions {
+  /**
+   * Allowed origins, e.g. `"app://localhost"` or `"http://app.localhost"`,
+   * or `"*"` for any origin.
+   */
+  origins: Array<string>;
+  /**
+   * Allowed methods.  Defaults to `GET`, `HEAD`, `POST`, `PUT`, `PATCH` and
+   * `DELETE`.
+   */
+  methods?: Array<string>;
+  /** Allowed request headers.  Defaults to the headers a preflight asks for. */
+  allowedHeaders?: Array<string>;
+  /** Response headers readable by the page. */
+  exposedHeaders?: Array<string>;
+  /** Allow cookies and other credentials.  Defaults to `false`. */
+  credentials?: boolean;
+  /** Seconds a preflight answer may be cached. */
+  maxAge?: number;
+}
+
+/**
+ * A custom-protocol response read from disk by the native side, so large
+ * files never pass through JS.
+ */
+export interface ProtocolFileResponse {
+  /** File to serve. */
+  path: string;
+  /** First byte of the file to serve.  Defaults to 0. */
+  offset?: number;
+  /**
+   * Number of bytes to serve from `offset`.  Defaults to the rest of the
+   * file.
+   */
+  length?: number;
+  /**
+   * HTTP status code.  Defaults to 200, which also answers `Range` and
+   * `If-None-Match` requests.
+   */
+  statusCode?: number;
+  /** Extra response headers. */
+  headers?: Array<HeaderData>;
+  /** MIME type.  Defaults to one derived from the file extension. */
+  mimeType?: string;
+}
+
+/**
+ * Security headers applied to every response of a custom protocol.  Headers
+ * set by the handler itself take precedence.
+ */
+export interface ProtocolHeaderPolicy {
+  /**
+   * Allow cross-origin requests; `OPTIONS` preflights are answered
+   * automatically.
+   */
+  cors?: ProtocolCorsOptions;
+  /** `Content-Security-Policy` header value. */
+  contentSecurityPolicy?: string;
+  /** `Cross-Origin-Opener-Policy` header value, e.g. `"same-origin"`. */
+  crossOriginOpenerPolicy?: string;
+  /** `Cross-Origin-Embedder-Policy` header value, e.g. `"require-corp"`. */
+  crossOriginEmbedderPolicy?: string;
+  /** `Cross-Origin-Resource-Policy` header value, e.g. `"cross-origin"`. */
+  crossOriginResourcePolicy?: string;
+  /**
+   * Shorthand for `same-origin` COOP and `require-corp` COEP, which make
+   * `SharedArrayBuffer` available to the page.
+   */
+  crossOriginIsolated?: boolean;
+}
+
+/** Options shared by the `BrowserWindow.register*Protocol()` methods. */
+export interface ProtocolOptions {
+  /**
+   * Milliseconds a request may stay unanswered by JS before it is answered
+   * with `504 Gateway Timeout`.  No timeout by default.
+   */
+  timeout?: number;
+  /** Headers added to every response of the scheme. */
+  policy?: ProtocolHeaderPolicy;
+}
+
+/**
+ * An in-flight custom-protocol request, as listed by
+ * `BrowserWindow.pendingProtocolRequests()`.
+ */
+export interface ProtocolRequestInfo {
+  id: number;
+  scheme: string;
+  url: string;
+  method: string;
+  /** Milliseconds since the request reached the handler. */
+  elapsedMs: number;
+}
+
+/**
+ * A route of `BrowserWindow.registerProtocolRouter()`.  Exactly one of
+ * `handler`, `directory`, `archive`, `proxy` and `redirect` must be set.
+ */
+export interface ProtocolRoute {
+  /** Host to match, e.g. `"localhost"`.  Matches any host by default. */
+  host?: string;
+  /** Path pattern, e.g. `"/api"`, `"/assets/*"` or `"/*.png"`. */
+  path: string;
+  /** Index of the JS handler, assigned by the JS wrapper. */
+  handler?: number;
+  /** Serve a local directory below the route prefix. */
+  directory?: StaticProtocolOptions;
+  /** Serve an archive below the route prefix. */
+  archive?: ArchiveProtocolOptions;
+  /** Forward requests below the route prefix to a local HTTP server. */
+  proxy?: ProxyProtocolOptions;
+  /** Redirect target.  A trailing `*` is replaced by the rest of the path. */
+  redirect?: string;
+  /** Redirect status code.  Defaults to 302. */
+  status?: number;
+}
+
+/** Options for `BrowserWindow.registerProxyProtocol()`. */
+export interface ProxyProtocolOptions {
+  /**
+   * Local HTTP server to forward to, e.g. `"http://127.0.0.1:5173"`.  A path
+   * is prepended to every forwarded path.
+   */
+  upstream: string;
+  /** Headers added to every forwarded request, replacing the page's. */
+  headers?: Array<HeaderData>;
+  /**
+   * Milliseconds to wait for the upstream to connect and for each read.
+   * Defaults to 30 seconds.
+   */
+  timeout?: number;
+}
+
 /** Converting `{http_or_https}://{protocol}.localhost/abc` back to `{protocol}://localhost/abc` */
 export declare function revertUriWorkAround(uri: string, httpOrHttps: string, protocol: string): string;
 
+/** How a `Webview._snapshotInternal()` capture ended. */
+export interface SnapshotData {
+  /** The PNG image. */
+  png?: Buffer;
+  error?: string;
+}
+
+/** Options of `Webview.snapshot()`. */
+export interface SnapshotOptions {
+  /**
+   * Area to capture, in logical pixels relative to the viewport.  Defaults
+   * to the whole visible viewport.
+   */
+  rect?: WebviewBounds;
+  /**
+   * Factor to scale the image down by, greater than 0 and at most 1.
+   * Defaults to 1, one image pixel per device pixel.
+   */
+  scale?: number;
+}
+
+/** Options for `BrowserWindow.registerStaticProtocol()`. */
+export interface StaticProtocolOptions {
+  /** Directory to serve.  Requests can never resolve outside of it. */
+  root: string;
+  /** File served for directory requests.  Defaults to `"index.html"`. */
+  index?: string;
+  /**
+   * Serve the index file for extension-less paths that do not exist, so
+   * client-side routers work.  Defaults to `true`.
+   */
+  spaFallback?: boolean;
+  /** Headers added to every response (e.g. a `Content-Security-Policy`). */
+  headers?: Array<HeaderData>;
+}
+
 export declare enum Theme {
   Light = 0,
   Dark = 1,
@@ -619,6 +1160,21 @@ export interface WebviewEventPayload {
   title?: string;
   /** Download success flag for `DownloadCompleted` events. */
   success?: boolean;
+  /** Limit hit by `IpcLimitExceeded` events: `"size"` or `"rate"`. */
+  reason?: string;
+  /**
+   * Messages discarded since the last `IpcLimitExceeded` event for the same
+   * limit.
+   */
+  count?: number;
+  /** Whether there is an entry to go back to, for `HistoryChanged` events. */
+  canGoBack?: boolean;
+  /** Whether there is an entry to go forward to, for `HistoryChanged` events. */
+  canGoForward?: boolean;
+  /** Search text for `FoundInPage` events. */
+  text?: string;
+  /** Matches of the search for `FoundInPage` events. */
+  matches?: number;
 }
 
 /** Event types fired by a Webview and surfaced as EventEmitter events in JS. */
@@ -634,6 +1190,22 @@ export declare enum WebviewEventType {
    * (`window.open`, `target="_blank"`, etc.).
    */
   NewWindowRequested = 6,
+  /** Fired when page messages run into the webview's `ipcLimits`. */
+  IpcLimitExceeded = 7,
+  /** Fired when the back-forward list or the current entry changes. */
+  HistoryChanged = 8,
+  /** Fired when the matches of a `find()` search were counted. */
+  FoundInPage = 9,
+}
+
+/** A webview's back-forward list, oldest entry first. */
+export interface WebviewHistory {
+  entries: Array<HistoryEntry>;
+  /**
+   * Position of the current entry in `entries`, or -1 before the first
+   * page loaded.
+   */
+  index: number;
 }
 
 export interface WebviewOptions {
@@ -656,6 +1228,17 @@ export interface WebviewOptions {
   backForwardNavigationGestures?: boolean;
   /** Custom name for the IPC global injected by wry (default: `"ipc"`). */
   ipcName?: string;
+  /**
+   * Origin or URL patterns of the pages whose IPC messages and events reach
+   * Node, e.g. `["app://localhost", "https://*.example.com"]`.  Also the
+   * default for `expose()`.  Every page is allowed when omitted.
+   */
+  ipcOrigins?: Array<string>;
+  /**
+   * Size and rate limits on IPC messages, reported with `IpcLimitExceeded`
+   * events.  Unlimited when omitted.
+   */
+  ipcLimits?: IpcLimitOptions;
   /** Whether to automatically normalize URL passed to `loadUrl` or `loadUrlWithHeaders` on windows (default: true). */
   autoNormalizeLoadUrl?: boolean;
   /**
@@ -755,6 +755,8 @@ module.exports.WebContext = nativeBinding.WebContext;
 module.exports.JsWebContext = nativeBinding.JsWebContext;
 module.exports.Webview = nativeBinding.Webview;
 module.exports.JsWebview = nativeBinding.JsWebview;
+module.exports.WebviewChannel = nativeBinding.WebviewChannel;
+module.exports.JsWebviewChannel = nativeBinding.JsWebviewChannel;
 module.exports.applyUriWorkAround = nativeBinding.applyUriWorkAround;
 module.exports.ControlFlow = nativeBinding.ControlFlow;
 module.exports.JsControlFlow = nativeBinding.JsControlFlow;
@@ -2,15 +2,21 @@ use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use crate::browser_window::BrowserWindow;
+use crate::channel::{ChannelState, JsWebviewChannel};
+use crate::ipc_limits::{drain_queues, IpcQueues};
+use crate::origins::OriginPolicy;
+use crate::protocol::{expire_requests, fail_requests};
 #[cfg(target_os = "android")]
 use crate::tray::JsTrayIcon;
 #[cfg(not(any(target_os = "android", target_os = "freebsd")))]
 use crate::tray::{event_payload, JsTrayIcon, TrayEventHandler, TrayResource};
 use crate::types::*;
 use crate::web_context::{JsWebContext, WebContextOptions, WebContextResource};
-use crate::webview::WebviewResource;
+use crate::webview::{PageBridges, ProtocolPendingMap, WebviewResource};
+use crate::workers::WorkerPool;
 #[cfg(all(not(target_os = "android"), not(target_os = "freebsd")))]
 use muda::Menu;
 use napi::bindgen_prelude::*;
@@ -22,6 +28,7 @@ use tao::{
   keyboard::{Key, KeyCode, ModifiersState},
   window::{Window, WindowId},
 };
+use wry::http::StatusCode;
 
 #[napi]
 pub fn get_webview_version() -> Result<String> {
@@ -63,10 +70,16 @@ struct AppState {
   /// WM_SIZE without forwarding to wry's subclass proc, so we resize manually
   /// when WindowEvent::Resized arrives.
   webviews: HashMap<WindowId, Rc<RefCell<Vec<WebviewResource>>>>,
+  /// Each window's id and the page bridges of its webviews, for broadcasts.
+  page_bridges: HashMap<WindowId, (u32, PageBridges)>,
   /// Per-window event handlers shared with each BrowserWindow instance.
   window_handlers: HashMap<WindowId, WindowEventHandler>,
   window_lifecycles: HashMap<WindowId, Rc<Cell<bool>>>,
   webview_lifecycles: HashMap<WindowId, WebviewLifecycles>,
+  /// In-flight custom-protocol requests per window, swept for timeouts.
+  protocol_requests: HashMap<WindowId, ProtocolPendingMap>,
+  /// Page messages held back by `ipcLimits` per window, drained every tick.
+  ipc_queues: HashMap<WindowId, IpcQueues>,
   /// Last known physical cursor position per window (for edge-resize hit testing).
   cursor_positions: HashMap<WindowId, (f64, f64)>,
   /// Last known modifier state.
@@ -78,6 +91,8 @@ struct AppState {
   #[cfg(not(any(target_os = "android", target_os = "freebsd")))]
   tray_resources: Vec<TrayResource>,
   web_contexts: Vec<WebContextResource>,
+  /// Channels between webviews, closed on exit.
+  channels: Vec<Rc<ChannelState>>,
 }
 
 impl AppState {
@@ -92,6 +107,18 @@ impl AppState {
       }
       self.tray_handlers.clear();
     }
+    // Answer in-flight protocol requests while their webviews still exist.
+    for requests in self.protocol_requests.values() {
+      fail_requests(requests, StatusCode::SERVICE_UNAVAILABLE, |_| true);
+    }
+    self.protocol_requests.clear();
+    for queues in self.ipc_queues.values() {
+      for queue in queues.borrow_mut().drain(..) {
+        queue.close();
+      }
+    }
+    self.ipc_queues.clear();
+    self.page_bridges.clear();
     for views in self.webviews.values() {
       for resource in views.borrow().iter() {
         if let Some(view) = resource.borrow_mut().take() {
@@ -123,6 +150,9 @@ impl AppState {
     for context in self.web_contexts.drain(..) {
       context.borrow_mut().take();
     }
+    for channel in self.channels.drain(..) {
+      channel.close();
+    }
     self.handler.borrow_mut().take();
     #[cfg(not(target_os = "android"))]
     {
@@ -131,6 +161,48 @@ impl AppState {
     self.should_exit = true;
   }
 
+  /// Forget a closed window: answer its in-flight protocol requests with
+  /// 503, discard its queued page messages and stop broadcasting to its
+  /// webviews.
+  fn close_window(&mut self, window_id: WindowId) {
+    if let Some(win) = self.windows.remove(&window_id) {
+      win.set_visible(false);
+    }
+    self.cursor_positions.remove(&window_id);
+    if let Some(requests) = self.protocol_requests.remove(&window_id) {
+      fail_requests(&requests, StatusCode::SERVICE_UNAVAILABLE, |_| true);
+    }
+    if let Some(queues) = self.ipc_queues.remove(&window_id) {
+      for queue in queues.borrow_mut().drain(..) {
+        queue.close();
+      }
+    }
+    self.page_bridges.remove(&window_id);
+  }
+
+  /// Answer timed-out protocol requests with 504 and return the earliest
+  /// deadline still pending, if any.
+  fn expire_protocol_requests(&self) -> Option<Instant> {
+    let now = Instant::now();
+    self
+      .protocol_requests
+      .values()
+      .filter_map(|requests| expire_requests(requests, now))
+      .min()
+  }
+
+  /// Deliver the page messages `ipcLimits` let through by now, and return
+  /// the earliest time more are due, if any.
+  fn drain_ipc_queues(&self) -> Option<Instant> {
+    let now = Instant::now();
+    // Cloned so delivery can open and close windows.
+    let queues: Vec<IpcQueues> = self.ipc_queues.values().cloned().collect();
+    queues
+      .iter()
+      .filter_map(|queues| drain_queues(queues, now))
+      .min()
+  }
+
   fn fire(&self, event: ApplicationEvent) {
     dispatch_reentrant(
       &self.handler,
@@ -281,10 +353,7 @@ fn handle_window_event(state: &mut AppState, window_id: WindowId, event: WindowE
           phase: None,
         },
       );
-      if let Some(win) = state.windows.remove(&window_id) {
-        win.set_visible(false);
-      }
-      state.cursor_positions.remove(&window_id);
+      state.close_window(window_id);
       state.fire(ApplicationEvent {
         event: WebviewApplicationEvent::WindowCloseRequested,
         custom_menu_event: None,
@@ -698,6 +767,8 @@ pub struct Application {
   #[cfg(not(target_os = "android"))]
   global_menu: Rc<RefCell<Option<Menu>>>,
   window_ids: Arc<Mutex<HashMap<String, u32>>>,
+  /// Threads answering the native protocol requests of every window.
+  workers: Arc<WorkerPool>,
 }
 
 #[napi]
@@ -734,6 +805,9 @@ impl Application {
         window_handlers: HashMap::new(),
         window_lifecycles: HashMap::new(),
         webview_lifecycles: HashMap::new(),
+        protocol_requests: HashMap::new(),
+        ipc_queues: HashMap::new(),
+        page_bridges: HashMap::new(),
         cursor_positions: HashMap::new(),
         current_modifiers: ModifiersState::default(),
         #[cfg(not(target_os = "android"))]
@@ -754,10 +828,12 @@ impl Application {
         #[cfg(not(any(target_os = "android", target_os = "freebsd")))]
         tray_resources: Vec::new(),
         web_contexts: Vec::new(),
+        channels: Vec::new(),
       },
       #[cfg(not(target_os = "android"))]
       global_menu: Rc::new(RefCell::new(initial_global_menu)),
       window_ids: Arc::new(Mutex::new(HashMap::new())),
+      workers: Arc::new(WorkerPool::new()),
     })
   }
 
@@ -786,6 +862,79 @@ impl Application {
     }
   }
 
+  /// Low-level method used by the JS `broadcast()` wrapper.  Dispatches
+  /// `event` to the `window.webviewjs.listen()` callbacks of every live
+  /// webview `options` select, and returns those webviews.  `payload_json`
+  /// must be valid JSON.
+  #[napi(js_name = "_broadcastInternal")]
+  pub fn broadcast_internal(
+    &self,
+    event: String,
+    payload_json: Option<String>,
+    options: Option<BroadcastOptions>,
+  ) -> Result<Vec<BroadcastReceipt>> {
+    let invalid = |e: String| napi::Error::new(napi::Status::InvalidArg, e);
+    if let Some(json) = &payload_json {
+      serde_json::from_str::<serde_json::Value>(json)
+        .map_err(|e| invalid(format!("Invalid event payload: {}", e)))?;
+    }
+    let (window_ids, origins) = match options {
+      Some(options) => (options.window_ids, options.origins),
+      None => (None, None),
+    };
+    let origins = origins
+      .map(|origins| OriginPolicy::new(Some(&origins)))
+      .transpose()
+      .map_err(invalid)?;
+
+    let mut windows: Vec<_> = self
+      .state
+      .page_bridges
+      .values()
+      .filter(|(id, _)| window_ids.as_ref().is_none_or(|ids| ids.contains(id)))
+      .map(|(id, bridges)| (*id, bridges.borrow().clone()))
+      .collect();
+    windows.sort_by_key(|(id, _)| *id);
+
+    let mut receipts = Vec::new();
+    for (window_id, bridges) in windows {
+      for (webview_id, bridge) in bridges {
+        if !bridge.is_attached() {
+          continue;
+        }
+        let url = bridge.current_url();
+        let allowed = match (&origins, &url) {
+          (None, _) => true,
+          (Some(origins), Some(url)) => origins.allows(url),
+          (Some(_), None) => false,
+        };
+        if allowed && bridge.emit(&event, payload_json.as_deref()).is_ok() {
+          receipts.push(BroadcastReceipt {
+            window_id,
+            webview_id,
+            url,
+          });
+        }
+      }
+    }
+    Ok(receipts)
+  }
+
+  /// Creates a channel relaying messages between the pages of two webviews.
+  #[napi]
+  pub fn create_channel(&mut self, env: Env) -> Result<JsWebviewChannel> {
+    if self.state.should_exit {
+      return Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        "Application has been disposed",
+      ));
+    }
+    let channel = JsWebviewChannel::create(env);
+    self.state.channels.retain(|channel| !channel.is_closed());
+    self.state.channels.push(channel.state());
+    Ok(channel)
+  }
+
   #[napi]
   /// Creates a new WebContext with the given options.
   pub fn create_web_context(&mut self, options: Option<WebContextOptions>) -> Result<JsWebContext> {
@@ -852,6 +1001,7 @@ impl Application {
       Some(window_options),
       false,
       self.global_menu.clone(),
+      Arc::clone(&self.workers),
     )?;
     #[cfg(target_os = "android")]
     let window = BrowserWindow::new(
@@ -859,6 +1009,7 @@ impl Application {
       Some(window_options),
       false,
       Rc::new(RefCell::new(None)),
+      Arc::clone(&self.workers),
     )?;
 
     if let Ok(mut ids) = self.window_ids.lock() {
@@ -882,6 +1033,18 @@ impl Application {
       .state
       .webview_lifecycles
       .insert(wid, window.webview_lifecycles_shared());
+    self
+      .state
+      .protocol_requests
+      .insert(wid, window.protocol_requests_shared());
+    self
+      .state
+      .ipc_queues
+      .insert(wid, window.ipc_queues_shared());
+    self
+      .state
+      .page_bridges
+      .insert(wid, (window.id(), window.page_bridges_shared()));
 
     Ok(window)
   }
@@ -905,9 +1068,21 @@ impl Application {
     })?;
 
     #[cfg(not(target_os = "android"))]
-    let window = BrowserWindow::new(event_loop, options, true, self.global_menu.clone())?;
+    let window = BrowserWindow::new(
+      event_loop,
+      options,
+      true,
+      self.global_menu.clone(),
+      Arc::clone(&self.workers),
+    )?;
     #[cfg(target_os = "android")]
-    let window = BrowserWindow::new(event_loop, options, true, Rc::new(RefCell::new(None)))?;
+    let window = BrowserWindow::new(
+      event_loop,
+      options,
+      true,
+      Rc::new(RefCell::new(None)),
+      Arc::clone(&self.workers),
+    )?;
 
     let wid = window.tao_window_id();
     self.state.windows.insert(wid, Arc::clone(&window.window));
@@ -924,6 +1099,18 @@ impl Application {
       .state
       .webview_lifecycles
       .insert(wid, window.webview_lifecycles_shared());
+    self
+      .state
+      .protocol_requests
+      .insert(wid, window.protocol_requests_shared());
+    self
+      .state
+      .ipc_queues
+      .insert(wid, window.ipc_queues_shared());
+    self
+      .state
+      .page_bridges
+      .insert(wid, (window.id(), window.page_bridges_shared()));
 
     Ok(window)
   }
@@ -1025,6 +1212,9 @@ impl Application {
       return false;
     }
 
+    self.state.expire_protocol_requests();
+    self.state.drain_ipc_queues();
+
     // Split borrows so the event handler can mutate application state.
     let event_loop = match &mut self.event_loop {
       Some(event_loop) => event_loop,
@@ -1134,7 +1324,16 @@ impl Application {
     let state = &mut self.state;
 
     event_loop.run_return(|event, _target, control_flow| {
-      *control_flow = ControlFlow::Wait;
+      // Wake up in time to answer protocol requests that run out of time,
+      // and to deliver page messages held back by `ipcLimits`.
+      let deadline = [state.expire_protocol_requests(), state.drain_ipc_queues()]
+        .into_iter()
+        .flatten()
+        .min();
+      *control_flow = match deadline {
+        Some(deadline) => ControlFlow::WaitUntil(deadline),
+        None => ControlFlow::Wait,
+      };
 
       if let Event::WindowEvent {
         window_id,
@@ -0,0 +1,1140 @@
+//! Native asset serving for custom protocols.
+//!
+//! An [`AssetSite`] answers `GET`/`HEAD` requests from an [`AssetSource`]:
+//! a directory on disk or an in-memory zip/tar archive.  Both share the same
+//! path validation, MIME types, conditional and range requests,
+//! pre-compressed variants and SPA fallback.
+//!
+//! A [`FileResponse`] serves a single file named by a JS handler the same way.
+
+use std::{
+  borrow::Cow,
+  collections::{hash_map::DefaultHasher, HashMap, HashSet},
+  fs::File,
+  hash::{Hash, Hasher},
+  io::{Cursor, Read, Seek, SeekFrom},
+  path::{Component, Path, PathBuf},
+  sync::Arc,
+  time::UNIX_EPOCH,
+};
+
+use wry::http::{header, Method, Request, Response, StatusCode};
+
+use crate::protocol::{text_response, NativeProtocol, ProtocolResponse};
+use crate::types::HeaderData;
+
+// ── Sites ─────────────────────────────────────────────────────────────────────
+
+/// Where an [`AssetSite`] reads its files from.
+pub(crate) trait AssetSource: Send + Sync {
+  /// Open the file at `path` (validated, `/`-separated, no leading slash).
+  /// Returns `Ok(None)` when there is no such file.
+  fn open(&self, path: &str) -> std::result::Result<Option<Asset>, StatusCode>;
+  /// Whether `path` names a directory; `""` is the root.
+  fn is_dir(&self, path: &str) -> bool;
+  /// Most bytes a gzip-compressed file is decoded to for clients that do not
+  /// accept gzip.
+  fn max_decoded_size(&self) -> u64 {
+    MAX_DECODED_SIZE
+  }
+}
+
+/// A file ready to be served.
+pub(crate) struct Asset {
+  len: u64,
+  etag: String,
+  body: AssetBody,
+}
+
+enum AssetBody {
+  /// The asset starts `offset` bytes into the file.
+  File {
+    file: File,
+    offset: u64,
+  },
+  Memory(Arc<[u8]>),
+}
+
+impl Asset {
+  fn memory(bytes: Arc<[u8]>, etag: String) -> Self {
+    Self {
+      len: bytes.len() as u64,
+      etag,
+      body: AssetBody::Memory(bytes),
+    }
+  }
+
+  /// Open `length` bytes of the file at `path` from `offset`, or the rest
+  /// of the file when `length` is `None`.
+  fn file(path: &Path, offset: u64, length: Option<u64>) -> std::io::Result<Self> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() {
+      return Err(std::io::ErrorKind::NotFound.into());
+    }
+    let available = metadata.len().saturating_sub(offset);
+    let len = length.map_or(available, |length| length.min(available));
+    let modified = metadata
+      .modified()
+      .ok()
+      .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+      .map(|duration| duration.as_nanos())
+      .unwrap_or(0);
+    let etag = match offset {
+      0 => format!("\"{:x}-{:x}\"", len, modified),
+      _ => format!("\"{:x}-{:x}-{:x}\"", len, modified, offset),
+    };
+    Ok(Self {
+      len,
+      etag,
+      body: AssetBody::File { file, offset },
+    })
+  }
+
+  /// Read `start..end` of the asset.
+  fn read(self, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    match self.body {
+      AssetBody::File { mut file, offset } => {
+        let mut body = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(offset + start))?;
+        file.read_exact(&mut body)?;
+        Ok(body)
+      }
+      AssetBody::Memory(bytes) => Ok(bytes[start as usize..end as usize].to_vec()),
+    }
+  }
+}
+
+/// Behaviour shared by every asset-backed scheme.
+pub(crate) struct SiteOptions {
+  index: String,
+  spa_fallback: bool,
+  headers: Vec<(String, String)>,
+}
+
+impl SiteOptions {
+  pub(crate) fn new(
+    index: Option<String>,
+    spa_fallback: Option<bool>,
+    headers: Option<Vec<HeaderData>>,
+  ) -> Self {
+    Self {
+      index: index.unwrap_or_else(|| "index.html".to_owned()),
+      spa_fallback: spa_fallback.unwrap_or(true),
+      headers: headers
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|h| h.value.map(|value| (h.key, value)))
+        .collect(),
+    }
+  }
+}
+
+/// An [`AssetSource`] served over a custom scheme.
+pub(crate) struct AssetSite<S> {
+  source: S,
+  options: SiteOptions,
+}
+
+impl<S: AssetSource> AssetSite<S> {
+  pub(crate) fn new(source: S, options: SiteOptions) -> Self {
+    Self { source, options }
+  }
+
+  /// Find the asset for a validated request path, returning the path it was
+  /// resolved to (which decides the MIME type) and its `Content-Encoding`.
+  fn resolve(
+    &self,
+    path: &str,
+    accept_encoding: &str,
+  ) -> std::result::Result<(String, Asset, Option<&'static str>), StatusCode> {
+    let candidate = if self.source.is_dir(path) {
+      join_path(path, &self.options.index)
+    } else {
+      path.to_owned()
+    };
+    if let Some((asset, encoding)) = self.open_encoded(&candidate, accept_encoding)? {
+      return Ok((candidate, asset, encoding));
+    }
+
+    // Extension-less paths are client-side routes: hand them the index.
+    let is_route = Path::new(&candidate).extension().is_none();
+    if self.options.spa_fallback && is_route {
+      let index = self.options.index.clone();
+      if let Some((asset, encoding)) = self.open_encoded(&index, accept_encoding)? {
+        return Ok((index, asset, encoding));
+      }
+    }
+    Err(StatusCode::NOT_FOUND)
+  }
+
+  /// Open `path`, preferring a pre-compressed `.br` / `.gz` sibling the
+  /// client accepts.  A file that only exists gzip-compressed is decoded for
+  /// clients that do not accept gzip.
+  fn open_encoded(
+    &self,
+    path: &str,
+    accept_encoding: &str,
+  ) -> std::result::Result<Option<(Asset, Option<&'static str>)>, StatusCode> {
+    for (encoding, extension) in [("br", "br"), ("gzip", "gz")] {
+      if accepts_encoding(accept_encoding, encoding) {
+        if let Some(asset) = self.source.open(&format!("{}.{}", path, extension))? {
+          return Ok(Some((asset, Some(encoding))));
+        }
+      }
+    }
+    if let Some(asset) = self.source.open(path)? {
+      return Ok(Some((asset, None)));
+    }
+    match self.source.open(&format!("{}.gz", path))? {
+      Some(asset) => Ok(Some((gunzip(asset, self.source.max_decoded_size())?, None))),
+      None => Ok(None),
+    }
+  }
+}
+
+impl<S: AssetSource> NativeProtocol for AssetSite<S> {
+  fn respond(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+    let mut response = if request.method() != Method::GET && request.method() != Method::HEAD {
+      let mut response = text_response(StatusCode::METHOD_NOT_ALLOWED);
+      response
+        .headers_mut()
+        .insert(header::ALLOW, header::HeaderValue::from_static("GET, HEAD"));
+      response
+    } else {
+      let accept_encoding = request
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+      match request_path(request.uri().path()).and_then(|path| self.resolve(&path, accept_encoding))
+      {
+        Ok((path, asset, encoding)) => serve_asset(asset, mime_type(&path), encoding, request),
+        Err(status) => text_response(status),
+      }
+    };
+
+    for (key, value) in &self.options.headers {
+      if let (Ok(name), Ok(value)) = (
+        header::HeaderName::from_bytes(key.as_bytes()),
+        header::HeaderValue::from_str(value),
+      ) {
+        response.headers_mut().insert(name, value);
+      }
+    }
+    response
+  }
+}
+
+/// Validate a URL path and turn it into a `/`-separated relative path.
+///
+/// `..` segments and anything that is not a plain file name are rejected
+/// outright, so a request can never name a file outside the source.
+pub(crate) fn request_path(path: &str) -> std::result::Result<String, StatusCode> {
+  let decoded = percent_decode(path).ok_or(StatusCode::BAD_REQUEST)?;
+
+  let mut segments = Vec::new();
+  for segment in decoded.split('/') {
+    match segment {
+      "" | "." => continue,
+      ".." => return Err(StatusCode::FORBIDDEN),
+      // Backslashes are separators on Windows; refuse them everywhere so
+      // behaviour does not depend on the platform.
+      _ if segment.contains('\\') => return Err(StatusCode::FORBIDDEN),
+      _ => {
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+          (Some(Component::Normal(_)), None) => segments.push(segment),
+          _ => return Err(StatusCode::FORBIDDEN),
+        }
+      }
+    }
+  }
+  Ok(segments.join("/"))
+}
+
+fn join_path(dir: &str, file: &str) -> String {
+  if dir.is_empty() {
+    file.to_owned()
+  } else {
+    format!("{}/{}", dir, file)
+  }
+}
+
+/// Decode a gzip-compressed asset, failing once it grows past `limit`
+/// bytes rather than trusting the size it declares.
+fn gunzip(asset: Asset, limit: u64) -> std::result::Result<Asset, StatusCode> {
+  let etag = format!("{}-d\"", asset.etag.trim_end_matches('"'));
+  let len = asset.len;
+  let compressed = asset
+    .read(0, len)
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+  let mut decoded = Vec::new();
+  flate2::read::GzDecoder::new(compressed.as_slice())
+    .take(limit.saturating_add(1))
+    .read_to_end(&mut decoded)
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+  if decoded.len() as u64 > limit {
+    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+  Ok(Asset::memory(decoded.into(), etag))
+}
+
+// ── Directory source ──────────────────────────────────────────────────────────
+
+/// A local directory.
+pub(crate) struct StaticDirectory {
+  /// Canonical root; every opened file must stay inside it.
+  root: PathBuf,
+}
+
+impl StaticDirectory {
+  pub(crate) fn new(root: &str) -> std::io::Result<Self> {
+    let root = Path::new(root).canonicalize()?;
+    if !root.is_dir() {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("{} is not a directory", root.display()),
+      ));
+    }
+    Ok(Self { root })
+  }
+}
+
+impl AssetSource for StaticDirectory {
+  fn open(&self, path: &str) -> std::result::Result<Option<Asset>, StatusCode> {
+    let full = self.root.join(path);
+    if !full.is_file() {
+      return Ok(None);
+    }
+    // Checked again after resolving symlinks so they cannot escape the root.
+    let canonical = full.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !canonical.starts_with(&self.root) {
+      return Err(StatusCode::FORBIDDEN);
+    }
+
+    Asset::file(&canonical, 0, None)
+      .map(Some)
+      .map_err(|_| StatusCode::NOT_FOUND)
+  }
+
+  fn is_dir(&self, path: &str) -> bool {
+    self.root.join(path).is_dir()
+  }
+}
+
+// ── Archive source ────────────────────────────────────────────────────────────
+
+/// Entries an archive may hold.
+const MAX_ARCHIVE_ENTRIES: usize = 65_536;
+
+/// Bytes an archive may expand to, so that a small, highly compressed
+/// archive cannot exhaust memory when it is registered.
+const MAX_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Bytes a `.gz` file of a directory may be decoded to.  An archive's own
+/// `.gz` entries may only use what is left of [`MAX_ARCHIVE_SIZE`].
+const MAX_DECODED_SIZE: u64 = MAX_ARCHIVE_SIZE;
+
+/// How much of the entry count and size limits an archive being loaded has
+/// used.
+struct ArchiveBudget {
+  entries: usize,
+  bytes: u64,
+}
+
+impl ArchiveBudget {
+  fn new(entries: usize, bytes: u64) -> Self {
+    Self { entries, bytes }
+  }
+
+  /// Count an entry, skipped or not.
+  fn entry(&mut self) -> std::io::Result<()> {
+    self.entries = self.entries.checked_sub(1).ok_or_else(|| {
+      invalid_data(format!(
+        "archive has more than {} entries",
+        MAX_ARCHIVE_ENTRIES
+      ))
+    })?;
+    Ok(())
+  }
+
+  /// Read an entry, stopping as soon as it goes over the size limit rather
+  /// than trusting the size the archive declares.
+  fn read(&mut self, entry: impl Read, name: &str) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    entry.take(self.bytes + 1).read_to_end(&mut bytes)?;
+    self.bytes = self.bytes.checked_sub(bytes.len() as u64).ok_or_else(|| {
+      invalid_data(format!(
+        "archive expands to more than {} bytes at {}",
+        MAX_ARCHIVE_SIZE, name
+      ))
+    })?;
+    Ok(bytes)
+  }
+}
+
+/// The files of a zip, tar or gzip-compressed tar archive, held in memory.
+pub(crate) struct AssetArchive {
+  files: HashMap<String, (Arc<[u8]>, String)>,
+  dirs: HashSet<String>,
+  /// What the entries left of the size limit, for decoding `.gz` entries.
+  remaining: u64,
+}
+
+impl AssetArchive {
+  /// Load an archive, detecting its format from the first bytes.  With
+  /// `root`, only entries below that directory are served, relative to it.
+  /// Fails when the archive has too many entries or expands to too many
+  /// bytes.
+  pub(crate) fn load(data: Vec<u8>, root: Option<&str>) -> std::io::Result<Self> {
+    Self::load_within(
+      data,
+      root,
+      ArchiveBudget::new(MAX_ARCHIVE_ENTRIES, MAX_ARCHIVE_SIZE),
+    )
+  }
+
+  fn load_within(
+    data: Vec<u8>,
+    root: Option<&str>,
+    mut budget: ArchiveBudget,
+  ) -> std::io::Result<Self> {
+    let root = match root {
+      Some(root) => {
+        request_path(root).map_err(|_| invalid_data(format!("invalid archive root: {}", root)))?
+      }
+      None => String::new(),
+    };
+    let mut archive = Self {
+      files: HashMap::new(),
+      dirs: HashSet::from([String::new()]),
+      remaining: 0,
+    };
+
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+      let mut zip = zip::ZipArchive::new(Cursor::new(data)).map_err(std::io::Error::other)?;
+      for i in 0..zip.len() {
+        budget.entry()?;
+        let entry = zip.by_index(i).map_err(std::io::Error::other)?;
+        if !entry.is_file() {
+          continue;
+        }
+        let name = entry.name().to_owned();
+        let bytes = budget.read(entry, &name)?;
+        archive.insert(&root, &name, bytes);
+      }
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+      archive.load_tar(
+        flate2::read::GzDecoder::new(data.as_slice()),
+        &root,
+        &mut budget,
+      )?;
+    } else if data.get(257..262) == Some(b"ustar") {
+      archive.load_tar(data.as_slice(), &root, &mut budget)?;
+    } else {
+      return Err(invalid_data(
+        "unrecognised archive format (expected zip, tar or tar.gz)".to_owned(),
+      ));
+    }
+
+    archive.remaining = budget.bytes;
+    Ok(archive)
+  }
+
+  fn load_tar(
+    &mut self,
+    reader: impl Read,
+    root: &str,
+    budget: &mut ArchiveBudget,
+  ) -> std::io::Result<()> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+      budget.entry()?;
+      let entry = entry?;
+      if !entry.header().entry_type().is_file() {
+        continue;
+      }
+      let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+      let bytes = budget.read(entry, &name)?;
+      self.insert(root, &name, bytes);
+    }
+    Ok(())
+  }
+
+  /// Add an entry.  Names that are not plain relative paths, or that lie
+  /// outside `root`, are skipped.
+  fn insert(&mut self, root: &str, name: &str, bytes: Vec<u8>) {
+    let Ok(path) = request_path(name) else {
+      return;
+    };
+    let path = if root.is_empty() {
+      path
+    } else {
+      match path
+        .strip_prefix(root)
+        .and_then(|rest| rest.strip_prefix('/'))
+      {
+        Some(rest) => rest.to_owned(),
+        None => return,
+      }
+    };
+
+    let mut dir = path.as_str();
+    while let Some((parent, _)) = dir.rsplit_once('/') {
+      self.dirs.insert(parent.to_owned());
+      dir = parent;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let etag = format!("\"{:x}-{:x}\"", bytes.len(), hasher.finish());
+    self.files.insert(path, (bytes.into(), etag));
+  }
+}
+
+impl AssetSource for AssetArchive {
+  fn open(&self, path: &str) -> std::result::Result<Option<Asset>, StatusCode> {
+    Ok(
+      self
+        .files
+        .get(path)
+        .map(|(bytes, etag)| Asset::memory(Arc::clone(bytes), etag.clone())),
+    )
+  }
+
+  fn is_dir(&self, path: &str) -> bool {
+    self.dirs.contains(path)
+  }
+
+  fn max_decoded_size(&self) -> u64 {
+    self.remaining
+  }
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+// ── File responses ────────────────────────────────────────────────────────────
+
+/// A response a JS handler answered with a file path instead of a body.
+pub(crate) struct FileResponse {
+  pub(crate) path: PathBuf,
+  pub(crate) offset: u64,
+  /// Bytes to serve from `offset`; the rest of the file when `None`.
+  pub(crate) length: Option<u64>,
+  /// Anything but `200` is sent as-is, without range or conditional
+  /// handling.
+  pub(crate) status: StatusCode,
+  pub(crate) mime_type: Option<String>,
+  pub(crate) headers: Vec<(header::HeaderName, header::HeaderValue)>,
+}
+
+impl FileResponse {
+  /// Read the file for `request`, whose method and headers decide about
+  /// ranges and `304`.  Blocks on I/O.
+  pub(crate) fn respond(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+    let asset = match Asset::file(&self.path, self.offset, self.length) {
+      Ok(asset) => asset,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+        return text_response(StatusCode::NOT_FOUND)
+      }
+      Err(_) => return text_response(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let content_type = self
+      .mime_type
+      .as_deref()
+      .unwrap_or_else(|| mime_type(&self.path.to_string_lossy()));
+    let mut response = if self.status == StatusCode::OK {
+      serve_asset(asset, content_type, None, request)
+    } else {
+      let len = asset.len;
+      match asset.read(0, len) {
+        Ok(body) => Response::builder()
+          .status(self.status)
+          .header(header::CONTENT_TYPE, content_type)
+          .header(header::CONTENT_LENGTH, len)
+          .body(Cow::Owned(body))
+          .expect("static protocol response is valid"),
+        Err(_) => text_response(StatusCode::INTERNAL_SERVER_ERROR),
+      }
+    };
+    for (name, value) in &self.headers {
+      response.headers_mut().insert(name.clone(), value.clone());
+    }
+    response
+  }
+}
+
+// ── HTTP helpers ──────────────────────────────────────────────────────────────
+
+/// Most bytes read for one range request.  Wry takes response bodies in one
+/// piece, so larger ranges are answered with their start; media elements
+/// and other range clients request the rest as they need it.
+const MAX_RANGE_LEN: u64 = 8 * 1024 * 1024;
+
+/// Serve an asset, honouring `If-None-Match`, `Range` and `HEAD`.
+fn serve_asset(
+  asset: Asset,
+  content_type: &str,
+  encoding: Option<&'static str>,
+  request: &Request<Vec<u8>>,
+) -> ProtocolResponse {
+  let len = asset.len;
+  let header_str = |name: header::HeaderName| {
+    request
+      .headers()
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+  };
+
+  let mut builder = Response::builder()
+    .header(header::ETAG, &asset.etag)
+    .header(header::ACCEPT_RANGES, "bytes");
+  if let Some(encoding) = encoding {
+    builder = builder
+      .header(header::CONTENT_ENCODING, encoding)
+      .header(header::VARY, "Accept-Encoding");
+  }
+
+  if header_str(header::IF_NONE_MATCH).is_some_and(|value| etag_matches(value, &asset.etag)) {
+    return builder
+      .status(StatusCode::NOT_MODIFIED)
+      .body(Cow::Borrowed(&[][..]))
+      .expect("static protocol response is valid");
+  }
+
+  // A stale `If-Range` validator means the client's partial copy is outdated.
+  let range_header = match header_str(header::IF_RANGE) {
+    Some(validator) if validator.trim() != asset.etag => None,
+    _ => header_str(header::RANGE),
+  };
+
+  let builder = builder.header(header::CONTENT_TYPE, content_type);
+  let (builder, start, end) = match byte_range(range_header, len) {
+    ByteRange::Full => (builder.status(StatusCode::OK), 0, len),
+    ByteRange::Partial(start, end) => {
+      let end = end.min(start + MAX_RANGE_LEN - 1);
+      (
+        builder.status(StatusCode::PARTIAL_CONTENT).header(
+          header::CONTENT_RANGE,
+          format!("bytes {}-{}/{}", start, end, len),
+        ),
+        start,
+        end + 1,
+      )
+    }
+    ByteRange::Unsatisfiable => {
+      return builder
+        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+        .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+        .body(Cow::Borrowed(&[][..]))
+        .expect("static protocol response is valid");
+    }
+  };
+
+  let builder = builder.header(header::CONTENT_LENGTH, end - start);
+  if request.method() == Method::HEAD {
+    return builder
+      .body(Cow::Borrowed(&[][..]))
+      .expect("static protocol response is valid");
+  }
+
+  match asset.read(start, end) {
+    Ok(body) => builder
+      .body(Cow::Owned(body))
+      .expect("static protocol response is valid"),
+    Err(_) => text_response(StatusCode::INTERNAL_SERVER_ERROR),
+  }
+}
+
+/// Outcome of evaluating a `Range` header against a resource length.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+  /// No usable range; serve the whole resource.
+  Full,
+  /// Inclusive `start..=end` byte offsets.
+  Partial(u64, u64),
+  /// The range lies outside the resource (`416`).
+  Unsatisfiable,
+}
+
+/// Evaluate a single-range `Range: bytes=…` header.  Malformed and
+/// multi-range headers are ignored, as RFC 9110 permits.
+pub(crate) fn byte_range(value: Option<&str>, len: u64) -> ByteRange {
+  let Some(spec) = value.and_then(|v| v.trim().strip_prefix("bytes=")) else {
+    return ByteRange::Full;
+  };
+  if spec.contains(',') {
+    return ByteRange::Full;
+  }
+  let Some((first, last)) = spec.trim().split_once('-') else {
+    return ByteRange::Full;
+  };
+
+  let (start, end) = match (first.trim(), last.trim()) {
+    ("", "") => return ByteRange::Full,
+    ("", suffix) => match suffix.parse::<u64>() {
+      Ok(0) => return ByteRange::Unsatisfiable,
+      Ok(n) => (len.saturating_sub(n), len.saturating_sub(1)),
+      Err(_) => return ByteRange::Full,
+    },
+    (first, "") => match first.parse::<u64>() {
+      Ok(start) => (start, len.saturating_sub(1)),
+      Err(_) => return ByteRange::Full,
+    },
+    (first, last) => match (first.parse::<u64>(), last.parse::<u64>()) {
+      (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+      _ => return ByteRange::Full,
+    },
+  };
+
+  if len == 0 || start >= len {
+    ByteRange::Unsatisfiable
+  } else {
+    ByteRange::Partial(start, end)
+  }
+}
+
+/// Weak comparison of an `If-None-Match` header against an entity tag.
+pub(crate) fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+  let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+  let etag = strip(etag);
+  if_none_match
+    .split(',')
+    .any(|candidate| candidate.trim() == "*" || strip(candidate) == etag)
+}
+
+/// Whether an `Accept-Encoding` header allows `encoding` (`q=0` refuses it).
+pub(crate) fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+  accept_encoding.split(',').any(|item| {
+    let mut parts = item.split(';');
+    let name = parts.next().unwrap_or("").trim();
+    let refused = parts.any(|param| {
+      param
+        .trim()
+        .strip_prefix("q=")
+        .and_then(|q| q.trim().parse::<f32>().ok())
+        .is_some_and(|q| q == 0.0)
+    });
+    (name.eq_ignore_ascii_case(encoding) || name == "*") && !refused
+  })
+}
+
+/// Decode `%XX` escapes in a URL path or query.  Returns `None` for
+/// malformed escapes or a result that is not valid UTF-8.
+pub(crate) fn percent_decode(input: &str) -> Option<String> {
+  let bytes = input.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%' {
+      let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+      decoded.push(u8::from_str_radix(hex, 16).ok()?);
+      i += 3;
+    } else {
+      decoded.push(bytes[i]);
+      i += 1;
+    }
+  }
+  String::from_utf8(decoded).ok()
+}
+
+/// Guess a `Content-Type` from a file extension.
+pub(crate) fn mime_type(path: &str) -> &'static str {
+  let extension = Path::new(path)
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .map(str::to_ascii_lowercase);
+  match extension.as_deref() {
+    Some("html" | "htm") => "text/html; charset=utf-8",
+    Some("js" | "mjs" | "cjs") => "text/javascript; charset=utf-8",
+    Some("css") => "text/css; charset=utf-8",
+    Some("json" | "map") => "application/json",
+    Some("webmanifest") => "application/manifest+json",
+    Some("txt") => "text/plain; charset=utf-8",
+    Some("csv") => "text/csv; charset=utf-8",
+    Some("xml") => "application/xml",
+    Some("wasm") => "application/wasm",
+    Some("svg") => "image/svg+xml",
+    Some("png") => "image/png",
+    Some("jpg" | "jpeg") => "image/jpeg",
+    Some("gif") => "image/gif",
+    Some("webp") => "image/webp",
+    Some("avif") => "image/avif",
+    Some("ico") => "image/x-icon",
+    Some("bmp") => "image/bmp",
+    Some("woff") => "font/woff",
+    Some("woff2") => "font/woff2",
+    Some("ttf") => "font/ttf",
+    Some("otf") => "font/otf",
+    Some("mp4" | "m4v") => "video/mp4",
+    Some("webm") => "video/webm",
+    Some("ogv") => "video/ogg",
+    Some("mov") => "video/quicktime",
+    Some("mp3") => "audio/mpeg",
+    Some("m4a") => "audio/mp4",
+    Some("ogg" | "oga") => "audio/ogg",
+    Some("wav") => "audio/wav",
+    Some("flac") => "audio/flac",
+    Some("pdf") => "application/pdf",
+    Some("zip") => "application/zip",
+    _ => "application/octet-stream",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  fn directory(spa_fallback: bool) -> (tempfile::TempDir, AssetSite<StaticDirectory>) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("index.html"), "<h1>home</h1>").unwrap();
+    std::fs::create_dir(dir.path().join("assets")).unwrap();
+    std::fs::write(dir.path().join("assets/app.js"), "console.log(1)").unwrap();
+    let site = AssetSite::new(
+      StaticDirectory::new(&dir.path().to_string_lossy()).unwrap(),
+      SiteOptions::new(None, Some(spa_fallback), None),
+    );
+    (dir, site)
+  }
+
+  fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+  }
+
+  fn get(uri: &str, headers: &[(&str, &str)]) -> Request<Vec<u8>> {
+    let mut builder = Request::builder().uri(uri);
+    for (key, value) in headers {
+      builder = builder.header(*key, *value);
+    }
+    builder.body(Vec::new()).unwrap()
+  }
+
+  #[test]
+  fn parses_single_byte_ranges() {
+    assert_eq!(byte_range(None, 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("bytes=0-4"), 10), ByteRange::Partial(0, 4));
+    assert_eq!(byte_range(Some("bytes=5-"), 10), ByteRange::Partial(5, 9));
+    assert_eq!(byte_range(Some("bytes=-3"), 10), ByteRange::Partial(7, 9));
+    assert_eq!(byte_range(Some("bytes=2-99"), 10), ByteRange::Partial(2, 9));
+    assert_eq!(byte_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+    assert_eq!(byte_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+    assert_eq!(byte_range(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("bytes=5-2"), 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("items=0-1"), 10), ByteRange::Full);
+  }
+
+  #[test]
+  fn compares_entity_tags_weakly() {
+    assert!(etag_matches("\"a-1\"", "\"a-1\""));
+    assert!(etag_matches("W/\"a-1\", \"b-2\"", "\"a-1\""));
+    assert!(etag_matches("*", "\"a-1\""));
+    assert!(!etag_matches("\"a-2\"", "\"a-1\""));
+  }
+
+  #[test]
+  fn negotiates_content_encodings() {
+    assert!(accepts_encoding("gzip, deflate, br", "br"));
+    assert!(accepts_encoding("GZIP;q=0.5", "gzip"));
+    assert!(accepts_encoding("*", "br"));
+    assert!(!accepts_encoding("gzip;q=0, br", "gzip"));
+    assert!(!accepts_encoding("", "gzip"));
+  }
+
+  #[test]
+  fn validates_request_paths() {
+    assert_eq!(request_path("/a%20b/./c.js").as_deref(), Ok("a b/c.js"));
+    assert_eq!(request_path("/").as_deref(), Ok(""));
+    assert_eq!(request_path("/../secret"), Err(StatusCode::FORBIDDEN));
+    assert_eq!(
+      request_path("/assets/%2e%2e/%2e%2e/secret"),
+      Err(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+      request_path("/assets/..%5C..%5Csecret"),
+      Err(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(request_path("/bad%2"), Err(StatusCode::BAD_REQUEST));
+  }
+
+  #[test]
+  fn falls_back_to_index_for_client_routes_only() {
+    let (_dir, site) = directory(true);
+
+    let route = site.respond(&get("app://localhost/settings/profile", &[]));
+    assert_eq!(route.status(), StatusCode::OK);
+    assert_eq!(route.body().as_ref(), b"<h1>home</h1>");
+    assert_eq!(
+      site
+        .respond(&get("app://localhost/missing.js", &[]))
+        .status(),
+      StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+      site
+        .respond(&get("app://localhost/../secret", &[]))
+        .status(),
+      StatusCode::FORBIDDEN
+    );
+
+    let (_dir, strict) = directory(false);
+    assert_eq!(
+      strict
+        .respond(&get("app://localhost/settings", &[]))
+        .status(),
+      StatusCode::NOT_FOUND
+    );
+  }
+
+  #[test]
+  fn serves_ranges_and_conditional_requests() {
+    let (_dir, site) = directory(true);
+
+    let full = site.respond(&get("app://localhost/assets/app.js", &[]));
+    assert_eq!(full.status(), StatusCode::OK);
+    assert_eq!(
+      full.headers()[header::CONTENT_TYPE],
+      "text/javascript; charset=utf-8"
+    );
+    let etag = full.headers()[header::ETAG].to_str().unwrap().to_owned();
+
+    let cached = site.respond(&get(
+      "app://localhost/assets/app.js",
+      &[("If-None-Match", &etag)],
+    ));
+    assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+    let partial = site.respond(&get(
+      "app://localhost/assets/app.js",
+      &[("Range", "bytes=0-6")],
+    ));
+    assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(partial.body().as_ref(), b"console");
+    assert_eq!(partial.headers()[header::CONTENT_RANGE], "bytes 0-6/14");
+  }
+
+  #[test]
+  fn caps_the_bytes_read_for_a_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let len = MAX_RANGE_LEN + 10;
+    std::fs::write(dir.path().join("movie.webm"), vec![0; len as usize]).unwrap();
+    let site = AssetSite::new(
+      StaticDirectory::new(&dir.path().to_string_lossy()).unwrap(),
+      SiteOptions::new(None, Some(false), None),
+    );
+
+    let first = site.respond(&get("app://localhost/movie.webm", &[("Range", "bytes=0-")]));
+    assert_eq!(first.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(first.body().len() as u64, MAX_RANGE_LEN);
+    assert_eq!(
+      first.headers()[header::CONTENT_RANGE],
+      format!("bytes 0-{}/{}", MAX_RANGE_LEN - 1, len)
+    );
+
+    let rest = site.respond(&get(
+      "app://localhost/movie.webm",
+      &[("Range", &format!("bytes={}-", MAX_RANGE_LEN))],
+    ));
+    assert_eq!(rest.body().len(), 10);
+  }
+
+  #[test]
+  fn serves_file_responses_with_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clip.mp4");
+    std::fs::write(&path, b"HEADER0123456789").unwrap();
+    let file = |offset, length, status| FileResponse {
+      path: path.clone(),
+      offset,
+      length,
+      status,
+      mime_type: None,
+      headers: vec![(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("no-store"),
+      )],
+    };
+
+    let window = file(6, Some(10), StatusCode::OK);
+    let full = window.respond(&get("app://localhost/video", &[]));
+    assert_eq!(full.status(), StatusCode::OK);
+    assert_eq!(full.headers()[header::CONTENT_TYPE], "video/mp4");
+    assert_eq!(full.headers()[header::CONTENT_LENGTH], "10");
+    assert_eq!(full.headers()[header::CACHE_CONTROL], "no-store");
+    assert_eq!(full.body().as_ref(), b"0123456789");
+
+    let partial = window.respond(&get("app://localhost/video", &[("range", "bytes=2-4")]));
+    assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(partial.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+    assert_eq!(partial.body().as_ref(), b"234");
+
+    let etag = full.headers()[header::ETAG].to_str().unwrap();
+    let cached = window.respond(&get("app://localhost/video", &[("if-none-match", etag)]));
+    assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+    // Other statuses are sent whole, ignoring ranges.
+    let created = file(0, None, StatusCode::CREATED)
+      .respond(&get("app://localhost/video", &[("range", "bytes=0-1")]));
+    assert_eq!(created.status(), StatusCode::CREATED);
+    assert_eq!(created.body().len(), 16);
+
+    let missing = FileResponse {
+      path: dir.path().join("missing.pdf"),
+      ..file(0, None, StatusCode::OK)
+    };
+    assert_eq!(
+      missing.respond(&get("app://localhost/doc", &[])).status(),
+      StatusCode::NOT_FOUND
+    );
+  }
+
+  #[test]
+  fn serves_pre_compressed_variants() {
+    let (dir, site) = directory(true);
+    std::fs::write(dir.path().join("assets/app.js.gz"), gzip(b"console.log(1)")).unwrap();
+    std::fs::write(dir.path().join("data.json.gz"), gzip(b"{\"a\":1}")).unwrap();
+
+    let encoded = site.respond(&get(
+      "app://localhost/assets/app.js",
+      &[("Accept-Encoding", "gzip")],
+    ));
+    assert_eq!(encoded.headers()[header::CONTENT_ENCODING], "gzip");
+    assert_eq!(
+      encoded.headers()[header::CONTENT_TYPE],
+      "text/javascript; charset=utf-8"
+    );
+
+    let plain = site.respond(&get("app://localhost/assets/app.js", &[]));
+    assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(plain.body().as_ref(), b"console.log(1)");
+
+    let decoded = site.respond(&get("app://localhost/data.json", &[]));
+    assert_eq!(decoded.status(), StatusCode::OK);
+    assert_eq!(decoded.body().as_ref(), b"{\"a\":1}");
+  }
+
+  #[test]
+  fn serves_zip_archives() {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    writer.start_file("dist/index.html", options).unwrap();
+    writer.write_all(b"<h1>zip</h1>").unwrap();
+    writer.start_file("dist/js/app.js", options).unwrap();
+    writer.write_all(b"export {}").unwrap();
+    writer.start_file("README.md", options).unwrap();
+    writer.write_all(b"outside root").unwrap();
+    let data = writer.finish().unwrap().into_inner();
+
+    let site = AssetSite::new(
+      AssetArchive::load(data, Some("dist")).unwrap(),
+      SiteOptions::new(None, None, None),
+    );
+
+    let index = site.respond(&get("app://localhost/", &[]));
+    assert_eq!(index.body().as_ref(), b"<h1>zip</h1>");
+    let script = site.respond(&get("app://localhost/js/app.js", &[("Range", "bytes=7-")]));
+    assert_eq!(script.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(script.body().as_ref(), b"{}");
+    assert_eq!(
+      site
+        .respond(&get("app://localhost/README.md", &[]))
+        .status(),
+      StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+      site.respond(&get("app://localhost/js", &[])).status(),
+      StatusCode::NOT_FOUND
+    );
+  }
+
+  #[test]
+  fn serves_gzipped_tar_archives() {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, body) in [
+      ("./index.html", &b"<h1>tar</h1>"[..]),
+      ("./app.css", b"p{}"),
+    ] {
+      let mut header = tar::Header::new_gnu();
+      header.set_size(body.len() as u64);
+      header.set_mode(0o644);
+      header.set_cksum();
+      builder.append_data(&mut header, name, body).unwrap();
+    }
+    let data = gzip(&builder.into_inner().unwrap());
+
+    let site = AssetSite::new(
+      AssetArchive::load(data, None).unwrap(),
+      SiteOptions::new(None, None, None),
+    );
+
+    let route = site.respond(&get("app://localhost/about", &[]));
+    assert_eq!(route.body().as_ref(), b"<h1>tar</h1>");
+    let css = site.respond(&get("app://localhost/app.css", &[]));
+    assert_eq!(
+      css.headers()[header::CONTENT_TYPE],
+      "text/css; charset=utf-8"
+    );
+    assert!(AssetArchive::load(b"not an archive".to_vec(), None).is_err());
+  }
+
+  #[test]
+  fn rejects_archives_over_the_limits() {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+      writer.start_file(name, options).unwrap();
+      writer.write_all(&[b'x'; 100]).unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+
+    let load = |entries, bytes| {
+      AssetArchive::load_within(data.clone(), None, ArchiveBudget::new(entries, bytes))
+    };
+    assert!(load(3, 300).is_ok());
+    let error = load(2, 300).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(load(3, 299).is_err());
+
+    // Tar entries count towards the same limits.
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(1000);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+      .append_data(&mut header, "big.bin", &[0u8; 1000][..])
+      .unwrap();
+    let data = gzip(&builder.into_inner().unwrap());
+    assert!(AssetArchive::load_within(data.clone(), None, ArchiveBudget::new(10, 999)).is_err());
+    assert!(AssetArchive::load_within(data, None, ArchiveBudget::new(10, 1000)).is_ok());
+  }
+
+  #[test]
+  fn caps_gzip_entries_decoded_on_request() {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    writer.start_file("small.txt.gz", options).unwrap();
+    writer.write_all(&gzip(&[b'x'; 100])).unwrap();
+    writer.start_file("bomb.txt.gz", options).unwrap();
+    writer.write_all(&gzip(&[b'x'; 10_000])).unwrap();
+    let data = writer.finish().unwrap().into_inner();
+    let archive = AssetArchive::load_within(data, None, ArchiveBudget::new(10, 1000)).unwrap();
+    let site = AssetSite::new(archive, SiteOptions::new(None, None, None));
+
+    let small = site.respond(&get("app://localhost/small.txt", &[]));
+    assert_eq!(small.body().as_ref(), &[b'x'; 100][..]);
+    let bomb = site.respond(&get("app://localhost/bomb.txt", &[]));
+    assert_eq!(bomb.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    // Clients accepting gzip get the entry as it is.
+    let encoded = site.respond(&get(
+      "app://localhost/bomb.txt",
+      &[("Accept-Encoding", "gzip")],
+    ));
+    assert_eq!(encoded.status(), StatusCode::OK);
+  }
+}
@@ -0,0 +1,1509 @@
+//! Native side of the page bridge: `Webview.expose()` and messages between
+//! the page and Node.
+//!
+//! Page calls are POST requests on an internal custom scheme, handed to the
+//! namespace's JS handler under a native call id.  The request stays open
+//! until [`PageBridge::reply`] answers it with the outcome of the call.
+//!
+//! Every document gets a random token, which ties the streams and callbacks
+//! it holds to it.
+//!
+//! A call can also settle with a stream, for exposed functions returning an
+//! async iterable.  The page pulls its items one `next` call at a time, so
+//! Node never produces more than the page asked for; the page's `return()`,
+//! navigation and disposal close the stream and end the Node iterator.
+//!
+//! Functions passed as arguments become callbacks: the page keeps them under
+//! an id, and Node can run them until it releases them or the document goes
+//! away.
+//!
+//! The page runtime is an initialization script, so it exists in every
+//! document.  On start it sends a `ready` message, answered by installing
+//! every exposed namespace; namespaces therefore survive reloads and
+//! navigation.
+//!
+//! IPC only carries strings, so binary messages travel over the same scheme:
+//! the page POSTs them, and fetches the ones pushed by Node by id and a
+//! random key only the document they were sent to is given.  Answers on
+//! the scheme are only readable by pages the origin policies allow.
+//!
+//! Named events are JSON payloads sent with `emit` on either side and
+//! dispatched to the `listen` callbacks of the other.
+//!
+//! Scripts run with [`PageBridge::evaluate_script`] report back over IPC,
+//! with their value once any promise settles or with the exception they
+//! ended with.  Each carries a random token along with its id, so the page
+//! cannot end an evaluation it was not asked to run.  Navigation and
+//! disposal abort the ones still running.
+//!
+//! Channel ports connect the pages of two webviews: a message posted on a
+//! port is relayed by its [`ChannelState`](crate::channel::ChannelState) to
+//! the page of the other webview.
+//!
+//! Everything the page sends is checked against an [`OriginPolicy`]: the
+//! webview's for messages and events, and each namespace's own for calls.
+//! Namespaces are only installed into documents they allow.
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  sync::Arc,
+};
+
+use std::borrow::Cow;
+
+use napi::{bindgen_prelude::FunctionRef, Env};
+use serde_json::{json, Value};
+use wry::http::{header, Method, Request, Response, StatusCode};
+
+use crate::assets::percent_decode;
+use crate::channel::PortLink;
+use crate::custom_protocol_workaround::UriWorkAround;
+use crate::ipc_limits::IpcLimit;
+use crate::origins::OriginPolicy;
+use crate::protocol::{text_response, ProtocolResponse};
+use crate::types::{EvaluationData, EvaluationError, ExposeCallData, PageEventData};
+use crate::webview::WebviewResource;
+
+/// Internal scheme carrying calls and binary messages between the page and
+/// Node.
+pub(crate) const MESSAGE_SCHEME: &str = "webviewjs-ipc";
+
+/// Whether `name` is [`MESSAGE_SCHEME`], which scheme names are compared
+/// case-insensitively.
+pub(crate) fn is_message_scheme(name: &str) -> bool {
+  name.eq_ignore_ascii_case(MESSAGE_SCHEME)
+}
+
+/// Key of the `{ [CALLBACK_KEY]: id }` objects standing in for functions
+/// passed as arguments.
+const CALLBACK_KEY: &str = "__webviewjsCallback";
+
+/// Page-side runtime, installed once per document.  `call()` posts a request
+/// to [`MESSAGE_SCHEME`] and settles with its response, where a `stream`
+/// reply becomes an async iterator whose `next()` calls are requests of their
+/// own; `ready` resolves once the namespaces exposed so far are installed.
+/// `deliver()` and `pull()`, which fetches a binary message with the key
+/// only this document was given, dispatch messages from Node on
+/// `window.webviewjs`, `dispatch()` its events, `invoke()` the functions
+/// passed as arguments and `portMessage()` the messages of channel ports,
+/// all in the order they were sent.  `evaluate()` runs the async function of
+/// a script from Node, unless an earlier one of the same id ran, and reports
+/// its value or its exception; `evaluated()` reports a syntax error when
+/// none did.
+/// `__ORIGIN__` is replaced by where the page reaches [`MESSAGE_SCHEME`],
+/// and `__CALLBACK__` by [`CALLBACK_KEY`].
+const RUNTIME: &str = r#"(function(){
+  if(window.__webviewjs__)return;
+  const origin=__ORIGIN__;
+  const doc=Array.from(crypto.getRandomValues(new Uint32Array(4)),function(n){return n.toString(36);}).join('');
+  let nextCallback=0;
+  const callbacks=new Map();
+  const evaluating=new Set();
+  let markReady;
+  const messages=new EventTarget();
+  const listeners=new Map();
+  const ports=new Map();
+  let inbox=Promise.resolve();
+  function enqueue(data,handle){
+    inbox=inbox.then(function(){return data;}).then(handle,function(){});
+  }
+  function message(data){messages.dispatchEvent(new MessageEvent('message',{data:data}));}
+  function port(name){
+    if(!ports.has(name)){
+      const p=new EventTarget();
+      p.postMessage=function(data){
+        let json;
+        try{json=JSON.stringify(data);if(json===undefined)throw new Error('not serialisable');}
+        catch{const err=new Error('Message is not JSON-serialisable');err.name='SerializationError';throw err;}
+        window.ipc.postMessage(JSON.stringify({__e:'port',name:name,data:JSON.parse(json)}));
+      };
+      p.close=function(){window.ipc.postMessage(JSON.stringify({__e:'port-close',name:name}));};
+      ports.set(name,p);
+    }
+    return ports.get(name);
+  }
+  function refused(r,what){
+    if(r.status===403){const err=new Error('This page may not '+what);err.name='PermissionDenied';return err;}
+    if(r.status===413||r.status===429){const err=new Error(r.status===413?'Message is too large':'Too many messages');err.name='IpcLimitExceeded';return err;}
+    return new Error('Message was rejected with status '+r.status);
+  }
+  function request(ns,method,args,stream,added){
+    function drop(){for(const id of added||[])callbacks.delete(id);}
+    return fetch(origin+'/call?url='+encodeURIComponent(location.href),{
+      method:'POST',
+      body:JSON.stringify({doc:doc,ns:ns,method:method,args:args,stream:stream})
+    }).then(function(r){
+      if(!r.ok)throw refused(r,'call '+ns);
+      return r.json();
+    }).then(function(r){
+      if(r.ok)return r.stream!==undefined?iterator(ns,r.stream):r.value;
+      if(r.released)drop();
+      const err=new Error(r.error.message);err.name=r.error.name;throw err;
+    },function(e){drop();throw e;});
+  }
+  function iterator(ns,stream){
+    let done=false;
+    function end(value){
+      if(!done){done=true;window.ipc.postMessage(JSON.stringify({__e:'return',doc:doc,ns:ns,stream:stream}));}
+      return Promise.resolve({done:true,value:value});
+    }
+    return {
+      next:function(){
+        if(done)return Promise.resolve({done:true,value:undefined});
+        return request(ns,'next',[],stream).then(function(r){if(r.done)done=true;return r;},function(e){done=true;throw e;});
+      },
+      return:end,
+      throw:function(e){return end().then(function(){throw e;});},
+      [Symbol.asyncIterator]:function(){return this;}
+    };
+  }
+  function report(id,token,result){
+    result.then(function(value){
+      let json;
+      try{json=JSON.stringify(value);}
+      catch{return {error:{name:'SerializationError',message:'Result is not JSON-serialisable'}};}
+      return {value:json===undefined?undefined:JSON.parse(json)};
+    },function(e){
+      const error=e!==null&&typeof e==='object'?e:{message:e};
+      return {error:{
+        name:typeof error.name==='string'?error.name:'Error',
+        message:String(error.message),
+        stack:typeof error.stack==='string'?error.stack:undefined
+      }};
+    }).then(function(r){
+      r.__e='result';r.id=id;r.token=token;
+      window.ipc.postMessage(JSON.stringify(r));
+    });
+  }
+  window.__webviewjs__={
+    ready:new Promise(function(res){markReady=res;}),
+    deliver:function(data){enqueue(data,message);},
+    pull:function(id,key){
+      enqueue(fetch(origin+'/push/'+id+'?key='+key).then(function(r){
+        if(!r.ok)throw new Error('Message '+id+' is gone');
+        return r.arrayBuffer();
+      }),message);
+    },
+    dispatch:function(event){
+      enqueue(event,function(event){
+        for(const cb of Array.from(listeners.get(event.event)||[])){
+          try{cb(event.payload);}catch(e){setTimeout(function(){throw e;});}
+        }
+      });
+    },
+    invoke:function(m){
+      if(m.doc!==doc)return;
+      enqueue(m,function(m){
+        const cb=callbacks.get(m.id);
+        if(!cb)return;
+        try{cb.apply(undefined,m.args);}catch(e){setTimeout(function(){throw e;});}
+      });
+    },
+    release:function(m){if(m.doc===doc)callbacks.delete(m.id);},
+    evaluate:function(id,token,run){
+      if(evaluating.has(id))return;
+      evaluating.add(id);
+      report(id,token,Promise.resolve().then(run));
+    },
+    evaluated:function(id,token){
+      if(evaluating.delete(id))return;
+      report(id,token,Promise.reject(new SyntaxError('The script is not valid JavaScript')));
+    },
+    portMessage:function(m){
+      enqueue(m,function(m){port(m.name).dispatchEvent(new MessageEvent('message',{data:m.data}));});
+    },
+    portClosed:function(name){
+      enqueue(name,function(name){
+        const p=ports.get(name);
+        if(p){ports.delete(name);p.dispatchEvent(new Event('close'));}
+      });
+    },
+    installed:function(){markReady();},
+    call:function(ns,method,args){
+      const added=[];
+      let argsJson;
+      try{
+        argsJson=JSON.stringify(args,function(key,value){
+          if(typeof value!=='function')return value;
+          const id=++nextCallback;
+          callbacks.set(id,value);
+          added.push(id);
+          return {__CALLBACK__:id};
+        });
+        if(argsJson===undefined)throw new Error('not serialisable');
+      }catch{
+        for(const id of added)callbacks.delete(id);
+        const err=new Error('Arguments are not JSON-serialisable');err.name='SerializationError';return Promise.reject(err);
+      }
+      return request(ns,method,JSON.parse(argsJson),undefined,added);
+    }
+  };
+  Object.defineProperty(window,'webviewjs',{configurable:true,value:Object.freeze({
+    ready:window.__webviewjs__.ready,
+    postMessage:function(data){
+      if(typeof data==='string'){window.ipc.postMessage(data);return Promise.resolve();}
+      if(!(data instanceof ArrayBuffer||ArrayBuffer.isView(data)||data instanceof Blob)){
+        return Promise.reject(new TypeError('postMessage() expects a string, an ArrayBuffer, a typed array, a DataView or a Blob'));
+      }
+      return fetch(origin+'/message?url='+encodeURIComponent(location.href),{method:'POST',body:data})
+        .then(function(r){if(!r.ok)throw refused(r,'post messages');});
+    },
+    addEventListener:function(type,listener,options){messages.addEventListener(type,listener,options);},
+    removeEventListener:function(type,listener,options){messages.removeEventListener(type,listener,options);},
+    emit:function(event,payload){
+      if(typeof event!=='string')throw new TypeError('emit() expects an event name');
+      let json;
+      try{json=payload===undefined?'':JSON.stringify(payload);if(json===undefined)throw new Error('not serialisable');}
+      catch{const err=new Error('Payload is not JSON-serialisable');err.name='SerializationError';throw err;}
+      window.ipc.postMessage(JSON.stringify({__e:'emit',event:event,payload:json===''?undefined:JSON.parse(json)}));
+    },
+    port:function(name){
+      if(typeof name!=='string')throw new TypeError('port() expects a port name');
+      return port(name);
+    },
+    listen:function(event,cb){
+      if(typeof cb!=='function')throw new TypeError('listen() expects a callback');
+      if(!listeners.has(event))listeners.set(event,new Set());
+      listeners.get(event).add(cb);
+      return function(){const set=listeners.get(event);if(set&&set.delete(cb)&&!set.size)listeners.delete(event);};
+    }
+  })});
+  window.ipc.postMessage(JSON.stringify({__e:'ready',doc:doc}));
+})();"#;
+
+/// A message from Node to the page.
+pub(crate) enum PageMessage {
+  Text(String),
+  Binary(Vec<u8>),
+}
+
+/// How an exposed call ended.
+pub(crate) enum ExposeOutcome {
+  /// Resolve with a JSON value, or `undefined` when `None`.
+  Value(Option<Value>),
+  /// Reject with an `Error` of the given `name`.
+  Error { name: String, message: String },
+  /// Reject, before the handler saw the call, with an `Error` of the given
+  /// `name`; the page drops the functions it passed as arguments.
+  Refused { name: String, message: String },
+  /// Resolve with an async iterator pulling from stream `0`.
+  Stream(u64),
+}
+
+impl ExposeOutcome {
+  pub(crate) fn error(name: &str, message: impl Into<String>) -> Self {
+    Self::Error {
+      name: name.to_owned(),
+      message: message.into(),
+    }
+  }
+
+  fn refused(name: &str, message: impl Into<String>) -> Self {
+    Self::Refused {
+      name: name.to_owned(),
+      message: message.into(),
+    }
+  }
+}
+
+/// An exposed namespace.
+struct Namespace {
+  /// JSON object of the static values.
+  statics_json: String,
+  func_names: Vec<String>,
+  /// Pages that get the namespace and may call it.
+  origins: OriginPolicy,
+  handler: FunctionRef<ExposeCallData, ()>,
+}
+
+impl Namespace {
+  fn script(&self, name: &str) -> serde_json::Result<String> {
+    namespace_script(name, &self.statics_json, &self.func_names)
+  }
+}
+
+/// Sends the response to a request on [`MESSAGE_SCHEME`].
+pub(crate) type Respond = Box<dyn FnOnce(ProtocolResponse)>;
+
+/// A page call waiting for its reply.
+struct PendingCall {
+  /// Namespace the call was made on.
+  ns: String,
+  /// Token of the calling document.
+  doc: String,
+  /// Stream a `next` call pulls from.
+  stream: Option<u64>,
+  /// Answers the page's request.
+  respond: Respond,
+}
+
+/// A script run by [`PageBridge::evaluate_script`].
+struct Evaluation {
+  /// Sent back with the result.
+  token: String,
+  handler: FunctionRef<EvaluationData, ()>,
+}
+
+/// A page function passed as an argument.
+struct PageCallback {
+  ns: String,
+  /// Token of the document holding the function.
+  doc: String,
+  /// Id of the function within that document.
+  id: f64,
+}
+
+/// An async iterator returned to the page.
+struct OpenStream {
+  ns: String,
+  /// Token of the document holding the iterator.
+  doc: String,
+}
+
+/// Exposed namespaces of one webview, their in-flight calls and the binary
+/// messages waiting to be fetched by the page.
+pub(crate) struct PageBridge {
+  env: Env,
+  webview: WebviewResource,
+  runtime: String,
+  /// Whether the current document reported the runtime ready.
+  has_runtime: Cell<bool>,
+  /// Pages that may post messages and emit events.
+  ipc_origins: OriginPolicy,
+  /// Maps page URLs on Windows and Android back to their custom scheme.
+  work_around: Option<Arc<UriWorkAround>>,
+  namespaces: RefCell<HashMap<String, Namespace>>,
+  /// Receives the events emitted by the page.
+  events: RefCell<Option<FunctionRef<PageEventData, ()>>>,
+  pending: RefCell<HashMap<u64, PendingCall>>,
+  streams: RefCell<HashMap<u64, OpenStream>>,
+  callbacks: RefCell<HashMap<u64, PageCallback>>,
+  /// Channel ends held by this webview, by port name.
+  ports: RefCell<HashMap<String, PortLink>>,
+  /// Scripts still running, by id.
+  evaluations: RefCell<HashMap<u64, Evaluation>>,
+  next_id: Cell<u64>,
+  outbox: Outbox,
+}
+
+impl PageBridge {
+  /// `message_origin` is where the page reaches [`MESSAGE_SCHEME`], e.g.
+  /// `http://webviewjs-ipc.localhost` on Windows.
+  pub(crate) fn new(
+    env: Env,
+    webview: WebviewResource,
+    message_origin: &str,
+    ipc_origins: OriginPolicy,
+    work_around: Option<Arc<UriWorkAround>>,
+  ) -> Self {
+    let origin = Value::String(message_origin.to_owned()).to_string();
+    Self {
+      env,
+      webview,
+      runtime: RUNTIME
+        .replace("__ORIGIN__", &origin)
+        .replace("__CALLBACK__", CALLBACK_KEY),
+      has_runtime: Cell::new(false),
+      ipc_origins,
+      work_around,
+      namespaces: RefCell::new(HashMap::new()),
+      events: RefCell::new(None),
+      pending: RefCell::new(HashMap::new()),
+      streams: RefCell::new(HashMap::new()),
+      callbacks: RefCell::new(HashMap::new()),
+      ports: RefCell::new(HashMap::new()),
+      evaluations: RefCell::new(HashMap::new()),
+      next_id: Cell::new(1),
+      outbox: Outbox::default(),
+    }
+  }
+
+  /// The page runtime, registered as an initialization script.
+  pub(crate) fn runtime(&self) -> &str {
+    &self.runtime
+  }
+
+  /// Whether the page at `url` may post messages and emit events.
+  pub(crate) fn allows_ipc(&self, url: &str) -> bool {
+    self.ipc_origins.allows(&self.page_url(url))
+  }
+
+  /// Expose namespace `name`, installing it in the current document and in
+  /// every later one that `origins` allows.  Without `origins` the
+  /// namespace has the webview's IPC origins.  Replaces a namespace of the
+  /// same name.
+  pub(crate) fn expose(
+    &self,
+    name: String,
+    statics_json: String,
+    func_names: Vec<String>,
+    origins: Option<OriginPolicy>,
+    handler: FunctionRef<ExposeCallData, ()>,
+  ) -> std::result::Result<(), String> {
+    let namespace = Namespace {
+      statics_json,
+      func_names,
+      origins: origins.unwrap_or_else(|| self.ipc_origins.clone()),
+      handler,
+    };
+    let script = namespace.script(&name).map_err(|e| e.to_string())?;
+    let allowed = self
+      .current_url()
+      .is_some_and(|url| namespace.origins.allows(&url));
+    self.namespaces.borrow_mut().insert(name, namespace);
+    if !allowed {
+      return Ok(());
+    }
+    self.evaluate_with_runtime(&script)
+  }
+
+  /// Remove namespace `name` from the page and reject its pending calls.
+  /// Returns `false` when it was not exposed.
+  pub(crate) fn unexpose(&self, name: &str) -> std::result::Result<bool, String> {
+    if !self.namespaces.borrow().contains_key(name) {
+      return Ok(false);
+    }
+    self.close_streams(|_, open| open.ns == name);
+    self
+      .callbacks
+      .borrow_mut()
+      .retain(|_, callback| callback.ns != name);
+    self.namespaces.borrow_mut().remove(name);
+    self.reject_where(
+      |call| call.ns == name,
+      &ExposeOutcome::error("AbortError", format!("Namespace {} was unexposed", name)),
+    );
+    let name = serde_json::to_string(name).map_err(|e| e.to_string())?;
+    self.evaluate(&format!("delete window[{}];", name))?;
+    Ok(true)
+  }
+
+  /// Install the namespaces allowing `url` into a document that announced
+  /// itself ready.
+  fn install_all(&self, url: &str) {
+    let mut script = String::new();
+    for (name, namespace) in self.namespaces.borrow().iter() {
+      if !namespace.origins.allows(url) {
+        continue;
+      }
+      if let Ok(namespace) = namespace.script(name) {
+        script.push_str(&namespace);
+        script.push('\n');
+      }
+    }
+    script.push_str("window.__webviewjs__&&window.__webviewjs__.installed();");
+    let _ = self.evaluate(&script);
+  }
+
+  /// Handle an IPC message body sent by the page at `url`.  Returns `false`
+  /// when it is not a bridge message, so it can be forwarded to the user's
+  /// IPC handler.
+  pub(crate) fn handle_ipc(&self, body: &str, url: &str) -> bool {
+    let Ok(message) = serde_json::from_str::<Value>(body) else {
+      return false;
+    };
+    let url = self.page_url(url);
+    match message.get("__e").and_then(Value::as_str) {
+      Some("return") => self.return_stream(&message),
+      Some("emit") if self.ipc_origins.allows(&url) => self.dispatch_event(&message),
+      Some("emit") => {}
+      Some("port") if self.ipc_origins.allows(&url) => self.relay_port_message(&message),
+      Some("port-close") if self.ipc_origins.allows(&url) => self.close_port(&message),
+      Some("port" | "port-close") => {}
+      Some("ready") => {
+        self.has_runtime.set(true);
+        self.install_all(&url);
+      }
+      Some("result") => self.finish_evaluation(&message),
+      _ => return false,
+    }
+    true
+  }
+
+  /// Set the handler of the events emitted by the page.
+  pub(crate) fn set_event_handler(&self, handler: FunctionRef<PageEventData, ()>) {
+    self.events.replace(Some(handler));
+  }
+
+  /// Hand an event emitted by the page to the event handler.  Events are
+  /// dropped while there is none.
+  fn dispatch_event(&self, message: &Value) {
+    let Some(event) = message.get("event").and_then(Value::as_str) else {
+      return;
+    };
+    // Not borrowed during the call, which may dispose the webview.
+    let handler = match self.events.borrow().as_ref() {
+      Some(handler) => handler.borrow_back(&self.env),
+      None => return,
+    };
+    if let Ok(handler) = handler {
+      let _ = handler.call(PageEventData {
+        event: event.to_owned(),
+        payload_json: message.get("payload").map(Value::to_string),
+      });
+    }
+  }
+
+  /// Dispatch event `event` to the page's `listen` callbacks.  `payload_json`
+  /// must be valid JSON; without it the callbacks receive `undefined`.
+  pub(crate) fn emit(
+    &self,
+    event: &str,
+    payload_json: Option<&str>,
+  ) -> std::result::Result<(), String> {
+    let payload = payload_json
+      .map(serde_json::from_str)
+      .transpose()
+      .map_err(|e| format!("Invalid event payload: {}", e))?;
+    self.evaluate(&format!(
+      "window.__webviewjs__&&window.__webviewjs__.dispatch({})",
+      event_message(event, payload)
+    ))
+  }
+
+  pub(crate) fn has_port(&self, name: &str) -> bool {
+    self.ports.borrow().contains_key(name)
+  }
+
+  /// Connect port `name` of the page to a channel.
+  pub(crate) fn add_port(&self, name: &str, link: PortLink) {
+    self.ports.borrow_mut().insert(name.to_owned(), link);
+  }
+
+  /// Disconnect port `name`, dispatching `close` on it in the page.
+  pub(crate) fn remove_port(&self, name: &str) {
+    if self.ports.borrow_mut().remove(name).is_some() {
+      let _ = self.evaluate(&format!(
+        "window.__webviewjs__&&window.__webviewjs__.portClosed({})",
+        Value::String(name.to_owned())
+      ));
+    }
+  }
+
+  /// Dispatch a message relayed by a channel on port `name` of the page.
+  pub(crate) fn deliver_port_message(&self, name: &str, data_json: &str) {
+    let Ok(data) = serde_json::from_str::<Value>(data_json) else {
+      return;
+    };
+    let _ = self.evaluate(&format!(
+      "window.__webviewjs__&&window.__webviewjs__.portMessage({})",
+      json!({ "name": name, "data": data })
+    ));
+  }
+
+  /// The channel of the port a page message names.  Not borrowed while the
+  /// channel runs, as it reaches back into the bridge.
+  fn port_link(&self, message: &Value) -> Option<PortLink> {
+    let name = message.get("name").and_then(Value::as_str)?;
+    self.ports.borrow().get(name).cloned()
+  }
+
+  fn relay_port_message(&self, message: &Value) {
+    if let (Some(link), Some(data)) = (self.port_link(message), message.get("data")) {
+      link.channel.relay(link.side, data.to_string());
+    }
+  }
+
+  fn close_port(&self, message: &Value) {
+    if let Some(link) = self.port_link(message) {
+      link.channel.close();
+    }
+  }
+
+  /// Run `script` in the page; `handler` receives how it ended.  Returns
+  /// the id of the evaluation, for [`PageBridge::cancel_evaluation`].
+  pub(crate) fn evaluate_script(
+    &self,
+    script: &str,
+    handler: FunctionRef<EvaluationData, ()>,
+  ) -> std::result::Result<u64, String> {
+    if !self.is_attached() {
+      return Err("Webview has been disposed".to_owned());
+    }
+    let token = token()?;
+    let id = self.next_id.replace(self.next_id.get() + 1);
+    let arguments = format!("{},{}", id, Value::String(token.clone()));
+    self
+      .evaluations
+      .borrow_mut()
+      .insert(id, Evaluation { token, handler });
+    // An expression resolves with its value, and statements with what they
+    // return.  Each wrapping is a script of its own, as one that does not
+    // parse does not run at all.
+    let evaluated = self
+      .evaluate_with_runtime(&format!(
+        "window.__webviewjs__.evaluate({},async()=>(\n{}\n))",
+        arguments, script
+      ))
+      .and_then(|()| {
+        self.evaluate(&format!(
+          "window.__webviewjs__.evaluate({},async function(){{\n{}\n}})",
+          arguments, script
+        ))
+      })
+      .and_then(|()| self.evaluate(&format!("window.__webviewjs__.evaluated({})", arguments)));
+    match evaluated {
+      Ok(()) => Ok(id),
+      Err(e) => {
+        self.evaluations.borrow_mut().remove(&id);
+        Err(e)
+      }
+    }
+  }
+
+  /// Stop waiting for evaluation `id`; its result is ignored.  Returns
+  /// `false` when it already ended.
+  pub(crate) fn cancel_evaluation(&self, id: u64) -> bool {
+    self.evaluations.borrow_mut().remove(&id).is_some()
+  }
+
+  /// Hand the result the page reported for an evaluation to its handler.
+  fn finish_evaluation(&self, message: &Value) {
+    let (Some(id), Some(token)) = (
+      message.get("id").and_then(Value::as_u64),
+      message.get("token").and_then(Value::as_str),
+    ) else {
+      return;
+    };
+    let expected = self
+      .evaluations
+      .borrow()
+      .get(&id)
+      .is_some_and(|evaluation| evaluation.token == token);
+    if !expected {
+      return;
+    }
+    let error = message.get("error").map(|error| {
+      let field = |key: &str| error.get(key).and_then(Value::as_str).map(str::to_owned);
+      EvaluationError {
+        name: field("name").unwrap_or_else(|| "Error".to_owned()),
+        message: field("message").unwrap_or_default(),
+        stack: field("stack"),
+      }
+    });
+    let value_json = match error {
+      Some(_) => None,
+      None => message.get("value").map(Value::to_string),
+    };
+    self.end_evaluation(
+      id,
+      EvaluationData {
+        value_json,
+        error,
+        aborted: None,
+      },
+    );
+  }
+
+  /// Call the handler of evaluation `id`, which may dispose the webview, so
+  /// nothing is borrowed during the call.
+  fn end_evaluation(&self, id: u64, data: EvaluationData) {
+    let Some(evaluation) = self.evaluations.borrow_mut().remove(&id) else {
+      return;
+    };
+    if let Ok(handler) = evaluation.handler.borrow_back(&self.env) {
+      let _ = handler.call(data);
+    }
+  }
+
+  fn abort_evaluations(&self, message: &str) {
+    let ids: Vec<u64> = self.evaluations.borrow().keys().copied().collect();
+    for id in ids {
+      self.end_evaluation(
+        id,
+        EvaluationData {
+          value_json: None,
+          error: None,
+          aborted: Some(message.to_owned()),
+        },
+      );
+    }
+  }
+
+  /// Hand a call from the page at `url`, whose `Origin` is `origin`, to the
+  /// handler of its namespace.  A call with a `stream` pulls the next item
+  /// from that stream.
+  fn dispatch_call(&self, message: &Value, url: &str, origin: &str, respond: Respond) {
+    let field = |key: &str| {
+      message
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_owned()
+    };
+    let (ns, method, doc) = (field("ns"), field("method"), field("doc"));
+    let stream = message.get("stream").and_then(Value::as_u64);
+
+    let id = self.next_id.replace(self.next_id.get() + 1);
+    let open = stream.is_none_or(|stream| {
+      self
+        .streams
+        .borrow()
+        .get(&stream)
+        .is_some_and(|open| open.ns == ns && open.doc == doc)
+    });
+    self.pending.borrow_mut().insert(
+      id,
+      PendingCall {
+        ns: ns.clone(),
+        doc: doc.clone(),
+        stream,
+        respond,
+      },
+    );
+    if !open {
+      self.reply(id, ExposeOutcome::refused("Error", "The stream is closed"));
+      return;
+    }
+
+    let allowed = self
+      .namespaces
+      .borrow()
+      .get(&ns)
+      .map(|namespace| namespace.origins.allows(url) && namespace.origins.allows_origin(origin));
+    if allowed == Some(false) {
+      let message = format!("{} may not call {}", url, ns);
+      self.reply(id, ExposeOutcome::refused("PermissionDenied", message));
+      return;
+    }
+
+    let mut args = message.get("args").cloned().unwrap_or_else(|| json!([]));
+    let mut callbacks = Vec::new();
+    replace_callbacks(&mut args, &mut |page_id| {
+      let callback = self.next_id.replace(self.next_id.get() + 1);
+      self.callbacks.borrow_mut().insert(
+        callback,
+        PageCallback {
+          ns: ns.clone(),
+          doc: doc.clone(),
+          id: page_id,
+        },
+      );
+      callbacks.push(callback);
+      callback
+    });
+
+    let called = self.call_handler(ExposeCallData {
+      ns,
+      method,
+      id: id as f64,
+      args_json: args.to_string(),
+      stream: stream.map(|stream| stream as f64),
+      callbacks: (!callbacks.is_empty())
+        .then(|| callbacks.iter().map(|&callback| callback as f64).collect()),
+    });
+    if let Err(message) = called {
+      self
+        .callbacks
+        .borrow_mut()
+        .retain(|callback, _| !callbacks.contains(callback));
+      self.reply(id, ExposeOutcome::refused("Error", message));
+    }
+  }
+
+  /// Run page callback `id` with `args_json`, a JSON array.  Returns `false`
+  /// when it was released or its document is gone.
+  pub(crate) fn invoke_callback(
+    &self,
+    id: u64,
+    args_json: &str,
+  ) -> std::result::Result<bool, String> {
+    let args: Value =
+      serde_json::from_str(args_json).map_err(|e| format!("Invalid callback arguments: {}", e))?;
+    if !args.is_array() {
+      return Err("Callback arguments must be an array".to_owned());
+    }
+    let invocation = match self.callbacks.borrow().get(&id) {
+      Some(callback) => json!({ "doc": callback.doc, "id": callback.id, "args": args }),
+      None => return Ok(false),
+    };
+    self.evaluate(&format!(
+      "window.__webviewjs__&&window.__webviewjs__.invoke({})",
+      invocation
+    ))?;
+    Ok(true)
+  }
+
+  /// Let the page drop callback `id`.  Returns `false` when it was already
+  /// released or its document is gone.
+  pub(crate) fn release_callback(&self, id: u64) -> bool {
+    let Some(callback) = self.callbacks.borrow_mut().remove(&id) else {
+      return false;
+    };
+    let _ = self.evaluate(&format!(
+      "window.__webviewjs__&&window.__webviewjs__.release({})",
+      json!({ "doc": callback.doc, "id": callback.id })
+    ));
+    true
+  }
+
+  fn call_handler(&self, data: ExposeCallData) -> std::result::Result<(), String> {
+    let namespaces = self.namespaces.borrow();
+    let ns = data.ns.clone();
+    namespaces
+      .get(&ns)
+      .ok_or_else(|| format!("No such namespace: {}", ns))
+      .and_then(|namespace| {
+        namespace
+          .handler
+          .borrow_back(&self.env)
+          .map_err(|_| format!("Namespace {} is unavailable", ns))
+      })
+      .and_then(|handler| handler.call(data).map_err(|e| e.to_string()))
+  }
+
+  /// Settle call `id` with a new stream, which the page pulls items from
+  /// until it ends.  Returns its id, or `None` when the call is no longer
+  /// pending.
+  pub(crate) fn open_stream(&self, id: u64) -> Option<u64> {
+    let stream = self.next_id.replace(self.next_id.get() + 1);
+    self
+      .reply(id, ExposeOutcome::Stream(stream))
+      .then_some(stream)
+  }
+
+  /// Close a stream the page returned early.
+  fn return_stream(&self, message: &Value) {
+    let (Some(stream), Some(doc)) = (
+      message.get("stream").and_then(Value::as_u64),
+      message.get("doc").and_then(Value::as_str),
+    ) else {
+      return;
+    };
+    self.close_streams(|id, open| id == stream && open.doc == doc);
+  }
+
+  /// Close the matching streams and have Node end their iterators.
+  fn close_streams(&self, filter: impl Fn(u64, &OpenStream) -> bool) {
+    let closed: Vec<_> = {
+      let mut streams = self.streams.borrow_mut();
+      let ids: Vec<u64> = streams
+        .iter()
+        .filter(|(id, open)| filter(**id, open))
+        .map(|(id, _)| *id)
+        .collect();
+      ids
+        .into_iter()
+        .filter_map(|id| Some((id, streams.remove(&id)?)))
+        .collect()
+    };
+    for (stream, open) in closed {
+      let _ = self.call_handler(ExposeCallData {
+        ns: open.ns,
+        method: "return".to_owned(),
+        id: 0.0,
+        args_json: "[]".to_owned(),
+        stream: Some(stream as f64),
+        callbacks: None,
+      });
+    }
+  }
+
+  /// Settle call `id`.  Returns `false` when it is no longer pending, e.g.
+  /// because the page navigated away in the meantime.
+  pub(crate) fn reply(&self, id: u64, outcome: ExposeOutcome) -> bool {
+    let Some(call) = self.pending.borrow_mut().remove(&id) else {
+      return false;
+    };
+    match (&outcome, call.stream) {
+      (ExposeOutcome::Stream(stream), _) => {
+        let open = OpenStream {
+          ns: call.ns.clone(),
+          doc: call.doc.clone(),
+        };
+        self.streams.borrow_mut().insert(*stream, open);
+      }
+      // A stream ends with its last item or its first error.
+      (ExposeOutcome::Value(Some(item)), Some(_)) if item["done"] != true => {}
+      (_, Some(stream)) => {
+        self.streams.borrow_mut().remove(&stream);
+      }
+      (_, None) => {}
+    }
+    (call.respond)(call_response(&outcome));
+    true
+  }
+
+  /// Drop the state of the outgoing document when a new one starts loading.
+  pub(crate) fn navigated(&self) {
+    self.has_runtime.set(false);
+    self.reject_all("The page navigated before the call completed");
+    self.abort_evaluations("The page navigated before the script completed");
+    self.close_streams(|_, _| true);
+    self.callbacks.borrow_mut().clear();
+    self.outbox.clear();
+  }
+
+  /// Reject pending calls, abort evaluations, close channels and forget
+  /// every namespace.
+  pub(crate) fn dispose(&self) {
+    self.reject_all("The webview was disposed before the call completed");
+    self.abort_evaluations("The webview was disposed before the script completed");
+    self.close_streams(|_, _| true);
+    self.callbacks.borrow_mut().clear();
+    let ports = self.ports.take();
+    for link in ports.into_values() {
+      link.channel.close();
+    }
+    self.namespaces.borrow_mut().clear();
+    self.events.replace(None);
+    self.outbox.clear();
+  }
+
+  fn reject_all(&self, message: &str) {
+    self.reject_where(|_| true, &ExposeOutcome::error("AbortError", message));
+  }
+
+  fn reject_where(&self, filter: impl Fn(&PendingCall) -> bool, outcome: &ExposeOutcome) {
+    let calls: Vec<_> = {
+      let mut pending = self.pending.borrow_mut();
+      let ids: Vec<u64> = pending
+        .iter()
+        .filter(|(_, call)| filter(call))
+        .map(|(id, _)| *id)
+        .collect();
+      ids.iter().filter_map(|id| pending.remove(id)).collect()
+    };
+    for call in calls {
+      (call.respond)(call_response(outcome));
+    }
+  }
+
+  /// Dispatch `message` as a `message` event on `window.webviewjs`.
+  pub(crate) fn post_message(&self, message: PageMessage) -> std::result::Result<(), String> {
+    match message {
+      PageMessage::Text(text) => self.evaluate(&format!(
+        "window.__webviewjs__&&window.__webviewjs__.deliver({})",
+        Value::String(text)
+      )),
+      PageMessage::Binary(data) => {
+        let (id, key) = self.outbox.push(data)?;
+        self.evaluate(&format!(
+          "window.__webviewjs__&&window.__webviewjs__.pull({},{})",
+          id,
+          Value::String(key)
+        ))
+      }
+    }
+  }
+
+  /// Answer a request on [`MESSAGE_SCHEME`] with `respond`.  Calls and
+  /// messages posted by the page get the posting page's URL as their URI,
+  /// and are refused when the origins do not allow that page or `admit`
+  /// returns a limit.  Messages are passed on to `deliver`; calls are
+  /// answered once they settle.
+  pub(crate) fn message_request(
+    &self,
+    request: Request<Vec<u8>>,
+    respond: Respond,
+    admit: impl FnOnce(&Request<Vec<u8>>) -> std::result::Result<(), IpcLimit>,
+    deliver: impl FnOnce(Request<Vec<u8>>),
+  ) {
+    let allow_origin = self.allow_origin(&request);
+    if request.method() != Method::POST || request.uri().path() != "/call" {
+      respond(self.outbox.respond(request, allow_origin, |message| {
+        // The URL is claimed by the page; the browser sets `Origin`.
+        let origin = self.request_origin(&message);
+        let allowed = self.allows_ipc(&message.uri().to_string())
+          && origin.is_some_and(|origin| self.ipc_origins.allows_origin(&origin));
+        if !allowed {
+          return Err(StatusCode::FORBIDDEN);
+        }
+        admit(&message).map_err(limit_status)?;
+        deliver(message);
+        Ok(())
+      }));
+      return;
+    }
+
+    let respond: Respond = Box::new(move |mut response| {
+      if let Some(allow_origin) = allow_origin {
+        response
+          .headers_mut()
+          .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+      }
+      respond(response)
+    });
+    let call = posted(request);
+    let Some(origin) = self.request_origin(&call) else {
+      return respond(text_response(StatusCode::FORBIDDEN));
+    };
+    if let Err(limit) = admit(&call) {
+      return respond(text_response(limit_status(limit)));
+    }
+    match serde_json::from_slice::<Value>(call.body()) {
+      Ok(message) => {
+        let url = self.page_url(&call.uri().to_string());
+        self.dispatch_call(&message, &url, &origin, respond);
+      }
+      Err(_) => respond(text_response(StatusCode::BAD_REQUEST)),
+    }
+  }
+
+  /// `Access-Control-Allow-Origin` of the answer to `request`: its `Origin`,
+  /// when the IPC origins allow that page, or for calls, the origins of an
+  /// exposed namespace.  Other pages cannot read the answer.
+  fn allow_origin(&self, request: &Request<Vec<u8>>) -> Option<header::HeaderValue> {
+    let value = request.headers().get(header::ORIGIN)?;
+    let origin = self.page_url(value.to_str().ok()?);
+    let allowed = self.ipc_origins.allows_origin(&origin)
+      || (request.uri().path() == "/call"
+        && self
+          .namespaces
+          .borrow()
+          .values()
+          .any(|namespace| namespace.origins.allows_origin(&origin)));
+    allowed.then(|| value.clone())
+  }
+
+  /// The `Origin` a request on [`MESSAGE_SCHEME`] was sent from.
+  fn request_origin(&self, request: &Request<Vec<u8>>) -> Option<String> {
+    request
+      .headers()
+      .get(header::ORIGIN)
+      .and_then(|origin| origin.to_str().ok())
+      .map(|origin| self.page_url(origin))
+  }
+
+  /// `url` with the custom protocol work around reverted.
+  fn page_url(&self, url: &str) -> String {
+    self
+      .work_around
+      .as_ref()
+      .and_then(|work_around| work_around.revert(url))
+      .unwrap_or_else(|| url.to_owned())
+  }
+
+  /// Whether the webview still exists.
+  pub(crate) fn is_attached(&self) -> bool {
+    self.webview.borrow().is_some()
+  }
+
+  /// URL of the current document.
+  pub(crate) fn current_url(&self) -> Option<String> {
+    let url = self.webview.borrow().as_ref()?.url().ok()?;
+    Some(self.page_url(&url))
+  }
+
+  /// Run `script` once the runtime exists.  It normally does; it does not in
+  /// documents that were loaded before the webview existed, e.g. restored
+  /// from the back cache, which never report it ready.
+  fn evaluate_with_runtime(&self, script: &str) -> std::result::Result<(), String> {
+    if !self.has_runtime.get() {
+      self.evaluate(&self.runtime)?;
+    }
+    self.evaluate(script)
+  }
+
+  fn evaluate(&self, script: &str) -> std::result::Result<(), String> {
+    match self.webview.borrow().as_ref() {
+      Some(webview) => webview.evaluate_script(script).map_err(|e| e.to_string()),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Binary messages from Node, held until the page fetches them with the
+/// key it was given along with the id.
+#[derive(Default)]
+struct Outbox {
+  messages: RefCell<HashMap<u64, (String, Vec<u8>)>>,
+  next_id: Cell<u64>,
+}
+
+impl Outbox {
+  /// Hold `data`; returns its id and key.
+  fn push(&self, data: Vec<u8>) -> std::result::Result<(u64, String), String> {
+    let key = token()?;
+    let id = self.next_id.get() + 1;
+    self.next_id.set(id);
+    self.messages.borrow_mut().insert(id, (key.clone(), data));
+    Ok((id, key))
+  }
+
+  /// Take the message `id`, if `key` is its key.
+  fn take(&self, id: u64, key: &str) -> Option<Vec<u8>> {
+    let mut messages = self.messages.borrow_mut();
+    if messages.get(&id)?.0 != key {
+      return None;
+    }
+    messages.remove(&id).map(|(_, data)| data)
+  }
+
+  fn clear(&self) {
+    self.messages.borrow_mut().clear();
+  }
+
+  /// Answer a request on [`MESSAGE_SCHEME`] from a page `allow_origin`
+  /// says may read the answer; without it, pushed messages are refused.
+  /// `deliver` returns the status refusing a posted message.
+  fn respond(
+    &self,
+    request: Request<Vec<u8>>,
+    allow_origin: Option<header::HeaderValue>,
+    deliver: impl FnOnce(Request<Vec<u8>>) -> std::result::Result<(), StatusCode>,
+  ) -> ProtocolResponse {
+    let path = request.uri().path().to_owned();
+    let mut response = match (request.method(), path.as_str()) {
+      (&Method::OPTIONS, _) => {
+        let mut response = Response::builder()
+          .status(StatusCode::NO_CONTENT)
+          .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST")
+          .header(header::ACCESS_CONTROL_MAX_AGE, "600");
+        if let Some(headers) = request
+          .headers()
+          .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        {
+          response = response.header(header::ACCESS_CONTROL_ALLOW_HEADERS, headers);
+        }
+        response
+          .body(Cow::Borrowed(&[][..]))
+          .expect("preflight response is valid")
+      }
+      (&Method::POST, "/message") => match deliver(posted(request)) {
+        Ok(()) => Response::builder()
+          .status(StatusCode::NO_CONTENT)
+          .body(Cow::Borrowed(&[][..]))
+          .expect("empty response is valid"),
+        Err(status) => text_response(status),
+      },
+      (&Method::GET, _) if allow_origin.is_none() => text_response(StatusCode::FORBIDDEN),
+      (&Method::GET, path) => {
+        let key = request
+          .uri()
+          .query()
+          .and_then(|query| query.strip_prefix("key="))
+          .unwrap_or_default();
+        let data = path
+          .strip_prefix("/push/")
+          .and_then(|id| id.parse().ok())
+          .and_then(|id| self.take(id, key));
+        match data {
+          Some(data) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(Cow::Owned(data))
+            .expect("message response is valid"),
+          None => text_response(StatusCode::NOT_FOUND),
+        }
+      }
+      _ => text_response(StatusCode::METHOD_NOT_ALLOWED),
+    };
+    // The scheme is cross-origin to every page, so answers carry CORS
+    // headers.
+    if let Some(origin) = allow_origin {
+      response
+        .headers_mut()
+        .insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    }
+    response
+  }
+}
+
+/// A request posted by the page, with its URI set to the page URL in its
+/// `url` query parameter.
+fn posted(request: Request<Vec<u8>>) -> Request<Vec<u8>> {
+  let url = request
+    .uri()
+    .query()
+    .and_then(|query| query.strip_prefix("url="))
+    .and_then(|url| percent_decode(url)?.parse().ok());
+  let (mut parts, body) = request.into_parts();
+  if let Some(url) = url {
+    parts.uri = url;
+  }
+  Request::from_parts(parts, body)
+}
+
+fn limit_status(limit: IpcLimit) -> StatusCode {
+  match limit {
+    IpcLimit::Size => StatusCode::PAYLOAD_TOO_LARGE,
+    IpcLimit::Rate => StatusCode::TOO_MANY_REQUESTS,
+  }
+}
+
+/// A token pages cannot guess, read from the OS's random source.
+fn token() -> std::result::Result<String, String> {
+  let mut bytes = [0u8; 16];
+  getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate a token: {}", e))?;
+  Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Script that creates `window[name]` from its static values and stubs for
+/// its functions.
+fn namespace_script(
+  name: &str,
+  statics_json: &str,
+  func_names: &[String],
+) -> serde_json::Result<String> {
+  Ok(format!(
+    r#"(function(){{
+  const __ns=Object.assign({{}},{statics});
+  for(const m of {funcs}){{
+    __ns[m]=function(){{return window.__webviewjs__.call({name},m,Array.from(arguments));}};
+  }}
+  window[{name}]=__ns;
+}})();"#,
+    statics = statics_json,
+    funcs = serde_json::to_string(func_names)?,
+    name = serde_json::to_string(name)?,
+  ))
+}
+
+/// The argument of the page's `dispatch()`.
+fn event_message(event: &str, payload: Option<Value>) -> Value {
+  let mut message = json!({ "event": event });
+  if let Some(payload) = payload {
+    message["payload"] = payload;
+  }
+  message
+}
+
+/// Replace the callback markers in `value` with the ids `register` returns
+/// for their page-side ids.
+fn replace_callbacks(value: &mut Value, register: &mut impl FnMut(f64) -> u64) {
+  match value {
+    Value::Array(items) => {
+      for item in items {
+        replace_callbacks(item, register);
+      }
+    }
+    Value::Object(fields) => {
+      let marker = match fields.get(CALLBACK_KEY).and_then(Value::as_f64) {
+        Some(id) if fields.len() == 1 => Some(id),
+        _ => None,
+      };
+      match marker {
+        Some(id) => {
+          fields.insert(CALLBACK_KEY.to_owned(), json!(register(id)));
+        }
+        None => {
+          for field in fields.values_mut() {
+            replace_callbacks(field, register);
+          }
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+/// The response to a call: its outcome as JSON.
+fn call_response(outcome: &ExposeOutcome) -> ProtocolResponse {
+  let reply = match outcome {
+    ExposeOutcome::Value(value) => {
+      let mut reply = json!({ "ok": true });
+      if let Some(value) = value {
+        reply["value"] = value.clone();
+      }
+      reply
+    }
+    ExposeOutcome::Error { name, message } => json!({
+      "ok": false,
+      "error": { "name": name, "message": message },
+    }),
+    ExposeOutcome::Refused { name, message } => json!({
+      "ok": false,
+      "error": { "name": name, "message": message },
+      "released": true,
+    }),
+    ExposeOutcome::Stream(stream) => json!({ "ok": true, "stream": stream }),
+  };
+  Response::builder()
+    .header(header::CONTENT_TYPE, "application/json")
+    .header(header::CACHE_CONTROL, "no-store")
+    .body(Cow::Owned(reply.to_string().into_bytes()))
+    .expect("call response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn call_responses_carry_the_outcome() {
+    let reply = |outcome| {
+      let response = call_response(&outcome);
+      assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+      serde_json::from_slice::<Value>(response.body()).unwrap()
+    };
+    assert_eq!(
+      reply(ExposeOutcome::Value(Some(json!({ "a": [1] })))),
+      json!({ "ok": true, "value": { "a": [1] } })
+    );
+    assert_eq!(reply(ExposeOutcome::Value(None)), json!({ "ok": true }));
+    assert_eq!(
+      reply(ExposeOutcome::error("AbortError", "gone")),
+      json!({ "ok": false, "error": { "name": "AbortError", "message": "gone" } })
+    );
+    assert_eq!(
+      reply(ExposeOutcome::refused("PermissionDenied", "no")),
+      json!({
+        "ok": false,
+        "error": { "name": "PermissionDenied", "message": "no" },
+        "released": true,
+      })
+    );
+    assert_eq!(
+      reply(ExposeOutcome::Stream(3)),
+      json!({ "ok": true, "stream": 3 })
+    );
+  }
+
+  #[test]
+  fn message_scheme_matches_any_case() {
+    assert!(is_message_scheme("webviewjs-ipc"));
+    assert!(is_message_scheme("WebviewJS-IPC"));
+    assert!(!is_message_scheme("webviewjs"));
+  }
+
+  #[test]
+  fn tokens_are_unique() {
+    let (first, second) = (token().unwrap(), token().unwrap());
+    assert_eq!(first.len(), 32);
+    assert_ne!(first, second);
+  }
+
+  #[test]
+  fn callbacks_get_native_ids() {
+    let mut args = json!([
+      "/tmp",
+      { "__webviewjsCallback": 1 },
+      { "options": { "onError": { "__webviewjsCallback": 2 } } },
+      { "__webviewjsCallback": 3, "other": true },
+    ]);
+    let mut seen = Vec::new();
+    replace_callbacks(&mut args, &mut |id| {
+      seen.push(id);
+      seen.len() as u64 + 10
+    });
+    assert_eq!(seen, [1.0, 2.0]);
+    assert_eq!(
+      args,
+      json!([
+        "/tmp",
+        { "__webviewjsCallback": 11 },
+        { "options": { "onError": { "__webviewjsCallback": 12 } } },
+        { "__webviewjsCallback": 3, "other": true },
+      ])
+    );
+  }
+
+  #[test]
+  fn event_messages_omit_missing_payloads() {
+    assert_eq!(
+      event_message("saved\"", Some(json!({ "path": "');alert(1);//" }))).to_string(),
+      r#"{"event":"saved\"","payload":{"path":"');alert(1);//"}}"#
+    );
+    assert_eq!(event_message("closed", None), json!({ "event": "closed" }));
+  }
+
+  fn request(method: &str, uri: &str) -> Request<Vec<u8>> {
+    Request::builder()
+      .method(method)
+      .uri(uri)
+      .header(header::ORIGIN, "app://localhost")
+      .body(b"\x00\x01\xff".to_vec())
+      .unwrap()
+  }
+
+  fn page_origin() -> Option<header::HeaderValue> {
+    Some(header::HeaderValue::from_static("app://localhost"))
+  }
+
+  #[test]
+  fn outbox_receives_page_messages() {
+    let outbox = Outbox::default();
+    let mut received = None;
+    let response = outbox.respond(
+      request(
+        "POST",
+        "webviewjs-ipc://localhost/message?url=app%3A%2F%2Flocalhost%2Findex.html%3Fa%3D1",
+      ),
+      page_origin(),
+      |message| {
+        received = Some(message);
+        Ok(())
+      },
+    );
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+      response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "app://localhost"
+    );
+    let received = received.unwrap();
+    assert_eq!(received.uri(), "app://localhost/index.html?a=1");
+    assert_eq!(received.body(), b"\x00\x01\xff");
+  }
+
+  #[test]
+  fn outbox_refuses_messages_the_page_may_not_post() {
+    let response = Outbox::default().respond(
+      request(
+        "POST",
+        "webviewjs-ipc://localhost/message?url=https%3A%2F%2Fevil.test%2F",
+      ),
+      None,
+      |_| Err(StatusCode::FORBIDDEN),
+    );
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(!response
+      .headers()
+      .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    let response = Outbox::default().respond(
+      request("POST", "webviewjs-ipc://localhost/message"),
+      page_origin(),
+      |_| Err(StatusCode::TOO_MANY_REQUESTS),
+    );
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+  }
+
+  #[test]
+  fn outbox_serves_pushed_messages_once() {
+    let outbox = Outbox::default();
+    let (id, key) = outbox.push(vec![1, 2, 3]).unwrap();
+    let uri = format!("webviewjs-ipc://localhost/push/{}?key={}", id, key);
+    let unused = |_| panic!("nothing is posted");
+    let get = |uri: &str, origin| outbox.respond(request("GET", uri), origin, unused).status();
+
+    // Other origins, and requests without the key, are refused.
+    assert_eq!(get(&uri, None), StatusCode::FORBIDDEN);
+    let guessed = format!("webviewjs-ipc://localhost/push/{}", id);
+    assert_eq!(get(&guessed, page_origin()), StatusCode::NOT_FOUND);
+    let wrong = format!("{}?key=0", guessed);
+    assert_eq!(get(&wrong, page_origin()), StatusCode::NOT_FOUND);
+
+    let response = outbox.respond(request("GET", &uri), page_origin(), unused);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().as_ref(), [1, 2, 3]);
+    assert_eq!(get(&uri, page_origin()), StatusCode::NOT_FOUND);
+
+    let preflight = outbox.respond(
+      request("OPTIONS", "webviewjs-ipc://localhost/message"),
+      page_origin(),
+      unused,
+    );
+    assert_eq!(preflight.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+      preflight.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "app://localhost"
+    );
+  }
+
+  #[test]
+  fn namespace_scripts_quote_names() {
+    let funcs = vec!["add".to_owned(), "a\"b".to_owned()];
+    let script = r#"(function(){
+  const __ns=Object.assign({},{"v":1});
+  for(const m of ["add","a\"b"]){
+    __ns[m]=function(){return window.__webviewjs__.call("api",m,Array.from(arguments));};
+  }
+  window["api"]=__ns;
+})();"#;
+    assert_eq!(
+      namespace_script("api", "{\"v\":1}", &funcs).unwrap(),
+      script
+    );
+  }
+}
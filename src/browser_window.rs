@@ -13,6 +13,7 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 use tao::{
   dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
   event_loop::EventLoop,
@@ -22,12 +23,20 @@ use tao::{
 #[cfg(target_os = "windows")]
 use tao::platform::windows::WindowExtWindows;
 
+use crate::assets::{AssetArchive, AssetSite, FileResponse, SiteOptions, StaticDirectory};
+use crate::ipc_limits::IpcQueues;
 #[cfg(not(target_os = "android"))]
 use crate::menu::{create_menu_from_options, init_menu_for_window};
+use crate::protocol::{
+  fail_requests, HeaderPolicy, PendingProtocol, PendingRequest, ProtocolHandler,
+};
+use crate::proxy::HttpProxy;
+use crate::router::{ProtocolRouter, Route, RouteTarget};
 use crate::webview::{
-  JsWebview, ProtocolCounterRef, ProtocolHandlerRef, ProtocolPendingMap, WebviewBoolHandlerRef,
+  JsWebview, PageBridges, ProtocolCounterRef, ProtocolPendingMap, WebviewBoolHandlerRef,
   WebviewEventHandlerRef, WebviewResource,
 };
+use crate::workers::WorkerPool;
 
 #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 fn decode_icon(
@@ -112,22 +121,19 @@ pub struct BrowserWindow {
   #[cfg(not(target_os = "android"))]
   window_menu: Option<Menu>,
   webviews: Rc<RefCell<Vec<WebviewResource>>>,
+  page_bridges: PageBridges,
   event_handler: Rc<RefCell<Option<FunctionRef<WindowEventPayload, ()>>>>,
   pending_protocols: Vec<PendingProtocol>,
   protocol_next_id: ProtocolCounterRef,
+  protocol_requests: ProtocolPendingMap,
+  ipc_queues: IpcQueues,
   pending_webview_event_handler: WebviewEventHandlerRef,
   pending_nav_handler: WebviewBoolHandlerRef,
   disposed: Rc<Cell<bool>>,
   webview_lifecycles: Rc<RefCell<Vec<Rc<Cell<bool>>>>>,
+  workers: Arc<WorkerPool>,
 }
 
-type PendingProtocol = (
-  String,
-  ProtocolHandlerRef,
-  ProtocolPendingMap,
-  ProtocolCounterRef,
-);
-
 #[napi]
 impl BrowserWindow {
   pub fn new(
@@ -136,6 +142,7 @@ impl BrowserWindow {
     child: bool,
     #[cfg(not(target_os = "android"))] global_menu: Rc<RefCell<Option<Menu>>>,
     #[cfg(target_os = "android")] _global_menu: Rc<RefCell<Option<()>>>,
+    workers: Arc<WorkerPool>,
   ) -> Result<Self> {
     let options = options.unwrap_or_default();
 
@@ -343,13 +350,17 @@ impl BrowserWindow {
       #[cfg(not(target_os = "android"))]
       window_menu,
       webviews: Rc::new(RefCell::new(Vec::new())),
+      page_bridges: Rc::new(RefCell::new(Vec::new())),
       event_handler: Rc::new(RefCell::new(None)),
       pending_protocols: Vec::new(),
       protocol_next_id: Rc::new(RefCell::new(0)),
+      protocol_requests: Rc::new(RefCell::new(std::collections::HashMap::new())),
+      ipc_queues: Rc::new(RefCell::new(Vec::new())),
       pending_webview_event_handler: Rc::new(RefCell::new(None)),
       pending_nav_handler: Rc::new(RefCell::new(None)),
       disposed: Rc::new(Cell::new(false)),
       webview_lifecycles: Rc::new(RefCell::new(Vec::new())),
+      workers,
     })
   }
 
@@ -357,6 +368,10 @@ impl BrowserWindow {
     Rc::clone(&self.webviews)
   }
 
+  pub(crate) fn page_bridges_shared(&self) -> PageBridges {
+    Rc::clone(&self.page_bridges)
+  }
+
   pub(crate) fn lifecycle_shared(&self) -> Rc<Cell<bool>> {
     Rc::clone(&self.disposed)
   }
@@ -365,30 +380,299 @@ impl BrowserWindow {
     Rc::clone(&self.webview_lifecycles)
   }
 
+  pub(crate) fn protocol_requests_shared(&self) -> ProtocolPendingMap {
+    Rc::clone(&self.protocol_requests)
+  }
+
+  pub(crate) fn ipc_queues_shared(&self) -> IpcQueues {
+    Rc::clone(&self.ipc_queues)
+  }
+
   #[napi(js_name = "_registerProtocol")]
-  pub fn register_protocol_raw(&mut self, name: String, handler: FunctionRef<String, ()>) {
-    self.pending_protocols.push((
+  pub fn register_protocol_raw(
+    &mut self,
+    name: String,
+    handler: FunctionRef<CustomProtocolRequest, ()>,
+    options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    self.push_protocol(
       name,
-      Rc::new(RefCell::new(Some(handler))),
-      Rc::new(RefCell::new(std::collections::HashMap::new())),
-      Rc::clone(&self.protocol_next_id),
-    ));
+      ProtocolHandler::Js(Rc::new(RefCell::new(Some(handler)))),
+      options,
+    )
+  }
+
+  /// Serve a custom protocol natively from a local directory.
+  ///
+  /// Requests are answered in Rust without a round-trip through JS.  Must be
+  /// called before `createWebview()`.
+  #[napi]
+  pub fn register_static_protocol(
+    &mut self,
+    name: String,
+    options: StaticProtocolOptions,
+    protocol_options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    let site = static_site(&name, options)?;
+    self.push_protocol(
+      name,
+      ProtocolHandler::Native(Arc::new(site)),
+      protocol_options,
+    )
+  }
+
+  /// Serve a custom protocol natively from a zip, tar or tar.gz archive,
+  /// loaded from a path or a `Buffer`.
+  ///
+  /// The archive is decompressed into memory once; nothing is unpacked to
+  /// disk.  Must be called before `createWebview()`.
+  #[napi]
+  pub fn register_archive_protocol(
+    &mut self,
+    name: String,
+    options: ArchiveProtocolOptions,
+    protocol_options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    let site = archive_site(&name, options)?;
+    self.push_protocol(
+      name,
+      ProtocolHandler::Native(Arc::new(site)),
+      protocol_options,
+    )
   }
 
+  /// Forward a custom protocol to a local HTTP server, e.g. a dev server,
+  /// so the page keeps its production origin during development.
+  ///
+  /// Method, headers and body are passed through and the response mapped
+  /// back; redirects to the upstream are rewritten to the scheme.  Must be
+  /// called before `createWebview()`.
+  #[napi]
+  pub fn register_proxy_protocol(
+    &mut self,
+    name: String,
+    options: ProxyProtocolOptions,
+    protocol_options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    let proxy = proxy_site(&name, options)?;
+    self.push_protocol(
+      name,
+      ProtocolHandler::Native(Arc::new(proxy)),
+      protocol_options,
+    )
+  }
+
+  #[napi(js_name = "_registerProtocolRouter")]
+  pub fn register_protocol_router_raw(
+    &mut self,
+    name: String,
+    routes: Vec<ProtocolRoute>,
+    handler: FunctionRef<CustomProtocolRequest, ()>,
+    options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    let routes = routes
+      .into_iter()
+      .map(|route| {
+        let target = match (
+          route.handler,
+          route.directory,
+          route.archive,
+          route.proxy,
+          route.redirect,
+        ) {
+          (Some(index), None, None, None, None) => RouteTarget::Js(index),
+          (None, Some(directory), None, None, None) => {
+            RouteTarget::Native(Arc::new(static_site(&name, directory)?))
+          }
+          (None, None, Some(archive), None, None) => {
+            RouteTarget::Native(Arc::new(archive_site(&name, archive)?))
+          }
+          (None, None, None, Some(proxy), None) => {
+            RouteTarget::Native(Arc::new(proxy_site(&name, proxy)?))
+          }
+          (None, None, None, None, Some(location)) => RouteTarget::Redirect {
+            location,
+            status: wry::http::StatusCode::from_u16(route.status.unwrap_or(302))
+              .ok()
+              .filter(wry::http::StatusCode::is_redirection)
+              .ok_or_else(|| {
+                napi::Error::new(
+                  napi::Status::InvalidArg,
+                  format!(
+                    "Route {} of protocol {}: invalid redirect status",
+                    route.path, name
+                  ),
+                )
+              })?,
+          },
+          _ => {
+            return Err(napi::Error::new(
+              napi::Status::InvalidArg,
+              format!(
+                "Route {} of protocol {}: exactly one of `handler`, `directory`, `archive`, `proxy` and `redirect` is required",
+                route.path, name
+              ),
+            ))
+          }
+        };
+        Route::new(route.host, &route.path, target)
+          .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))
+      })
+      .collect::<Result<Vec<_>>>()?;
+    self.push_protocol(
+      name,
+      ProtocolHandler::Router(
+        Arc::new(ProtocolRouter::new(routes)),
+        Rc::new(RefCell::new(Some(handler))),
+      ),
+      options,
+    )
+  }
+
+  fn push_protocol(
+    &mut self,
+    name: String,
+    handler: ProtocolHandler,
+    options: Option<ProtocolOptions>,
+  ) -> Result<()> {
+    if crate::bridge::is_message_scheme(&name) {
+      return Err(napi::Error::new(
+        napi::Status::InvalidArg,
+        format!(
+          "Protocol name {} is reserved for messages between pages and Node",
+          name
+        ),
+      ));
+    }
+    let (timeout, policy) = match options {
+      Some(options) => (options.timeout, options.policy),
+      None => (None, None),
+    };
+    let policy = policy
+      .map(HeaderPolicy::new)
+      .transpose()
+      .map_err(|e| {
+        napi::Error::new(
+          napi::Status::InvalidArg,
+          format!("Header policy of protocol {}: {}", name, e),
+        )
+      })?
+      .map(Arc::new);
+    self.pending_protocols.push(PendingProtocol {
+      name,
+      handler,
+      responders: Rc::clone(&self.protocol_requests),
+      counter: Rc::clone(&self.protocol_next_id),
+      timeout: timeout.map(|ms| Duration::from_millis(ms as u64)),
+      policy,
+      workers: Arc::clone(&self.workers),
+    });
+    Ok(())
+  }
+
+  /// Answer a pending request with a complete response.  wry's responder
+  /// takes the body in one piece, so there is no chunked variant.
   #[napi(js_name = "_completeProtocol")]
   pub fn complete_protocol(&self, id: f64, response: CustomProtocolResponse) -> Result<()> {
-    let id = id as u64;
-    for (_, _, responders, _) in &self.pending_protocols {
-      let mut map = responders.borrow_mut();
-      if let Some(responder) = map.remove(&id) {
-        let http = build_wry_response(response)?;
-        responder.respond(http);
-        return Ok(());
-      }
+    let pending = self.protocol_requests.borrow_mut().remove(&(id as u64));
+    if let Some(pending) = pending {
+      let http = build_wry_response(response)?;
+      pending.respond(http);
     }
     Ok(())
   }
 
+  /// Answer a pending request with a file, read and range-sliced natively on
+  /// a worker thread.
+  #[napi(js_name = "_completeProtocolFile")]
+  pub fn complete_protocol_file(&self, id: f64, response: ProtocolFileResponse) -> Result<()> {
+    let invalid = |message: String| napi::Error::new(napi::Status::InvalidArg, message);
+    let status = wry::http::StatusCode::from_u16(response.status_code.unwrap_or(200))
+      .map_err(|e| invalid(format!("Invalid protocol response status: {}", e)))?;
+    let headers = response
+      .headers
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|h| Some((h.key, h.value?)))
+      .map(|(key, value)| {
+        Ok((
+          wry::http::HeaderName::from_bytes(key.as_bytes())
+            .map_err(|e| invalid(format!("Invalid protocol response header {}: {}", key, e)))?,
+          wry::http::HeaderValue::from_str(&value)
+            .map_err(|e| invalid(format!("Invalid protocol response header {}: {}", key, e)))?,
+        ))
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let file = FileResponse {
+      path: response.path.into(),
+      offset: response.offset.unwrap_or(0.0).max(0.0) as u64,
+      length: response.length.map(|length| length.max(0.0) as u64),
+      status,
+      mime_type: response.mime_type,
+      headers,
+    };
+
+    let Some(pending) = self.protocol_requests.borrow_mut().remove(&(id as u64)) else {
+      return Ok(());
+    };
+    let PendingRequest {
+      responder,
+      url,
+      method,
+      headers,
+      ..
+    } = pending;
+    let mut request = wry::http::Request::builder()
+      .method(method.as_str())
+      .uri(url)
+      .body(Vec::new())
+      .unwrap_or_default();
+    *request.headers_mut() = headers;
+    self
+      .workers
+      .execute(move || responder.respond(file.respond(&request)));
+    Ok(())
+  }
+
+  /// List the custom-protocol requests still waiting for a JS response.
+  #[napi]
+  pub fn pending_protocol_requests(&self) -> Vec<ProtocolRequestInfo> {
+    let mut requests: Vec<ProtocolRequestInfo> = self
+      .protocol_requests
+      .borrow()
+      .iter()
+      .map(|(id, pending)| ProtocolRequestInfo {
+        id: *id as f64,
+        scheme: pending.scheme.clone(),
+        url: pending.url.clone(),
+        method: pending.method.clone(),
+        elapsed_ms: pending.started.elapsed().as_secs_f64() * 1000.0,
+      })
+      .collect();
+    requests.sort_by(|a, b| a.id.total_cmp(&b.id));
+    requests
+  }
+
+  /// Answer an in-flight custom-protocol request with an error status
+  /// (default `503`).  Returns `false` if the request is no longer pending.
+  #[napi]
+  pub fn cancel_protocol_request(&self, id: f64, status_code: Option<u16>) -> Result<bool> {
+    let status = wry::http::StatusCode::from_u16(status_code.unwrap_or(503)).map_err(|e| {
+      napi::Error::new(
+        napi::Status::InvalidArg,
+        format!("Invalid status code: {}", e),
+      )
+    })?;
+    let pending = self.protocol_requests.borrow_mut().remove(&(id as u64));
+    Ok(match pending {
+      Some(pending) => {
+        pending.fail(status);
+        true
+      }
+      None => false,
+    })
+  }
+
   #[napi]
   pub fn create_webview(
     &mut self,
@@ -412,6 +696,8 @@ impl BrowserWindow {
       options.unwrap_or_default(),
       web_context,
       &self.pending_protocols,
+      &self.protocol_requests,
+      &self.ipc_queues,
       event_handler,
       nav_handler,
     )?;
@@ -419,6 +705,10 @@ impl BrowserWindow {
       .webviews
       .borrow_mut()
       .push(Rc::clone(&webview.webview_inner));
+    self
+      .page_bridges
+      .borrow_mut()
+      .push((webview.id(), Rc::clone(webview.bridge())));
     self
       .webview_lifecycles
       .borrow_mut()
@@ -703,6 +993,12 @@ impl BrowserWindow {
     if self.disposed.replace(true) {
       return;
     }
+    // Answer in-flight protocol requests while their webviews still exist.
+    fail_requests(
+      &self.protocol_requests,
+      wry::http::StatusCode::SERVICE_UNAVAILABLE,
+      |_| true,
+    );
     self.window.set_visible(false);
     for resource in self.webviews.borrow().iter() {
       if let Some(webview) = resource.borrow_mut().take() {
@@ -710,16 +1006,21 @@ impl BrowserWindow {
       }
     }
     self.webviews.borrow_mut().clear();
+    self.page_bridges.borrow_mut().clear();
     for lifecycle in self.webview_lifecycles.borrow().iter() {
       lifecycle.set(true);
     }
     self.webview_lifecycles.borrow_mut().clear();
+    for queue in self.ipc_queues.borrow_mut().drain(..) {
+      queue.close();
+    }
     self.event_handler.borrow_mut().take();
     self.pending_webview_event_handler.borrow_mut().take();
     self.pending_nav_handler.borrow_mut().take();
-    for (_, handler, responders, _) in &self.pending_protocols {
-      handler.borrow_mut().take();
-      responders.borrow_mut().clear();
+    for protocol in &self.pending_protocols {
+      if let ProtocolHandler::Js(handler) = &protocol.handler {
+        handler.borrow_mut().take();
+      }
     }
     self.pending_protocols.clear();
     #[cfg(not(target_os = "android"))]
@@ -1390,6 +1691,65 @@ pub(crate) fn next_protocol_id(counter: &ProtocolCounterRef) -> u64 {
   id
 }
 
+/// Build the native site of `BrowserWindow.registerStaticProtocol()`.
+fn static_site(name: &str, options: StaticProtocolOptions) -> Result<AssetSite<StaticDirectory>> {
+  let directory = StaticDirectory::new(&options.root).map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!(
+        "Cannot serve protocol {} from {}: {}",
+        name, options.root, e
+      ),
+    )
+  })?;
+  Ok(AssetSite::new(
+    directory,
+    SiteOptions::new(options.index, options.spa_fallback, options.headers),
+  ))
+}
+
+/// Build the native site of `BrowserWindow.registerArchiveProtocol()`.
+fn archive_site(name: &str, options: ArchiveProtocolOptions) -> Result<AssetSite<AssetArchive>> {
+  let err = |message: String| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("Cannot serve protocol {}: {}", name, message),
+    )
+  };
+  let data = match (&options.path, &options.data) {
+    (Some(path), None) => std::fs::read(path).map_err(|e| err(format!("{}: {}", path, e)))?,
+    (None, Some(data)) => data.to_vec(),
+    _ => {
+      return Err(err(
+        "exactly one of `path` and `data` is required".to_owned(),
+      ))
+    }
+  };
+  let archive =
+    AssetArchive::load(data, options.root.as_deref()).map_err(|e| err(e.to_string()))?;
+  Ok(AssetSite::new(
+    archive,
+    SiteOptions::new(options.index, options.spa_fallback, options.headers),
+  ))
+}
+
+/// Build the native handler of `BrowserWindow.registerProxyProtocol()`.
+fn proxy_site(name: &str, options: ProxyProtocolOptions) -> Result<HttpProxy> {
+  let headers = options
+    .headers
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|h| h.value.map(|value| (h.key, value)))
+    .collect();
+  let timeout = std::time::Duration::from_millis(options.timeout.unwrap_or(30_000).into());
+  HttpProxy::new(&options.upstream, timeout, headers).map_err(|e| {
+    napi::Error::new(
+      napi::Status::InvalidArg,
+      format!("Cannot proxy protocol {}: {}", name, e),
+    )
+  })
+}
+
 fn monitor_to_js(m: tao::monitor::MonitorHandle) -> Monitor {
   Monitor {
     name: m.name(),
@@ -0,0 +1,186 @@
+//! Message channels between the pages of two webviews.
+//!
+//! Node creates a channel and connects it to two webviews under a port name.
+//! Each page reaches its end with `window.webviewjs.port(name)`; messages
+//! posted there are relayed here to the page at the other end, without a
+//! round trip through JavaScript.  Node may observe the messages and close
+//! the channel, which either page can do too.  Disposing a webview closes
+//! its channels.
+
+use std::{
+  cell::{Cell, RefCell},
+  rc::{Rc, Weak},
+};
+
+use napi::{bindgen_prelude::FunctionRef, Env, Result};
+use napi_derive::napi;
+
+use crate::bridge::PageBridge;
+use crate::types::ChannelEventData;
+use crate::webview::JsWebview;
+
+/// One end of a channel, as seen from the webview holding it.
+#[derive(Clone)]
+pub(crate) struct PortLink {
+  pub(crate) channel: Rc<ChannelState>,
+  /// 0 for the first webview, 1 for the second.
+  pub(crate) side: usize,
+}
+
+/// State shared by a channel and the webviews it connects.
+pub(crate) struct ChannelState {
+  env: Env,
+  /// The bridge of each webview and its port name, once connected.
+  ends: RefCell<Vec<(Weak<PageBridge>, String)>>,
+  observer: RefCell<Option<FunctionRef<ChannelEventData, ()>>>,
+  closed: Cell<bool>,
+}
+
+impl ChannelState {
+  /// Relay a message posted by the page at `from` to the other end.
+  pub(crate) fn relay(&self, from: usize, data_json: String) {
+    if self.closed.get() {
+      return;
+    }
+    let target = self
+      .ends
+      .borrow()
+      .get(1 - from)
+      .and_then(|(bridge, name)| Some((bridge.upgrade()?, name.clone())));
+    if let Some((bridge, name)) = target {
+      bridge.deliver_port_message(&name, &data_json);
+    }
+    self.notify(ChannelEventData {
+      event: "message".to_owned(),
+      port: Some(from as u32),
+      data_json: Some(data_json),
+    });
+  }
+
+  pub(crate) fn is_closed(&self) -> bool {
+    self.closed.get()
+  }
+
+  /// Close both ends and tell the observer, once.
+  pub(crate) fn close(&self) {
+    if self.closed.replace(true) {
+      return;
+    }
+    let ends = self.ends.take();
+    for (bridge, name) in ends {
+      if let Some(bridge) = bridge.upgrade() {
+        bridge.remove_port(&name);
+      }
+    }
+    self.notify(ChannelEventData {
+      event: "close".to_owned(),
+      port: None,
+      data_json: None,
+    });
+    self.observer.borrow_mut().take();
+  }
+
+  fn notify(&self, event: ChannelEventData) {
+    // Not borrowed during the call, which may close the channel.
+    let observer = match self.observer.borrow().as_ref() {
+      Some(observer) => observer.borrow_back(&self.env),
+      None => return,
+    };
+    if let Ok(observer) = observer {
+      let _ = observer.call(event);
+    }
+  }
+}
+
+#[napi(js_name = "WebviewChannel")]
+pub struct JsWebviewChannel {
+  state: Rc<ChannelState>,
+}
+
+impl JsWebviewChannel {
+  pub(crate) fn create(env: Env) -> Self {
+    Self {
+      state: Rc::new(ChannelState {
+        env,
+        ends: RefCell::new(Vec::new()),
+        observer: RefCell::new(None),
+        closed: Cell::new(false),
+      }),
+    }
+  }
+
+  pub(crate) fn state(&self) -> Rc<ChannelState> {
+    Rc::clone(&self.state)
+  }
+}
+
+#[napi]
+impl JsWebviewChannel {
+  #[napi(constructor)]
+  pub fn new() -> Result<Self> {
+    Err(napi::Error::new(
+      napi::Status::GenericFailure,
+      "WebviewChannel must be created with app.createChannel()",
+    ))
+  }
+
+  /// Hand one port of the channel to each webview, both named `name`.
+  #[napi]
+  pub fn connect(&self, first: &JsWebview, second: &JsWebview, name: String) -> Result<()> {
+    let fail = |message: &str| {
+      Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        message.to_owned(),
+      ))
+    };
+    if self.state.closed.get() {
+      return fail("The channel is closed");
+    }
+    if !self.state.ends.borrow().is_empty() {
+      return fail("The channel is already connected");
+    }
+    if first.is_disposed() || second.is_disposed() {
+      return fail("Webview has been disposed");
+    }
+    let (first, second) = (first.bridge(), second.bridge());
+    if Rc::ptr_eq(first, second) {
+      return fail("A channel connects two different webviews");
+    }
+    if first.has_port(&name) || second.has_port(&name) {
+      return fail(&format!("Port {:?} is already connected", name));
+    }
+    for (side, bridge) in [first, second].into_iter().enumerate() {
+      bridge.add_port(
+        &name,
+        PortLink {
+          channel: Rc::clone(&self.state),
+          side,
+        },
+      );
+      self
+        .state
+        .ends
+        .borrow_mut()
+        .push((Rc::downgrade(bridge), name.clone()));
+    }
+    Ok(())
+  }
+
+  /// Close the channel.  Both pages' ports receive a `close` event.
+  #[napi]
+  pub fn close(&self) {
+    self.state.close();
+  }
+
+  #[napi]
+  pub fn is_closed(&self) -> bool {
+    self.state.is_closed()
+  }
+
+  #[napi(js_name = "_onChannelEvent")]
+  pub fn on_channel_event(&self, handler: Option<FunctionRef<ChannelEventData, ()>>) {
+    if !self.state.closed.get() {
+      *self.state.observer.borrow_mut() = handler;
+    }
+  }
+}
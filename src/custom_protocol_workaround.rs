@@ -8,33 +8,111 @@
 //! 1. Use [`apply_uri_work_around`] to convert the URI we want to navigate to
 //! 2. Intercept http(s) requests, test the request URI against [`is_work_around_uri`],
 //!    if it matches, we apply [`revert_uri_work_around`] to the URI and feed it to the custom protocol handler
+//!
+//! Only the scheme and authority of a URI are rewritten; the path, query and
+//! fragment are copied verbatim, so URIs nested in a query string survive.
 
 use napi_derive::napi;
 
+/// A URI split at its authority: `{scheme}://{userinfo@}{host}{:port}{rest}`.
+struct UriParts<'a> {
+  scheme: &'a str,
+  /// `userinfo@`, including the `@`, or empty.
+  userinfo: &'a str,
+  host: &'a str,
+  /// `:port`, including the `:`, or empty.
+  port: &'a str,
+  /// Path, query and fragment.
+  rest: &'a str,
+}
+
+impl<'a> UriParts<'a> {
+  fn parse(uri: &'a str) -> Option<Self> {
+    let (scheme, after_scheme) = uri.split_once("://")?;
+    if scheme.is_empty()
+      || !scheme
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+      return None;
+    }
+    let authority_end = after_scheme
+      .find(['/', '?', '#'])
+      .unwrap_or(after_scheme.len());
+    let (authority, rest) = after_scheme.split_at(authority_end);
+    let (userinfo, host_port) = match authority.rfind('@') {
+      Some(at) => authority.split_at(at + 1),
+      None => ("", authority),
+    };
+    // IPv6 literals cannot be prefixed with a protocol label.
+    if host_port.starts_with('[') {
+      return None;
+    }
+    let (host, port) = match host_port.rfind(':') {
+      Some(colon) => host_port.split_at(colon),
+      None => (host_port, ""),
+    };
+    if host.is_empty() {
+      return None;
+    }
+    Some(Self {
+      scheme,
+      userinfo,
+      host,
+      port,
+      rest,
+    })
+  }
+
+  fn join(&self, scheme: &str, host_prefix: &str, host: &str) -> String {
+    format!(
+      "{scheme}://{}{host_prefix}{host}{}{}",
+      self.userinfo, self.port, self.rest
+    )
+  }
+}
+
 /// If the URI is a work around URI for this protocol which starts with `{http_or_https}://{protocol}.`
 pub fn is_work_around_uri(uri: &str, http_or_https: &str, protocol: &str) -> bool {
-  uri
-    .strip_prefix(http_or_https)
-    .and_then(|rest| rest.strip_prefix("://"))
-    .and_then(|rest| rest.strip_prefix(protocol))
-    .and_then(|rest| rest.strip_prefix("."))
-    .is_some()
+  work_around_host(uri, http_or_https, protocol).is_some()
+}
+
+/// The original host of a work around URI, e.g. `localhost` for
+/// `http://app.localhost/` and protocol `app`.
+fn work_around_host<'a>(uri: &'a str, http_or_https: &str, protocol: &str) -> Option<UriParts<'a>> {
+  let mut parts = UriParts::parse(uri)?;
+  if !parts.scheme.eq_ignore_ascii_case(http_or_https) {
+    return None;
+  }
+  let label = parts.host.get(..protocol.len())?;
+  let host = parts.host[protocol.len()..].strip_prefix('.')?;
+  if !label.eq_ignore_ascii_case(protocol) || host.is_empty() {
+    return None;
+  }
+  parts.host = host;
+  Some(parts)
 }
 
 /// Conveting `{protocol}://localhost/abc` to `{http_or_https}://{protocol}.localhost/abc`
+///
+/// URIs of other schemes are returned unchanged.
 pub fn apply_uri_work_around(uri: &str, http_or_https: &str, protocol: &str) -> String {
-  uri.replace(
-    &original_uri_prefix(protocol),
-    &work_around_uri_prefix(http_or_https, protocol),
-  )
+  match UriParts::parse(uri) {
+    Some(parts) if parts.scheme.eq_ignore_ascii_case(protocol) => {
+      parts.join(http_or_https, &format!("{protocol}."), parts.host)
+    }
+    _ => uri.to_owned(),
+  }
 }
 
 /// Conveting `{http_or_https}://{protocol}.localhost/abc` back to `{protocol}://localhost/abc`
+///
+/// URIs that are not work around URIs for `protocol` are returned unchanged.
 pub fn revert_uri_work_around(uri: &str, http_or_https: &str, protocol: &str) -> String {
-  uri.replace(
-    &work_around_uri_prefix(http_or_https, protocol),
-    &original_uri_prefix(protocol),
-  )
+  match work_around_host(uri, http_or_https, protocol) {
+    Some(parts) => parts.join(protocol, "", parts.host),
+    None => uri.to_owned(),
+  }
 }
 
 pub fn original_uri_prefix(protocol: &str) -> String {
@@ -45,6 +123,87 @@ pub fn work_around_uri_prefix(http_or_https: &str, protocol: &str) -> String {
   format!("{http_or_https}://{protocol}.")
 }
 
+/// Rewrites the URIs carried in headers between the custom schemes of a
+/// webview and their work around form.
+///
+/// wry reverts the request URI itself, but the page still sees the work
+/// around origin: `Origin` and `Referer` arrive in that form, and `Location`
+/// headers must be sent back in it.
+#[cfg(not(target_os = "freebsd"))]
+pub(crate) struct UriWorkAround {
+  http_or_https: &'static str,
+  protocols: Vec<String>,
+}
+
+#[cfg(not(target_os = "freebsd"))]
+impl UriWorkAround {
+  pub(crate) fn new(http_or_https: &'static str, protocols: Vec<String>) -> Self {
+    Self {
+      http_or_https,
+      protocols,
+    }
+  }
+
+  /// Revert `uri` if it is a work around URI of one of the protocols.  The
+  /// longest matching protocol wins, so `app.v2` is preferred over `app`.
+  pub(crate) fn revert(&self, uri: &str) -> Option<String> {
+    self
+      .protocols
+      .iter()
+      .filter(|protocol| is_work_around_uri(uri, self.http_or_https, protocol))
+      .max_by_key(|protocol| protocol.len())
+      .map(|protocol| revert_uri_work_around(uri, self.http_or_https, protocol))
+  }
+
+  /// Apply the work around to `uri` if it uses one of the protocols.
+  pub(crate) fn apply(&self, uri: &str) -> Option<String> {
+    let parts = UriParts::parse(uri)?;
+    self
+      .protocols
+      .iter()
+      .find(|protocol| parts.scheme.eq_ignore_ascii_case(protocol))
+      .map(|protocol| apply_uri_work_around(uri, self.http_or_https, protocol))
+  }
+
+  /// Revert the `Origin` and `Referer` headers of an incoming request.
+  pub(crate) fn revert_request_headers(&self, headers: &mut wry::http::HeaderMap) {
+    use wry::http::header;
+    self.rewrite_headers(headers, &[header::ORIGIN, header::REFERER], Self::revert);
+  }
+
+  /// Apply the work around to the URIs in the headers of a response.
+  pub(crate) fn apply_response_headers(&self, headers: &mut wry::http::HeaderMap) {
+    use wry::http::header;
+    self.rewrite_headers(
+      headers,
+      &[
+        header::LOCATION,
+        header::CONTENT_LOCATION,
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+      ],
+      Self::apply,
+    );
+  }
+
+  fn rewrite_headers(
+    &self,
+    headers: &mut wry::http::HeaderMap,
+    names: &[wry::http::HeaderName],
+    rewrite: fn(&Self, &str) -> Option<String>,
+  ) {
+    for name in names {
+      let rewritten = headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| rewrite(self, value))
+        .and_then(|value| wry::http::HeaderValue::from_str(&value).ok());
+      if let Some(value) = rewritten {
+        headers.insert(name.clone(), value);
+      }
+    }
+  }
+}
+
 #[napi(js_name = "isWorkAroundUri")]
 /// If the URI is a work around URI for this protocol which starts with `{http_or_https}://{protocol}.`
 pub fn js_is_work_around_uri(uri: String, http_or_https: String, protocol: String) -> bool {
@@ -77,7 +236,8 @@ pub fn js_work_around_uri_prefix(http_or_https: String, protocol: String) -> Str
 
 #[cfg(test)]
 mod tests {
-  use super::is_work_around_uri;
+  use super::*;
+  use proptest::prelude::*;
 
   #[test]
   fn checks_if_custom_protocol_uri() {
@@ -85,5 +245,146 @@ mod tests {
     let uri = "http://wry.localhost/path/to/page";
     assert!(is_work_around_uri(uri, scheme, "wry"));
     assert!(!is_work_around_uri(uri, scheme, "asset"));
+    assert!(!is_work_around_uri("http://wryx.localhost/", scheme, "wry"));
+    assert!(!is_work_around_uri("http://wry./", scheme, "wry"));
+  }
+
+  #[test]
+  fn only_rewrites_the_authority() {
+    assert_eq!(
+      apply_uri_work_around(
+        "app://localhost/?next=app://localhost/x#app://y",
+        "http",
+        "app"
+      ),
+      "http://app.localhost/?next=app://localhost/x#app://y"
+    );
+    assert_eq!(
+      revert_uri_work_around(
+        "http://app.localhost/?next=http://app.localhost/x",
+        "http",
+        "app"
+      ),
+      "app://localhost/?next=http://app.localhost/x"
+    );
+  }
+
+  #[test]
+  fn keeps_hosts_ports_and_userinfo() {
+    assert_eq!(
+      apply_uri_work_around("app://user:pw@docs.example:8080?q#f", "https", "app"),
+      "https://user:pw@app.docs.example:8080?q#f"
+    );
+    assert_eq!(
+      revert_uri_work_around("https://user:pw@app.docs.example:8080/a", "https", "app"),
+      "app://user:pw@docs.example:8080/a"
+    );
+    assert_eq!(
+      apply_uri_work_around("app://localhost", "http", "app"),
+      "http://app.localhost"
+    );
+  }
+
+  #[test]
+  fn leaves_other_uris_alone() {
+    for uri in [
+      "https://example.com/app://localhost",
+      "app:localhost",
+      "app:///no-host",
+      "app://[::1]/",
+    ] {
+      assert_eq!(apply_uri_work_around(uri, "http", "app"), uri);
+    }
+    assert_eq!(
+      revert_uri_work_around("http://other.localhost/", "http", "app"),
+      "http://other.localhost/"
+    );
+  }
+
+  #[cfg(not(target_os = "freebsd"))]
+  #[test]
+  fn rewrites_request_and_response_headers() {
+    use wry::http::{header, HeaderMap, HeaderValue};
+
+    let work_around = UriWorkAround::new("http", vec!["app".to_owned(), "app.v2".to_owned()]);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::ORIGIN,
+      HeaderValue::from_static("http://app.v2.localhost"),
+    );
+    headers.insert(
+      header::REFERER,
+      HeaderValue::from_static("http://app.localhost/a?b"),
+    );
+    work_around.revert_request_headers(&mut headers);
+    assert_eq!(headers[header::ORIGIN], "app.v2://localhost");
+    assert_eq!(headers[header::REFERER], "app://localhost/a?b");
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::LOCATION,
+      HeaderValue::from_static("app://localhost/login"),
+    );
+    headers.insert(
+      header::ACCESS_CONTROL_ALLOW_ORIGIN,
+      HeaderValue::from_static("app://localhost"),
+    );
+    headers.insert(
+      header::CONTENT_LOCATION,
+      HeaderValue::from_static("/relative"),
+    );
+    work_around.apply_response_headers(&mut headers);
+    assert_eq!(headers[header::LOCATION], "http://app.localhost/login");
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "http://app.localhost"
+    );
+    assert_eq!(headers[header::CONTENT_LOCATION], "/relative");
+  }
+
+  fn protocol() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9+.-]{0,8}"
+  }
+
+  fn uri(protocol: String) -> impl Strategy<Value = String> {
+    (
+      "([a-z0-9]{1,6}(:[a-z0-9]{0,6})?@)?",
+      "[a-z0-9-]{1,10}(\\.[a-z0-9-]{1,10}){0,2}",
+      "(:[0-9]{1,5})?",
+      "(/[!-~]{0,20})?",
+    )
+      .prop_map(move |(userinfo, host, port, rest)| {
+        format!("{protocol}://{userinfo}{host}{port}{rest}")
+      })
+  }
+
+  proptest! {
+    #[test]
+    fn apply_then_revert_round_trips(
+      (protocol, uri) in protocol().prop_flat_map(|p| (Just(p.clone()), uri(p))),
+      https in any::<bool>(),
+    ) {
+      let scheme = if https { "https" } else { "http" };
+      let applied = apply_uri_work_around(&uri, scheme, &protocol);
+      prop_assert!(is_work_around_uri(&applied, scheme, &protocol));
+      prop_assert_eq!(revert_uri_work_around(&applied, scheme, &protocol), uri);
+    }
+
+    #[test]
+    fn rewriting_preserves_path_query_and_fragment(
+      (protocol, uri) in protocol().prop_flat_map(|p| (Just(p.clone()), uri(p))),
+    ) {
+      let applied = apply_uri_work_around(&uri, "http", &protocol);
+      let rest = |uri: &str| UriParts::parse(uri).map(|parts| parts.rest.to_owned());
+      prop_assert_eq!(rest(&applied), rest(&uri));
+    }
+
+    #[test]
+    fn other_schemes_are_never_rewritten(uri in "[ -~]{0,40}", protocol in protocol()) {
+      let unrelated = UriParts::parse(&uri)
+        .is_none_or(|parts| !parts.scheme.eq_ignore_ascii_case(&protocol));
+      prop_assume!(unrelated);
+      prop_assert_eq!(apply_uri_work_around(&uri, "http", &protocol), uri);
+    }
   }
 }
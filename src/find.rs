@@ -0,0 +1,126 @@
+//! Find-in-page.
+//!
+//! WebKitGTK's find controller highlights the matches, selects one and
+//! counts them asynchronously; the count reaches Node as a `found-in-page`
+//! event.  Other platforms have no find API yet.
+
+use napi::Result;
+
+use crate::history::Step;
+use crate::types::FindOptions;
+
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+pub(crate) use platform::watch;
+pub(crate) use platform::{find, find_again, stop_find};
+
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+mod platform {
+  use webkit2gtk::{FindControllerExt, FindOptions as WebKitFindOptions, WebViewExt};
+  use wry::WebViewExtUnix;
+
+  use super::*;
+
+  fn controller(webview: &wry::WebView) -> Result<webkit2gtk::FindController> {
+    webview.webview().find_controller().ok_or_else(|| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        "The webview has no find controller",
+      )
+    })
+  }
+
+  /// Highlight the matches of `text`, select the first one and count them.
+  pub(crate) fn find(
+    webview: &wry::WebView,
+    text: &str,
+    options: Option<FindOptions>,
+  ) -> Result<()> {
+    if text.is_empty() {
+      return Err(napi::Error::new(
+        napi::Status::InvalidArg,
+        "The search text must not be empty",
+      ));
+    }
+    let options = options.unwrap_or_default();
+    let mut flags = WebKitFindOptions::NONE;
+    if !options.case_sensitive.unwrap_or(false) {
+      flags |= WebKitFindOptions::CASE_INSENSITIVE;
+    }
+    if options.wrap.unwrap_or(true) {
+      flags |= WebKitFindOptions::WRAP_AROUND;
+    }
+    let controller = controller(webview)?;
+    controller.count_matches(text, flags.bits(), u32::MAX);
+    controller.search(text, flags.bits(), u32::MAX);
+    Ok(())
+  }
+
+  /// Select the next or previous match of the current search.  Returns
+  /// `false` when there is no search.
+  pub(crate) fn find_again(webview: &wry::WebView, step: Step) -> Result<bool> {
+    let controller = controller(webview)?;
+    if controller.search_text().is_none() {
+      return Ok(false);
+    }
+    match step {
+      Step::Forward => controller.search_next(),
+      Step::Back => controller.search_previous(),
+    }
+    Ok(true)
+  }
+
+  /// End the current search and remove its highlights.
+  pub(crate) fn stop_find(webview: &wry::WebView) -> Result<()> {
+    controller(webview)?.search_finish();
+    Ok(())
+  }
+
+  /// Call `notify` with the search text and the number of matches once each
+  /// search was counted.
+  pub(crate) fn watch(webview: &wry::WebView, notify: impl Fn(String, u32) + 'static) {
+    if let Some(controller) = webview.webview().find_controller() {
+      controller.connect_counted_matches(move |controller, matches| {
+        if let Some(text) = controller.search_text() {
+          notify(text.into(), matches);
+        }
+      });
+    }
+  }
+}
+
+#[cfg(not(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+)))]
+mod platform {
+  use super::*;
+
+  fn unsupported<T>() -> Result<T> {
+    Err(napi::Error::new(
+      napi::Status::GenericFailure,
+      "Find-in-page is not supported on this platform",
+    ))
+  }
+
+  pub(crate) fn find(
+    _webview: &wry::WebView,
+    _text: &str,
+    _options: Option<FindOptions>,
+  ) -> Result<()> {
+    unsupported()
+  }
+
+  pub(crate) fn find_again(_webview: &wry::WebView, _step: Step) -> Result<bool> {
+    unsupported()
+  }
+
+  pub(crate) fn stop_find(_webview: &wry::WebView) -> Result<()> {
+    unsupported()
+  }
+}
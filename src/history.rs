@@ -0,0 +1,180 @@
+//! Back-forward navigation.
+//!
+//! Wry has no history API, so WebKitGTK's back-forward list is used
+//! directly.  WebView2 and WKWebView keep their lists behind wry's handles
+//! to the native views, which only the `webview2-com` and `objc2-web-kit`
+//! bindings can talk to, pinned to the versions wry itself uses; until wry
+//! has a history API these methods throw there.  The page's `history` object
+//! is no substitute, as it cannot tell whether there is an entry to go to.
+
+use napi::Result;
+
+use crate::types::WebviewHistory;
+
+/// Direction of a step through the back-forward list, or from one match of
+/// a search to the next or previous one.
+#[derive(Clone, Copy)]
+pub(crate) enum Step {
+  Back,
+  Forward,
+}
+
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+pub(crate) use platform::watch;
+pub(crate) use platform::{can_go, go, history, stop};
+
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+mod platform {
+  use std::{cell::RefCell, rc::Rc};
+
+  use gtk::prelude::ObjectExt;
+  use webkit2gtk::{BackForwardListExt, BackForwardListItemExt, LoadEvent, WebViewExt};
+  use wry::WebViewExtUnix;
+
+  use super::*;
+  use crate::types::HistoryEntry;
+
+  /// What `history-changed` events report.
+  #[derive(Clone, PartialEq)]
+  pub(crate) struct HistoryState {
+    pub(crate) url: Option<String>,
+    pub(crate) can_go_back: bool,
+    pub(crate) can_go_forward: bool,
+  }
+
+  impl HistoryState {
+    fn of(view: &webkit2gtk::WebView) -> Self {
+      Self {
+        url: view.uri().map(Into::into),
+        can_go_back: view.can_go_back(),
+        can_go_forward: view.can_go_forward(),
+      }
+    }
+  }
+
+  pub(crate) fn can_go(webview: &wry::WebView, step: Step) -> Result<bool> {
+    let view = webview.webview();
+    Ok(match step {
+      Step::Back => view.can_go_back(),
+      Step::Forward => view.can_go_forward(),
+    })
+  }
+
+  /// Take one step, unless there is no entry to go to.  Returns whether a
+  /// navigation started.
+  pub(crate) fn go(webview: &wry::WebView, step: Step) -> Result<bool> {
+    if !can_go(webview, step)? {
+      return Ok(false);
+    }
+    let view = webview.webview();
+    match step {
+      Step::Back => view.go_back(),
+      Step::Forward => view.go_forward(),
+    }
+    Ok(true)
+  }
+
+  pub(crate) fn stop(webview: &wry::WebView) -> Result<()> {
+    webview.webview().stop_loading();
+    Ok(())
+  }
+
+  pub(crate) fn history(webview: &wry::WebView) -> Result<WebviewHistory> {
+    let list = match webview.webview().back_forward_list() {
+      Some(list) if list.current_item().is_some() => list,
+      _ => {
+        return Ok(WebviewHistory {
+          entries: Vec::new(),
+          index: -1,
+        })
+      }
+    };
+    let back = list.back_list().len() as i32;
+    let forward = list.forward_list().len() as i32;
+    let entries = (-back..=forward)
+      .filter_map(|offset| list.nth_item(offset))
+      .map(|item| HistoryEntry {
+        url: item.uri().map(Into::into).unwrap_or_default(),
+        title: item
+          .title()
+          .map(String::from)
+          .filter(|title| !title.is_empty()),
+      })
+      .collect();
+    Ok(WebviewHistory {
+      entries,
+      index: back,
+    })
+  }
+
+  /// Call `notify` whenever the back-forward list, or the current entry in
+  /// it, changes.  Going back and forth only moves the current entry, and
+  /// same-document navigations only change the URL, so the list's own
+  /// `changed` signal is not enough.
+  pub(crate) fn watch(webview: &wry::WebView, notify: impl Fn(HistoryState) + 'static) {
+    let view = webview.webview();
+    let last = RefCell::new(None);
+    let check = Rc::new(move |view: &webkit2gtk::WebView| {
+      let state = HistoryState::of(view);
+      if last.replace(Some(state.clone())).as_ref() != Some(&state) {
+        notify(state);
+      }
+    });
+
+    let on_load = Rc::clone(&check);
+    view.connect_load_changed(move |view, event| {
+      if matches!(event, LoadEvent::Committed | LoadEvent::Finished) {
+        on_load(view);
+      }
+    });
+    let on_uri = Rc::clone(&check);
+    view.connect_notify_local(Some("uri"), move |view, _| on_uri(view));
+    if let Some(list) = view.back_forward_list() {
+      // The list lives as long as the view, so hold the view weakly.
+      let weak = view.downgrade();
+      list.connect_local("changed", false, move |_| {
+        if let Some(view) = weak.upgrade() {
+          check(&view);
+        }
+        None
+      });
+    }
+  }
+}
+
+#[cfg(not(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+)))]
+mod platform {
+  use super::*;
+
+  fn unsupported<T>() -> Result<T> {
+    Err(napi::Error::new(
+      napi::Status::GenericFailure,
+      "Navigation history is only supported with WebKitGTK on Linux",
+    ))
+  }
+
+  pub(crate) fn can_go(_webview: &wry::WebView, _step: Step) -> Result<bool> {
+    unsupported()
+  }
+
+  pub(crate) fn go(_webview: &wry::WebView, _step: Step) -> Result<bool> {
+    unsupported()
+  }
+
+  pub(crate) fn stop(_webview: &wry::WebView) -> Result<()> {
+    unsupported()
+  }
+
+  pub(crate) fn history(_webview: &wry::WebView) -> Result<WebviewHistory> {
+    unsupported()
+  }
+}
@@ -0,0 +1,438 @@
+//! Limits on the IPC messages a page sends to Node.
+//!
+//! A webview may cap the size of each message and the rate they are
+//! delivered at.  The rate is a token bucket holding one second's worth of
+//! messages, so short bursts go through at once.  Messages over the rate wait
+//! in a bounded queue, drained by the application's event loop; once the
+//! queue is full the overflow policy drops the oldest queued message or
+//! rejects the new one.
+//!
+//! Only the page's own messages and exposed calls are limited; messages of
+//! the page runtime are handled before they reach a queue.
+//!
+//! Binary messages are answered right away, so they are never queued: over
+//! the rate they are rejected, and the page's `postMessage()` fails.
+//!
+//! Violations are reported at most once a second for each limit, with the
+//! number of messages discarded since the last report.
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::VecDeque,
+  rc::Rc,
+  time::{Duration, Instant},
+};
+
+use wry::http::Request;
+
+use crate::types::IpcLimitOptions;
+
+/// Shortest time between two reports of the same limit.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The limited IPC queues of a window's webviews, drained by the application.
+pub(crate) type IpcQueues = Rc<RefCell<Vec<Rc<IpcQueue>>>>;
+
+/// A limit a message ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum IpcLimit {
+  /// The message was larger than `maxMessageSize`.
+  Size,
+  /// The message was over `maxMessagesPerSecond` and did not fit the queue.
+  Rate,
+}
+
+impl IpcLimit {
+  pub(crate) fn as_str(self) -> &'static str {
+    match self {
+      IpcLimit::Size => "size",
+      IpcLimit::Rate => "rate",
+    }
+  }
+}
+
+/// Messages discarded because of `limit` since its last report.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct IpcViolation {
+  pub(crate) limit: IpcLimit,
+  pub(crate) count: u32,
+  /// URL of the page that sent the last of them.
+  pub(crate) url: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Overflow {
+  /// Drop the oldest queued message to make room for the new one.
+  Drop,
+  /// Reject the new message.
+  Reject,
+}
+
+/// Validated [`IpcLimitOptions`].
+#[derive(Clone, Debug)]
+pub(crate) struct IpcLimits {
+  max_size: Option<usize>,
+  rate: Option<f64>,
+  capacity: usize,
+  overflow: Overflow,
+}
+
+impl IpcLimits {
+  /// `None` when `options` limit nothing.
+  pub(crate) fn new(options: &IpcLimitOptions) -> std::result::Result<Option<Self>, String> {
+    let overflow = match options.overflow.as_deref() {
+      None | Some("drop") => Overflow::Drop,
+      Some("reject") => Overflow::Reject,
+      Some(other) => {
+        return Err(format!(
+          "Invalid IPC overflow policy {:?}: expected \"drop\" or \"reject\"",
+          other
+        ))
+      }
+    };
+    if options.max_messages_per_second == Some(0) {
+      return Err("maxMessagesPerSecond must be greater than 0".to_owned());
+    }
+    if options.max_message_size.is_none() && options.max_messages_per_second.is_none() {
+      return Ok(None);
+    }
+    Ok(Some(Self {
+      max_size: options.max_message_size.map(|size| size as usize),
+      rate: options.max_messages_per_second.map(f64::from),
+      capacity: options.max_queued_messages.unwrap_or(0) as usize,
+      overflow,
+    }))
+  }
+}
+
+/// What became of a message handed to [`IpcGate::admit`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Admission<T> {
+  /// Deliver it now.
+  Deliver(T),
+  /// Queued, see [`IpcGate::next`].
+  Queued,
+  /// Discarded.
+  Refused,
+}
+
+/// Reports owed for one limit.
+#[derive(Default)]
+struct Pending {
+  count: u32,
+  url: String,
+  last_report: Option<Instant>,
+}
+
+/// Applies [`IpcLimits`] to the messages of one webview.
+pub(crate) struct IpcGate<T> {
+  limits: IpcLimits,
+  tokens: f64,
+  refilled: Instant,
+  queue: VecDeque<T>,
+  pending: [Pending; 2],
+}
+
+impl<T> IpcGate<T> {
+  pub(crate) fn new(limits: IpcLimits, now: Instant) -> Self {
+    Self {
+      tokens: limits.rate.unwrap_or(0.0),
+      limits,
+      refilled: now,
+      queue: VecDeque::new(),
+      pending: Default::default(),
+    }
+  }
+
+  /// Admit a message of `size` bytes sent by `url`.
+  pub(crate) fn admit(&mut self, size: usize, url: &str, message: T, now: Instant) -> Admission<T> {
+    if let Err(limit) = self.check_size(size) {
+      self.violate(limit, url);
+      return Admission::Refused;
+    }
+    if self.queue.is_empty() && self.take_token(now) {
+      return Admission::Deliver(message);
+    }
+    if self.queue.len() < self.limits.capacity {
+      self.queue.push_back(message);
+      return Admission::Queued;
+    }
+    self.violate(IpcLimit::Rate, url);
+    match self.limits.overflow {
+      Overflow::Drop if self.limits.capacity > 0 => {
+        self.queue.pop_front();
+        self.queue.push_back(message);
+        Admission::Queued
+      }
+      _ => Admission::Refused,
+    }
+  }
+
+  /// Admit a message that cannot wait in the queue.
+  pub(crate) fn admit_now(
+    &mut self,
+    size: usize,
+    url: &str,
+    now: Instant,
+  ) -> std::result::Result<(), IpcLimit> {
+    let admitted = self.check_size(size).and_then(|()| {
+      if self.queue.is_empty() && self.take_token(now) {
+        Ok(())
+      } else {
+        Err(IpcLimit::Rate)
+      }
+    });
+    if let Err(limit) = admitted {
+      self.violate(limit, url);
+    }
+    admitted
+  }
+
+  /// The next queued message, once the rate allows it.
+  pub(crate) fn next(&mut self, now: Instant) -> Option<T> {
+    if self.queue.is_empty() || !self.take_token(now) {
+      return None;
+    }
+    self.queue.pop_front()
+  }
+
+  /// The reports due at `now`.
+  pub(crate) fn take_reports(&mut self, now: Instant) -> Vec<IpcViolation> {
+    let mut reports = Vec::new();
+    for (limit, pending) in [IpcLimit::Size, IpcLimit::Rate]
+      .into_iter()
+      .zip(&mut self.pending)
+    {
+      let due = pending
+        .last_report
+        .is_none_or(|last| now >= last + REPORT_INTERVAL);
+      if pending.count > 0 && due {
+        reports.push(IpcViolation {
+          limit,
+          count: std::mem::take(&mut pending.count),
+          url: std::mem::take(&mut pending.url),
+        });
+        pending.last_report = Some(now);
+      }
+    }
+    reports
+  }
+
+  /// When a queued message or a report is next due.
+  pub(crate) fn deadline(&self) -> Option<Instant> {
+    let message = match (self.queue.is_empty(), self.limits.rate) {
+      (false, Some(rate)) => {
+        let wait = (1.0 - self.tokens).max(0.0) / rate;
+        Some(self.refilled + Duration::from_secs_f64(wait))
+      }
+      _ => None,
+    };
+    let reports = self
+      .pending
+      .iter()
+      .filter(|pending| pending.count > 0)
+      .filter_map(|pending| pending.last_report)
+      .map(|last| last + REPORT_INTERVAL);
+    message.into_iter().chain(reports).min()
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.queue.clear();
+  }
+
+  fn check_size(&self, size: usize) -> std::result::Result<(), IpcLimit> {
+    match self.limits.max_size {
+      Some(max) if size > max => Err(IpcLimit::Size),
+      _ => Ok(()),
+    }
+  }
+
+  fn take_token(&mut self, now: Instant) -> bool {
+    let Some(rate) = self.limits.rate else {
+      return true;
+    };
+    let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+    self.tokens = (self.tokens + elapsed * rate).min(rate);
+    self.refilled = now;
+    if self.tokens < 1.0 {
+      return false;
+    }
+    self.tokens -= 1.0;
+    true
+  }
+
+  fn violate(&mut self, limit: IpcLimit, url: &str) {
+    let pending = &mut self.pending[limit as usize];
+    pending.count = pending.count.saturating_add(1);
+    url.clone_into(&mut pending.url);
+  }
+}
+
+/// The IPC messages of a webview with limits, and where they go.
+pub(crate) struct IpcQueue {
+  gate: RefCell<IpcGate<Request<String>>>,
+  deliver: Box<dyn Fn(Request<String>)>,
+  report: Box<dyn Fn(IpcViolation)>,
+  closed: Cell<bool>,
+}
+
+impl IpcQueue {
+  pub(crate) fn new(
+    limits: IpcLimits,
+    deliver: impl Fn(Request<String>) + 'static,
+    report: impl Fn(IpcViolation) + 'static,
+  ) -> Self {
+    Self {
+      gate: RefCell::new(IpcGate::new(limits, Instant::now())),
+      deliver: Box::new(deliver),
+      report: Box::new(report),
+      closed: Cell::new(false),
+    }
+  }
+
+  /// Deliver or queue a message from the page.
+  pub(crate) fn post(&self, message: Request<String>) {
+    if self.closed.get() {
+      return;
+    }
+    let now = Instant::now();
+    let url = message.uri().to_string();
+    let size = message.body().len();
+    let admission = self.gate.borrow_mut().admit(size, &url, message, now);
+    if let Admission::Deliver(message) = admission {
+      (self.deliver)(message);
+    }
+    self.report_due(now);
+  }
+
+  /// Admit a binary message from `url`, which is delivered by the caller.
+  pub(crate) fn admit_now(&self, size: usize, url: &str) -> std::result::Result<(), IpcLimit> {
+    let now = Instant::now();
+    let admitted = self.gate.borrow_mut().admit_now(size, url, now);
+    self.report_due(now);
+    admitted
+  }
+
+  /// Deliver the queued messages the rate allows and the reports due, and
+  /// return when to come back.
+  pub(crate) fn drain(&self, now: Instant) -> Option<Instant> {
+    while !self.closed.get() {
+      // Not borrowed during delivery, which may post or dispose.
+      let Some(message) = self.gate.borrow_mut().next(now) else {
+        break;
+      };
+      (self.deliver)(message);
+    }
+    self.report_due(now);
+    self.gate.borrow().deadline()
+  }
+
+  /// Discard the queue; later messages are ignored.
+  pub(crate) fn close(&self) {
+    self.closed.set(true);
+    self.gate.borrow_mut().clear();
+  }
+
+  fn report_due(&self, now: Instant) {
+    if self.closed.get() {
+      return;
+    }
+    let reports = self.gate.borrow_mut().take_reports(now);
+    for report in reports {
+      (self.report)(report);
+    }
+  }
+}
+
+/// Drain every open queue, forget the closed ones, and return the earliest
+/// deadline.
+pub(crate) fn drain_queues(queues: &IpcQueues, now: Instant) -> Option<Instant> {
+  queues.borrow_mut().retain(|queue| !queue.closed.get());
+  // Cloned so delivery can create or close queues.
+  let open = queues.borrow().clone();
+  open.iter().filter_map(|queue| queue.drain(now)).min()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn gate(size: Option<u32>, rate: Option<u32>, queued: u32, overflow: &str) -> IpcGate<u32> {
+    let limits = IpcLimits::new(&IpcLimitOptions {
+      max_message_size: size,
+      max_messages_per_second: rate,
+      max_queued_messages: Some(queued),
+      overflow: Some(overflow.to_owned()),
+    })
+    .unwrap()
+    .unwrap();
+    IpcGate::new(limits, Instant::now())
+  }
+
+  #[test]
+  fn refuses_large_messages() {
+    let now = Instant::now();
+    let mut gate = gate(Some(4), None, 0, "drop");
+    assert_eq!(gate.admit(4, "app://a/", 1, now), Admission::Deliver(1));
+    assert_eq!(gate.admit(5, "app://b/", 2, now), Admission::Refused);
+    assert_eq!(gate.admit_now(9, "app://c/", now), Err(IpcLimit::Size));
+    assert_eq!(
+      gate.take_reports(now),
+      [IpcViolation {
+        limit: IpcLimit::Size,
+        count: 2,
+        url: "app://c/".to_owned(),
+      }]
+    );
+  }
+
+  #[test]
+  fn queues_messages_over_the_rate() {
+    let start = Instant::now();
+    let mut gate = gate(None, Some(2), 2, "drop");
+    assert_eq!(gate.admit(0, "", 1, start), Admission::Deliver(1));
+    assert_eq!(gate.admit(0, "", 2, start), Admission::Deliver(2));
+    assert_eq!(gate.admit(0, "", 3, start), Admission::Queued);
+    assert_eq!(gate.admit(0, "", 4, start), Admission::Queued);
+    // Full: the oldest queued message makes room.
+    assert_eq!(gate.admit(0, "", 5, start), Admission::Queued);
+    assert_eq!(gate.admit_now(0, "", start), Err(IpcLimit::Rate));
+    assert_eq!(gate.next(start), None);
+    assert_eq!(gate.deadline(), Some(start + Duration::from_millis(500)));
+
+    let later = start + Duration::from_millis(500);
+    assert_eq!(gate.next(later), Some(4));
+    assert_eq!(gate.next(later), None);
+    assert_eq!(gate.next(later + Duration::from_secs(5)), Some(5));
+    assert_eq!(gate.next(later + Duration::from_secs(5)), None);
+  }
+
+  #[test]
+  fn rejects_overflow_and_throttles_reports() {
+    let start = Instant::now();
+    let mut gate = gate(None, Some(1), 1, "reject");
+    assert_eq!(gate.admit(0, "", 1, start), Admission::Deliver(1));
+    assert_eq!(gate.admit(0, "", 2, start), Admission::Queued);
+    assert_eq!(gate.admit(0, "a", 3, start), Admission::Refused);
+    assert_eq!(gate.take_reports(start).len(), 1);
+    assert_eq!(gate.admit(0, "b", 4, start), Admission::Refused);
+    assert!(gate.take_reports(start).is_empty());
+    assert_eq!(gate.next(start + REPORT_INTERVAL), Some(2));
+    assert_eq!(gate.deadline(), Some(start + REPORT_INTERVAL));
+    let reports = gate.take_reports(start + REPORT_INTERVAL);
+    assert_eq!((reports[0].count, reports[0].url.as_str()), (1, "b"));
+  }
+
+  #[test]
+  fn validates_options() {
+    let options = |rate, overflow: &str| IpcLimitOptions {
+      max_message_size: None,
+      max_messages_per_second: rate,
+      max_queued_messages: None,
+      overflow: Some(overflow.to_owned()),
+    };
+    assert!(IpcLimits::new(&options(None, "drop")).unwrap().is_none());
+    assert!(IpcLimits::new(&options(Some(0), "drop")).is_err());
+    assert!(IpcLimits::new(&options(Some(1), "block")).is_err());
+  }
+}
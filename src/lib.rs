@@ -14,11 +14,35 @@ mod freebsd_stub;
 #[cfg(not(target_os = "freebsd"))]
 pub mod app;
 #[cfg(not(target_os = "freebsd"))]
+pub mod assets;
+#[cfg(not(target_os = "freebsd"))]
+pub mod bridge;
+#[cfg(not(target_os = "freebsd"))]
 pub mod browser_window;
 #[cfg(not(target_os = "freebsd"))]
+pub mod channel;
+#[cfg(not(target_os = "freebsd"))]
+pub mod find;
+#[cfg(not(target_os = "freebsd"))]
+pub mod history;
+#[cfg(not(target_os = "freebsd"))]
+pub mod ipc_limits;
+#[cfg(not(target_os = "freebsd"))]
 pub mod menu;
 #[cfg(not(target_os = "freebsd"))]
 pub mod notifications;
+#[cfg(not(target_os = "freebsd"))]
+pub mod origins;
+#[cfg(not(target_os = "freebsd"))]
+pub mod pdf;
+#[cfg(not(target_os = "freebsd"))]
+pub mod protocol;
+#[cfg(not(target_os = "freebsd"))]
+pub mod proxy;
+#[cfg(not(target_os = "freebsd"))]
+pub mod router;
+#[cfg(not(target_os = "freebsd"))]
+pub mod snapshot;
 #[cfg(not(any(target_os = "android", target_os = "freebsd")))]
 pub mod tray;
 #[cfg(target_os = "android")]
@@ -28,3 +52,5 @@ pub mod tray;
 pub mod web_context;
 #[cfg(not(target_os = "freebsd"))]
 pub mod webview;
+#[cfg(not(target_os = "freebsd"))]
+pub mod workers;
@@ -0,0 +1,256 @@
+//! Origin allowlists for IPC and exposed namespaces.
+//!
+//! A pattern is `*`, which allows everything, or a URL of the form
+//! `scheme://host[:port][/path]`:
+//!
+//! - host `*` matches any host, and a `*.` prefix matches any subdomain;
+//! - port `*` matches any port, and a missing port the scheme's default;
+//! - `*` in the path matches any run of characters, and a missing path, or
+//!   `/`, matches every path.
+//!
+//! So `app://localhost`, `https://*.example.com` and
+//! `http://localhost:*/admin/*` are all valid patterns.
+
+use wry::http::Uri;
+
+/// The URLs allowed to use a channel.
+#[derive(Clone)]
+pub(crate) struct OriginPolicy {
+  /// `None` allows every URL.
+  patterns: Option<Vec<OriginPattern>>,
+}
+
+impl OriginPolicy {
+  /// A policy allowing every URL.
+  pub(crate) fn allow_all() -> Self {
+    Self { patterns: None }
+  }
+
+  /// `None` allows every URL, like a `*` pattern.
+  pub(crate) fn new(patterns: Option<&[String]>) -> std::result::Result<Self, String> {
+    let Some(patterns) = patterns else {
+      return Ok(Self::allow_all());
+    };
+    if patterns.iter().any(|pattern| pattern == "*") {
+      return Ok(Self::allow_all());
+    }
+    let patterns = patterns
+      .iter()
+      .map(|pattern| OriginPattern::parse(pattern))
+      .collect::<std::result::Result<_, _>>()?;
+    Ok(Self {
+      patterns: Some(patterns),
+    })
+  }
+
+  /// Whether `url` matches one of the patterns.  URLs that cannot be parsed,
+  /// such as `about:blank`, only match `*`.
+  pub(crate) fn allows(&self, url: &str) -> bool {
+    self.any(url, OriginPattern::matches)
+  }
+
+  /// Whether `origin`, e.g. an `Origin` header, matches one of the patterns
+  /// when their paths are ignored.
+  pub(crate) fn allows_origin(&self, origin: &str) -> bool {
+    self.any(origin, OriginPattern::matches_origin)
+  }
+
+  fn any(&self, url: &str, matches: fn(&OriginPattern, &Uri) -> bool) -> bool {
+    let Some(patterns) = &self.patterns else {
+      return true;
+    };
+    let Ok(uri) = url.parse::<Uri>() else {
+      return false;
+    };
+    patterns.iter().any(|pattern| matches(pattern, &uri))
+  }
+}
+
+#[derive(Clone)]
+struct OriginPattern {
+  scheme: String,
+  /// Lowercase; `*` or a `*.` prefix are wildcards.
+  host: String,
+  /// `None` matches any port.
+  port: Option<Option<u16>>,
+  /// `None` matches any path.
+  path: Option<String>,
+}
+
+impl OriginPattern {
+  fn parse(pattern: &str) -> std::result::Result<Self, String> {
+    let invalid = |reason: &str| format!("Invalid origin pattern {:?}: {}", pattern, reason);
+    let (scheme, rest) = pattern
+      .split_once("://")
+      .ok_or_else(|| invalid("expected scheme://host"))?;
+    if scheme.is_empty()
+      || !scheme
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+      return Err(invalid("bad scheme"));
+    }
+    let (authority, path) = match rest.find('/') {
+      Some(index) => rest.split_at(index),
+      None => (rest, ""),
+    };
+    // The closing bracket of an IPv6 host is followed by the port, if any.
+    let port_at = match authority.rfind(']') {
+      Some(end) => authority[end..].find(':').map(|index| end + index),
+      None => authority.rfind(':'),
+    };
+    let (host, port) = match port_at {
+      Some(index) => (&authority[..index], Some(&authority[index + 1..])),
+      None => (authority, None),
+    };
+    let literal = host.strip_prefix("*.").unwrap_or(host);
+    if literal.is_empty() || host != "*" && literal.contains('*') {
+      return Err(invalid("bad host"));
+    }
+    let port = match port {
+      Some("*") => None,
+      Some(port) => Some(Some(port.parse().map_err(|_| invalid("bad port"))?)),
+      None => Some(None),
+    };
+    Ok(Self {
+      scheme: scheme.to_ascii_lowercase(),
+      host: host.to_ascii_lowercase(),
+      port,
+      path: (!matches!(path, "" | "/")).then(|| path.to_owned()),
+    })
+  }
+
+  fn matches(&self, uri: &Uri) -> bool {
+    self.matches_origin(uri)
+      && match &self.path {
+        None => true,
+        Some(path) => glob_matches(path, uri.path()),
+      }
+  }
+
+  fn matches_origin(&self, uri: &Uri) -> bool {
+    let (Some(scheme), Some(host)) = (uri.scheme_str(), uri.host()) else {
+      return false;
+    };
+    if !scheme.eq_ignore_ascii_case(&self.scheme) {
+      return false;
+    }
+    let host = host.to_ascii_lowercase();
+    let host_matches = if self.host == "*" {
+      true
+    } else if let Some(domain) = self.host.strip_prefix("*.") {
+      host
+        .strip_suffix(domain)
+        .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.'))
+    } else {
+      host == self.host
+    };
+    let port_matches = match self.port {
+      None => true,
+      Some(port) => {
+        port.or_else(|| default_port(&self.scheme))
+          == uri.port_u16().or_else(|| default_port(&self.scheme))
+      }
+    };
+    host_matches && port_matches
+  }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+  match scheme {
+    "http" | "ws" => Some(80),
+    "https" | "wss" => Some(443),
+    _ => None,
+  }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+  let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+  let (mut p, mut t) = (0, 0);
+  // Where the last `*` was, and the text position it currently swallows to.
+  let mut star = None;
+  while t < text.len() {
+    if p < pattern.len() && pattern[p] == b'*' {
+      star = Some((p, t));
+      p += 1;
+    } else if p < pattern.len() && pattern[p] == text[t] {
+      p += 1;
+      t += 1;
+    } else if let Some((star_p, star_t)) = star {
+      p = star_p + 1;
+      t = star_t + 1;
+      star = Some((star_p, star_t + 1));
+    } else {
+      return false;
+    }
+  }
+  pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy(patterns: &[&str]) -> OriginPolicy {
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+    OriginPolicy::new(Some(&patterns)).unwrap()
+  }
+
+  #[test]
+  fn matches_origins() {
+    let policy = policy(&[
+      "app://localhost",
+      "https://*.example.com",
+      "http://127.0.0.1:*",
+    ]);
+    assert!(policy.allows("app://localhost/index.html"));
+    assert!(policy.allows("APP://LOCALHOST/"));
+    assert!(policy.allows("https://a.b.example.com:443/x"));
+    assert!(policy.allows("http://127.0.0.1:5173/"));
+    assert!(!policy.allows("https://example.com/"));
+    assert!(!policy.allows("https://evil-example.com/"));
+    assert!(!policy.allows("https://a.example.com:8443/"));
+    assert!(!policy.allows("app://localhost.evil.test/"));
+    assert!(!policy.allows("about:blank"));
+    assert!(OriginPolicy::new(None).unwrap().allows("about:blank"));
+    assert!(OriginPolicy::new(Some(&["*".to_owned()]))
+      .unwrap()
+      .allows("data:text/html,hi"));
+  }
+
+  #[test]
+  fn matches_ports_and_paths() {
+    let policy = policy(&[
+      "https://example.com/app/*",
+      "http://localhost:8080/",
+      "http://[::1]:3000",
+    ]);
+    assert!(policy.allows("https://example.com/app/settings?tab=1"));
+    assert!(!policy.allows("https://example.com/apps"));
+    assert!(!policy.allows("https://example.com/"));
+    assert!(policy.allows_origin("https://example.com"));
+    assert!(policy.allows("http://localhost:8080/any/path"));
+    assert!(!policy.allows("http://localhost/"));
+    assert!(policy.allows("http://[::1]:3000/"));
+    assert!(glob_matches("/a/*/c*", "/a/b/b/cd"));
+    assert!(!glob_matches("/a/*/c", "/a/b/cd"));
+  }
+
+  #[test]
+  fn rejects_malformed_patterns() {
+    for pattern in [
+      "localhost",
+      "://x",
+      "https://",
+      "https://ex*ample.com",
+      "http://x:port",
+    ] {
+      assert!(
+        OriginPolicy::new(Some(&[pattern.to_owned()])).is_err(),
+        "{}",
+        pattern
+      );
+    }
+  }
+}
@@ -0,0 +1,456 @@
+//! Printing a webview to PDF without a dialog.
+//!
+//! WebKitGTK prints through a GTK print operation writing to a file; a PDF
+//! wanted as a `Buffer` goes to a temporary file first.  WebKitGTK has no
+//! headers or footers, so the templates are added to the page as
+//! `position: fixed` elements, which repeat on every printed page, and
+//! removed once printing ended.  Their heights are added to the top and
+//! bottom margins and the elements are moved into that space, so they never
+//! cover the content.  Other platforms cannot print to PDF yet.
+
+// Elsewhere only the options are validated.
+#![cfg_attr(
+  not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+  )),
+  allow(dead_code)
+)]
+
+use std::path::PathBuf;
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use crate::types::PdfOptions;
+
+/// Paper sizes by name, in millimetres.
+const PAPER_SIZES: [(&str, f64, f64); 6] = [
+  ("a3", 297.0, 420.0),
+  ("a4", 210.0, 297.0),
+  ("a5", 148.0, 210.0),
+  ("letter", 215.9, 279.4),
+  ("legal", 215.9, 355.6),
+  ("tabloid", 279.4, 431.8),
+];
+
+/// Margin on every side when none is given, in millimetres.
+const DEFAULT_MARGIN: f64 = 10.0;
+
+/// Height of a header or footer when none is given, in millimetres.
+const DEFAULT_TEMPLATE_HEIGHT: f64 = 10.0;
+
+/// Adds the header and footer templates to the page, each moved out of the
+/// content area into the margin reserved for it.  Elements with the class
+/// `title`, `url` or `date` are filled in.
+const DECORATE: &str = r#"(function(header,footer,headerHeight,footerHeight){
+  if(document.getElementById('__webviewjsPrintStyle'))return;
+  const style=document.createElement('style');
+  style.id='__webviewjsPrintStyle';
+  style.textContent='.__webviewjsPrint{display:none}@media print{.__webviewjsPrint{display:block;position:fixed;left:0;right:0;overflow:hidden;z-index:2147483647}'
+    +'#__webviewjsPrintHeader{top:-'+headerHeight+'mm;height:'+headerHeight+'mm}'
+    +'#__webviewjsPrintFooter{bottom:-'+footerHeight+'mm;height:'+footerHeight+'mm}}';
+  document.head.appendChild(style);
+  const values={title:document.title,url:location.href,date:new Date().toLocaleDateString()};
+  for(const [id,html] of [['__webviewjsPrintHeader',header],['__webviewjsPrintFooter',footer]]){
+    if(html==null)continue;
+    const el=document.createElement('div');
+    el.id=id;el.className='__webviewjsPrint';el.innerHTML=html;
+    for(const key in values){for(const slot of el.getElementsByClassName(key))slot.textContent=values[key];}
+    document.body.appendChild(el);
+  }
+})"#;
+
+/// Removes what [`DECORATE`] added.
+const UNDECORATE: &str = "for(const id of ['__webviewjsPrintStyle','__webviewjsPrintHeader','__webviewjsPrintFooter'])document.getElementById(id)?.remove();";
+
+/// A validated `Webview.printToPdf()` request.  Lengths are in millimetres.
+pub(crate) struct PdfRequest {
+  /// File to write; `None` returns the PDF.
+  pub(crate) path: Option<PathBuf>,
+  /// Width and height of the paper, in portrait.
+  pub(crate) paper: (f64, f64),
+  /// Top, right, bottom and left margins, without the header and footer.
+  pub(crate) margins: [f64; 4],
+  /// Heights of the header and footer.
+  pub(crate) template_heights: (f64, f64),
+  pub(crate) landscape: bool,
+  pub(crate) scale: f64,
+  pub(crate) print_background: bool,
+  header: Option<String>,
+  footer: Option<String>,
+}
+
+impl PdfRequest {
+  pub(crate) fn new(options: Option<PdfOptions>) -> Result<Self, String> {
+    let options = options.unwrap_or_default();
+    let paper = match (options.page_width, options.page_height) {
+      (Some(width), Some(height)) => {
+        if options.page_size.is_some() {
+          return Err("pageSize cannot be combined with pageWidth and pageHeight".to_owned());
+        }
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+          return Err("pageWidth and pageHeight must be positive".to_owned());
+        }
+        (width, height)
+      }
+      (None, None) => {
+        let name = options.page_size.as_deref().unwrap_or("a4");
+        PAPER_SIZES
+          .iter()
+          .find(|(size, _, _)| size.eq_ignore_ascii_case(name))
+          .map(|&(_, width, height)| (width, height))
+          .ok_or_else(|| format!("Unknown pageSize {:?}", name))?
+      }
+      _ => return Err("pageWidth and pageHeight must be given together".to_owned()),
+    };
+    let margins = options.margins.unwrap_or_default();
+    let margins = [margins.top, margins.right, margins.bottom, margins.left]
+      .map(|margin| margin.unwrap_or(DEFAULT_MARGIN));
+    if margins
+      .iter()
+      .any(|margin| !(margin.is_finite() && *margin >= 0.0))
+    {
+      return Err("Margins must not be negative".to_owned());
+    }
+    let template_height = |height: Option<f64>, template: &Option<String>| match height {
+      Some(height) if !(height.is_finite() && height >= 0.0) => {
+        Err("headerHeight and footerHeight must not be negative".to_owned())
+      }
+      Some(height) => Ok(height),
+      None if template.is_some() => Ok(DEFAULT_TEMPLATE_HEIGHT),
+      None => Ok(0.0),
+    };
+    let template_heights = (
+      template_height(options.header_height, &options.header_template)?,
+      template_height(options.footer_height, &options.footer_template)?,
+    );
+    let landscape = options.landscape.unwrap_or(false);
+    let (width, height) = if landscape { (paper.1, paper.0) } else { paper };
+    let top = margins[0] + template_heights.0;
+    let bottom = margins[2] + template_heights.1;
+    if margins[1] + margins[3] >= width || top + bottom >= height {
+      return Err("Margins leave no room on the page".to_owned());
+    }
+    let scale = options.scale.unwrap_or(1.0);
+    if !(0.1..=2.0).contains(&scale) {
+      return Err("scale must be between 0.1 and 2".to_owned());
+    }
+    let path = match options.path {
+      Some(path) if path.is_empty() => return Err("path must not be empty".to_owned()),
+      Some(path) => {
+        Some(std::path::absolute(&path).map_err(|e| format!("Invalid path {:?}: {}", path, e))?)
+      }
+      None => None,
+    };
+    Ok(Self {
+      path,
+      paper,
+      margins,
+      template_heights,
+      landscape,
+      scale,
+      print_background: options.print_background.unwrap_or(false),
+      header: options.header_template,
+      footer: options.footer_template,
+    })
+  }
+
+  /// Margins of the printed page: the header and footer heights are added to
+  /// the top and bottom margins.
+  pub(crate) fn page_margins(&self) -> [f64; 4] {
+    let [top, right, bottom, left] = self.margins;
+    [
+      top + self.template_heights.0,
+      right,
+      bottom + self.template_heights.1,
+      left,
+    ]
+  }
+
+  /// Script adding the header and footer to the page, if there are any.
+  pub(crate) fn decorate_script(&self) -> Option<String> {
+    if self.header.is_none() && self.footer.is_none() {
+      return None;
+    }
+    let template = |html: &Option<String>| match html {
+      Some(html) => Value::String(html.clone()),
+      None => Value::Null,
+    };
+    Some(format!(
+      "{}({},{},{},{});",
+      DECORATE,
+      template(&self.header),
+      template(&self.footer),
+      self.template_heights.0,
+      self.template_heights.1
+    ))
+  }
+
+  /// Script removing the header and footer again.
+  pub(crate) fn undecorate_script(&self) -> Option<&'static str> {
+    (self.header.is_some() || self.footer.is_some()).then_some(UNDECORATE)
+  }
+}
+
+/// Print `webview` as `request` says and hand `done` the PDF, or `None`
+/// once it was written to `request.path`.
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+pub(crate) fn print_to_pdf(
+  webview: &Rc<wry::WebView>,
+  request: PdfRequest,
+  done: impl FnOnce(Result<Option<Vec<u8>>, String>) + 'static,
+) -> Result<(), String> {
+  use std::cell::RefCell;
+
+  use gtk::{gio, glib, PageOrientation, PageSetup, PaperSize, PrintSettings, Unit};
+  use webkit2gtk::{PrintOperation, PrintOperationExt, SettingsExt, WebViewExt};
+  use wry::WebViewExtUnix;
+
+  // A PDF returned as a `Buffer` is written to a temporary file first.
+  let (path, temporary) = match &request.path {
+    Some(path) => (path.clone(), None),
+    None => {
+      let file = tempfile::Builder::new()
+        .prefix("webviewjs-")
+        .suffix(".pdf")
+        .tempfile()
+        .map_err(|e| format!("Failed to create a temporary file: {}", e))?;
+      (file.path().to_owned(), Some(file))
+    }
+  };
+  let uri = glib::filename_to_uri(&path, None).map_err(|e| e.to_string())?;
+
+  let page_setup = PageSetup::new();
+  page_setup.set_paper_size(&PaperSize::new_custom(
+    "webviewjs",
+    "WebviewJS",
+    request.paper.0,
+    request.paper.1,
+    Unit::Mm,
+  ));
+  page_setup.set_orientation(if request.landscape {
+    PageOrientation::Landscape
+  } else {
+    PageOrientation::Portrait
+  });
+  let [top, right, bottom, left] = request.page_margins();
+  page_setup.set_top_margin(top, Unit::Mm);
+  page_setup.set_right_margin(right, Unit::Mm);
+  page_setup.set_bottom_margin(bottom, Unit::Mm);
+  page_setup.set_left_margin(left, Unit::Mm);
+
+  let print_settings = PrintSettings::new();
+  print_settings.set("printer", Some("Print to File"));
+  print_settings.set("output-uri", Some(uri.as_str()));
+  print_settings.set("output-file-format", Some("pdf"));
+  print_settings.set_scale(request.scale * 100.0);
+
+  let gtk_webview = webview.webview();
+  // Restored once printing ended.
+  let settings = WebViewExt::settings(&gtk_webview);
+  let print_backgrounds = settings.as_ref().map(|settings| {
+    let before = settings.is_print_backgrounds();
+    settings.set_print_backgrounds(request.print_background);
+    before
+  });
+
+  let operation = PrintOperation::new(&gtk_webview);
+  operation.set_page_setup(&page_setup);
+  operation.set_print_settings(&print_settings);
+
+  // `finished` also follows `failed`, so it settles.
+  let failure = Rc::new(RefCell::new(None));
+  {
+    let failure = Rc::clone(&failure);
+    operation.connect_failed(move |_, error| {
+      failure.replace(Some(format!("Failed to print: {}", error)));
+    });
+  }
+  let undecorate = request.undecorate_script();
+  let webview = Rc::downgrade(webview);
+  // Holds the operation until it finished.
+  let pending = RefCell::new(Some((done, temporary, operation.clone())));
+  operation.connect_finished(move |_| {
+    let Some((done, temporary, _operation)) = pending.take() else {
+      return;
+    };
+    if let (Some(settings), Some(before)) = (&settings, print_backgrounds) {
+      settings.set_print_backgrounds(before);
+    }
+    if let (Some(script), Some(webview)) = (undecorate, webview.upgrade()) {
+      let _ = webview.evaluate_script(script);
+    }
+    let result = match (failure.take(), temporary) {
+      (Some(failure), _) => Err(failure),
+      (None, Some(file)) => std::fs::read(file.path())
+        .map(Some)
+        .map_err(|e| format!("Failed to read the PDF: {}", e)),
+      (None, None) => Ok(None),
+    };
+    done(result);
+  });
+  // The header and footer must be on the page before it is printed.
+  match request.decorate_script() {
+    Some(script) => gtk_webview.run_javascript(&script, None::<&gio::Cancellable>, move |_| {
+      operation.print()
+    }),
+    None => operation.print(),
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::types::PdfMargins;
+
+  fn options() -> PdfOptions {
+    PdfOptions::default()
+  }
+
+  #[test]
+  fn requests_default_to_a4_portrait() {
+    let request = PdfRequest::new(None).unwrap();
+    assert_eq!(request.paper, (210.0, 297.0));
+    assert_eq!(request.margins, [DEFAULT_MARGIN; 4]);
+    assert_eq!(request.page_margins(), [DEFAULT_MARGIN; 4]);
+    assert!(!request.landscape && !request.print_background);
+    assert_eq!(request.scale, 1.0);
+    assert!(request.path.is_none());
+    assert!(request.decorate_script().is_none());
+    assert!(request.undecorate_script().is_none());
+  }
+
+  #[test]
+  fn requests_resolve_paper_sizes_and_margins() {
+    let request = PdfRequest::new(Some(PdfOptions {
+      page_size: Some("Letter".to_owned()),
+      margins: Some(PdfMargins {
+        top: Some(0.0),
+        left: Some(25.4),
+        ..Default::default()
+      }),
+      landscape: Some(true),
+      ..options()
+    }))
+    .unwrap();
+    assert_eq!(request.paper, (215.9, 279.4));
+    assert_eq!(request.margins, [0.0, DEFAULT_MARGIN, DEFAULT_MARGIN, 25.4]);
+
+    let custom = PdfRequest::new(Some(PdfOptions {
+      page_width: Some(100.0),
+      page_height: Some(150.0),
+      ..options()
+    }))
+    .unwrap();
+    assert_eq!(custom.paper, (100.0, 150.0));
+
+    let path = PdfRequest::new(Some(PdfOptions {
+      path: Some("out/report.pdf".to_owned()),
+      ..options()
+    }))
+    .unwrap()
+    .path
+    .unwrap();
+    assert!(path.is_absolute() && path.ends_with("out/report.pdf"));
+  }
+
+  #[test]
+  fn requests_reject_invalid_options() {
+    let invalid = [
+      PdfOptions {
+        page_size: Some("b5".to_owned()),
+        ..options()
+      },
+      PdfOptions {
+        page_width: Some(100.0),
+        ..options()
+      },
+      PdfOptions {
+        page_size: Some("a4".to_owned()),
+        page_width: Some(100.0),
+        page_height: Some(100.0),
+        ..options()
+      },
+      PdfOptions {
+        margins: Some(PdfMargins {
+          right: Some(-1.0),
+          ..Default::default()
+        }),
+        ..options()
+      },
+      PdfOptions {
+        margins: Some(PdfMargins {
+          top: Some(150.0),
+          bottom: Some(150.0),
+          ..Default::default()
+        }),
+        ..options()
+      },
+      PdfOptions {
+        header_height: Some(-1.0),
+        ..options()
+      },
+      PdfOptions {
+        page_height: Some(100.0),
+        page_width: Some(100.0),
+        header_template: Some("header".to_owned()),
+        footer_height: Some(80.0),
+        ..options()
+      },
+      PdfOptions {
+        scale: Some(3.0),
+        ..options()
+      },
+      PdfOptions {
+        path: Some(String::new()),
+        ..options()
+      },
+    ];
+    for options in invalid {
+      assert!(PdfRequest::new(Some(options)).is_err());
+    }
+  }
+
+  #[test]
+  fn templates_are_passed_as_json() {
+    let request = PdfRequest::new(Some(PdfOptions {
+      footer_template: Some("<span class=\"url\"></span> \"');".to_owned()),
+      ..options()
+    }))
+    .unwrap();
+    let script = request.decorate_script().unwrap();
+    assert!(script.ends_with(r#"(null,"<span class=\"url\"></span> \"');",0,10);"#));
+    assert_eq!(request.undecorate_script(), Some(UNDECORATE));
+  }
+
+  #[test]
+  fn templates_reserve_margin_space() {
+    let request = PdfRequest::new(Some(PdfOptions {
+      header_template: Some("header".to_owned()),
+      footer_template: Some("footer".to_owned()),
+      footer_height: Some(15.5),
+      ..options()
+    }))
+    .unwrap();
+    assert_eq!(request.margins, [DEFAULT_MARGIN; 4]);
+    assert_eq!(
+      request.page_margins(),
+      [
+        DEFAULT_MARGIN + DEFAULT_TEMPLATE_HEIGHT,
+        DEFAULT_MARGIN,
+        DEFAULT_MARGIN + 15.5,
+        DEFAULT_MARGIN
+      ]
+    );
+    assert!(request.decorate_script().unwrap().ends_with(",10,15.5);"));
+  }
+}
@@ -0,0 +1,484 @@
+//! Custom-protocol registrations and the handlers that answer them natively.
+//!
+//! Schemes registered with `BrowserWindow::register_protocol_raw` are answered
+//! by a JS function.  Native schemes (see [`crate::assets`]) are answered
+//! directly inside the `with_asynchronous_custom_protocol` closure without a
+//! round-trip through Node.  Routed schemes (see [`crate::router`]) mix both.
+
+use std::{
+  borrow::Cow,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use wry::http::{
+  header::{self, HeaderName, HeaderValue},
+  HeaderMap, Method, Request, Response, StatusCode,
+};
+
+use crate::custom_protocol_workaround::UriWorkAround;
+use crate::router::ProtocolRouter;
+use crate::types::{ProtocolCorsOptions, ProtocolHeaderPolicy};
+use crate::webview::{ProtocolCounterRef, ProtocolHandlerRef, ProtocolPendingMap};
+use crate::workers::WorkerPool;
+
+pub(crate) type ProtocolResponse = Response<Cow<'static, [u8]>>;
+
+/// A scheme answered entirely in Rust.
+///
+/// `respond` runs on a [`WorkerPool`] thread and may block on I/O.
+pub(crate) trait NativeProtocol: Send + Sync {
+  fn respond(&self, request: &Request<Vec<u8>>) -> ProtocolResponse;
+}
+
+/// How a registered scheme answers its requests.
+pub(crate) enum ProtocolHandler {
+  /// Forwarded to a JS function and answered through `_completeProtocol`.
+  Js(ProtocolHandlerRef),
+  /// Answered natively, off the main thread.
+  Native(Arc<dyn NativeProtocol>),
+  /// Dispatched by path; JS routes go through the JS function with the
+  /// route's index.
+  Router(Arc<ProtocolRouter>, ProtocolHandlerRef),
+}
+
+/// A scheme registered on a `BrowserWindow`.  Every webview created by the
+/// window afterwards receives the same registration.
+pub(crate) struct PendingProtocol {
+  pub(crate) name: String,
+  pub(crate) handler: ProtocolHandler,
+  /// In-flight requests, shared by every scheme of the window.
+  pub(crate) responders: ProtocolPendingMap,
+  pub(crate) counter: ProtocolCounterRef,
+  /// Requests left unanswered for longer are answered with `504`.
+  pub(crate) timeout: Option<Duration>,
+  pub(crate) policy: Option<Arc<HeaderPolicy>>,
+  /// Threads answering native requests.
+  pub(crate) workers: Arc<WorkerPool>,
+}
+
+// ── Pending requests ──────────────────────────────────────────────────────────
+
+/// Answers one request, adding the scheme's policy headers and converting
+/// URIs in the response headers to the URI workaround form where it applies.
+pub(crate) struct ProtocolResponder {
+  responder: wry::RequestAsyncResponder,
+  policy: Option<Arc<HeaderPolicy>>,
+  /// `Origin` header of the request, for the CORS headers of the response.
+  origin: Option<HeaderValue>,
+  work_around: Option<Arc<UriWorkAround>>,
+}
+
+impl ProtocolResponder {
+  pub(crate) fn new(
+    responder: wry::RequestAsyncResponder,
+    request: &Request<Vec<u8>>,
+    policy: Option<Arc<HeaderPolicy>>,
+    work_around: Option<Arc<UriWorkAround>>,
+  ) -> Self {
+    Self {
+      responder,
+      policy,
+      origin: request.headers().get(header::ORIGIN).cloned(),
+      work_around,
+    }
+  }
+
+  /// The policy's answer to `request` if it is a CORS preflight.
+  pub(crate) fn preflight(&self, request: &Request<Vec<u8>>) -> Option<ProtocolResponse> {
+    self.policy.as_ref()?.preflight(request)
+  }
+
+  pub(crate) fn respond(self, mut response: ProtocolResponse) {
+    if let Some(policy) = &self.policy {
+      policy.apply(self.origin.as_ref(), &mut response);
+    }
+    if let Some(work_around) = &self.work_around {
+      work_around.apply_response_headers(response.headers_mut());
+    }
+    self.responder.respond(response);
+  }
+}
+
+/// A request handed to JS that has not been answered yet.
+pub(crate) struct PendingRequest {
+  pub(crate) responder: ProtocolResponder,
+  pub(crate) scheme: String,
+  pub(crate) url: String,
+  pub(crate) method: String,
+  /// Request headers, for responses that depend on them (e.g. `Range`).
+  pub(crate) headers: HeaderMap,
+  /// `wry::WebView::id()` of the webview that issued the request.
+  pub(crate) webview_id: String,
+  pub(crate) started: Instant,
+  pub(crate) deadline: Option<Instant>,
+}
+
+impl PendingRequest {
+  pub(crate) fn respond(self, response: ProtocolResponse) {
+    self.responder.respond(response);
+  }
+
+  /// Answer with a plain-text error response.
+  pub(crate) fn fail(self, status: StatusCode) {
+    self.respond(text_response(status));
+  }
+}
+
+/// Remove the requests matching `predicate` and answer each with `status`.
+/// Returns how many requests were answered.
+pub(crate) fn fail_requests(
+  requests: &ProtocolPendingMap,
+  status: StatusCode,
+  mut predicate: impl FnMut(&PendingRequest) -> bool,
+) -> usize {
+  // Collect first: responding may re-enter the webview, which must not find
+  // the map borrowed.
+  let failed: Vec<PendingRequest> = {
+    let mut map = requests.borrow_mut();
+    let ids: Vec<u64> = map
+      .iter()
+      .filter(|(_, request)| predicate(request))
+      .map(|(id, _)| *id)
+      .collect();
+    ids.iter().filter_map(|id| map.remove(id)).collect()
+  };
+  let count = failed.len();
+  for request in failed {
+    request.fail(status);
+  }
+  count
+}
+
+/// Answer every request whose deadline has passed with `504`, and return the
+/// earliest deadline still outstanding.
+pub(crate) fn expire_requests(requests: &ProtocolPendingMap, now: Instant) -> Option<Instant> {
+  fail_requests(requests, StatusCode::GATEWAY_TIMEOUT, |request| {
+    request.deadline.is_some_and(|deadline| deadline <= now)
+  });
+  requests
+    .borrow()
+    .values()
+    .filter_map(|request| request.deadline)
+    .min()
+}
+
+// ── Header policy ─────────────────────────────────────────────────────────────
+
+const DEFAULT_CORS_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE";
+
+/// Headers added to every response of a scheme, see [`ProtocolHeaderPolicy`].
+pub(crate) struct HeaderPolicy {
+  cors: Option<CorsPolicy>,
+  headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+struct CorsPolicy {
+  /// `None` allows any origin.
+  origins: Option<Vec<String>>,
+  methods: HeaderValue,
+  allowed_headers: Option<HeaderValue>,
+  exposed_headers: Option<HeaderValue>,
+  credentials: bool,
+  max_age: Option<u32>,
+}
+
+impl HeaderPolicy {
+  pub(crate) fn new(options: ProtocolHeaderPolicy) -> std::result::Result<Self, String> {
+    let isolated = options.cross_origin_isolated.unwrap_or(false);
+    let coop = options
+      .cross_origin_opener_policy
+      .or_else(|| isolated.then(|| "same-origin".to_owned()));
+    let coep = options
+      .cross_origin_embedder_policy
+      .or_else(|| isolated.then(|| "require-corp".to_owned()));
+    let headers = [
+      (
+        header::CONTENT_SECURITY_POLICY,
+        options.content_security_policy,
+      ),
+      (HeaderName::from_static("cross-origin-opener-policy"), coop),
+      (
+        HeaderName::from_static("cross-origin-embedder-policy"),
+        coep,
+      ),
+      (
+        HeaderName::from_static("cross-origin-resource-policy"),
+        options.cross_origin_resource_policy,
+      ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .map(|(name, value)| Ok((name, header_value(&value)?)))
+    .collect::<std::result::Result<_, String>>()?;
+    Ok(Self {
+      cors: options.cors.map(CorsPolicy::new).transpose()?,
+      headers,
+    })
+  }
+
+  /// Answer `request` if it is a CORS preflight.  Preflights from origins
+  /// the policy does not allow are answered with `403`.
+  pub(crate) fn preflight(&self, request: &Request<Vec<u8>>) -> Option<ProtocolResponse> {
+    let cors = self.cors.as_ref()?;
+    let headers = request.headers();
+    if request.method() != Method::OPTIONS
+      || !headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+    {
+      return None;
+    }
+    let origin = headers.get(header::ORIGIN)?;
+    let Some(allow_origin) = cors.allow_origin(origin) else {
+      return Some(text_response(StatusCode::FORBIDDEN));
+    };
+    let mut response = Response::builder()
+      .status(StatusCode::NO_CONTENT)
+      .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin)
+      .header(header::ACCESS_CONTROL_ALLOW_METHODS, cors.methods.clone())
+      .header(header::VARY, "Origin");
+    let allowed_headers = cors
+      .allowed_headers
+      .as_ref()
+      .or_else(|| headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS));
+    if let Some(allowed_headers) = allowed_headers {
+      response = response.header(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+    }
+    if cors.credentials {
+      response = response.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+    }
+    if let Some(max_age) = cors.max_age {
+      response = response.header(header::ACCESS_CONTROL_MAX_AGE, max_age);
+    }
+    let mut response = response
+      .body(Cow::Borrowed(&[][..]))
+      .expect("preflight response is valid");
+    self.apply_headers(&mut response);
+    Some(response)
+  }
+
+  /// Add the policy's headers to the response to a request from `origin`.
+  pub(crate) fn apply(&self, origin: Option<&HeaderValue>, response: &mut ProtocolResponse) {
+    self.apply_headers(response);
+    let Some(cors) = &self.cors else {
+      return;
+    };
+    let headers = response.headers_mut();
+    if headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN) {
+      return;
+    }
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    let Some(allow_origin) = origin.and_then(|origin| cors.allow_origin(origin)) else {
+      return;
+    };
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    if cors.credentials {
+      headers.insert(
+        header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+        HeaderValue::from_static("true"),
+      );
+    }
+    if let Some(exposed) = &cors.exposed_headers {
+      headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed.clone());
+    }
+  }
+
+  fn apply_headers(&self, response: &mut ProtocolResponse) {
+    let headers = response.headers_mut();
+    for (name, value) in &self.headers {
+      if !headers.contains_key(name) {
+        headers.insert(name.clone(), value.clone());
+      }
+    }
+  }
+}
+
+impl CorsPolicy {
+  fn new(options: ProtocolCorsOptions) -> std::result::Result<Self, String> {
+    let list = |values: Vec<String>| header_value(&values.join(", "));
+    let origins = (!options.origins.iter().any(|origin| origin == "*")).then(|| {
+      options
+        .origins
+        .iter()
+        .map(|origin| origin.trim_end_matches('/').to_ascii_lowercase())
+        .collect()
+    });
+    Ok(Self {
+      origins,
+      methods: match options.methods {
+        Some(methods) => list(methods)?,
+        None => HeaderValue::from_static(DEFAULT_CORS_METHODS),
+      },
+      allowed_headers: options.allowed_headers.map(list).transpose()?,
+      exposed_headers: options.exposed_headers.map(list).transpose()?,
+      credentials: options.credentials.unwrap_or(false),
+      max_age: options.max_age,
+    })
+  }
+
+  /// The `Access-Control-Allow-Origin` value for `origin`, if it is allowed.
+  fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+    match &self.origins {
+      // A wildcard cannot be combined with credentials; echo the origin.
+      None if !self.credentials => Some(HeaderValue::from_static("*")),
+      None => Some(origin.clone()),
+      Some(origins) => {
+        let value = origin.to_str().ok()?;
+        origins
+          .iter()
+          .any(|allowed| allowed.eq_ignore_ascii_case(value))
+          .then(|| origin.clone())
+      }
+    }
+  }
+}
+
+fn header_value(value: &str) -> std::result::Result<HeaderValue, String> {
+  HeaderValue::from_str(value).map_err(|_| format!("invalid header value {:?}", value))
+}
+
+// ── HTTP helpers ──────────────────────────────────────────────────────────────
+
+/// A plain-text response whose body is the status' canonical reason.
+pub(crate) fn text_response(status: StatusCode) -> ProtocolResponse {
+  let body = status.canonical_reason().unwrap_or("Error").as_bytes();
+  Response::builder()
+    .status(status)
+    .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+    .body(Cow::Borrowed(body))
+    .expect("static protocol response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cors(origins: &[&str], credentials: bool) -> ProtocolCorsOptions {
+    ProtocolCorsOptions {
+      origins: origins.iter().map(|origin| origin.to_string()).collect(),
+      methods: None,
+      allowed_headers: None,
+      exposed_headers: Some(vec!["X-Total".to_owned()]),
+      credentials: Some(credentials),
+      max_age: Some(600),
+    }
+  }
+
+  fn policy(cors: Option<ProtocolCorsOptions>) -> HeaderPolicy {
+    HeaderPolicy::new(ProtocolHeaderPolicy {
+      cors,
+      content_security_policy: Some("default-src 'self'".to_owned()),
+      cross_origin_opener_policy: None,
+      cross_origin_embedder_policy: None,
+      cross_origin_resource_policy: None,
+      cross_origin_isolated: Some(true),
+    })
+    .unwrap()
+  }
+
+  fn preflight(origin: &str) -> Request<Vec<u8>> {
+    Request::builder()
+      .method(Method::OPTIONS)
+      .uri("api://localhost/items")
+      .header(header::ORIGIN, origin)
+      .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+      .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+      .body(Vec::new())
+      .unwrap()
+  }
+
+  #[test]
+  fn answers_preflights_for_allowed_origins() {
+    let policy = policy(Some(cors(&["app://localhost"], false)));
+    let response = policy.preflight(&preflight("app://localhost")).unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let headers = response.headers();
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "app://localhost"
+    );
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+      DEFAULT_CORS_METHODS
+    );
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+      "content-type"
+    );
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+    let response = policy.preflight(&preflight("https://evil.test")).unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  }
+
+  #[test]
+  fn leaves_other_requests_to_the_handler() {
+    let request = Request::builder()
+      .method(Method::OPTIONS)
+      .uri("api://localhost/items")
+      .body(Vec::new())
+      .unwrap();
+    assert!(policy(Some(cors(&["*"], false)))
+      .preflight(&request)
+      .is_none());
+    assert!(policy(None)
+      .preflight(&preflight("app://localhost"))
+      .is_none());
+  }
+
+  #[test]
+  fn adds_headers_without_overriding_the_handler() {
+    let policy = policy(Some(cors(&["*"], true)));
+    let mut response = text_response(StatusCode::OK);
+    response.headers_mut().insert(
+      header::CONTENT_SECURITY_POLICY,
+      HeaderValue::from_static("default-src *"),
+    );
+    let origin = HeaderValue::from_static("http://app.localhost");
+    policy.apply(Some(&origin), &mut response);
+
+    let headers = response.headers();
+    assert_eq!(headers[header::CONTENT_SECURITY_POLICY], "default-src *");
+    assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+    assert_eq!(headers["cross-origin-embedder-policy"], "require-corp");
+    // Credentials rule out the `*` wildcard.
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "http://app.localhost"
+    );
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[header::ACCESS_CONTROL_EXPOSE_HEADERS], "X-Total");
+    assert_eq!(headers[header::VARY], "Origin");
+  }
+
+  #[test]
+  fn omits_cors_headers_for_other_origins() {
+    let policy = policy(Some(cors(&["app://localhost/"], false)));
+    let mut response = text_response(StatusCode::OK);
+    let origin = HeaderValue::from_static("https://evil.test");
+    policy.apply(Some(&origin), &mut response);
+    assert!(!response
+      .headers()
+      .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    let mut response = text_response(StatusCode::OK);
+    let origin = HeaderValue::from_static("APP://localhost");
+    policy.apply(Some(&origin), &mut response);
+    assert_eq!(
+      response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "APP://localhost"
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_header_values() {
+    let result = HeaderPolicy::new(ProtocolHeaderPolicy {
+      cors: None,
+      content_security_policy: Some("a\nb".to_owned()),
+      cross_origin_opener_policy: None,
+      cross_origin_embedder_policy: None,
+      cross_origin_resource_policy: None,
+      cross_origin_isolated: None,
+    });
+    assert!(result.is_err());
+  }
+}
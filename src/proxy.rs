@@ -0,0 +1,570 @@
+//! A custom scheme forwarded to a local HTTP server, e.g. a dev server.
+//!
+//! Requests are sent over plain HTTP/1.1 with one connection per request, so
+//! no HTTP client dependency is needed.  Only `http://` upstreams are
+//! supported: the upstream is expected to run on the same machine.
+
+use std::{
+  borrow::Cow,
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpStream, ToSocketAddrs},
+  time::Duration,
+};
+
+use wry::http::{
+  header::{self, HeaderName, HeaderValue},
+  Request, Response, StatusCode, Uri,
+};
+
+use crate::protocol::{text_response, NativeProtocol, ProtocolResponse};
+
+/// Longest status line or header line accepted from the upstream.
+const MAX_LINE: usize = 64 * 1024;
+
+/// Largest response body read from the upstream.  Bodies are buffered
+/// whole, so larger ones are answered with `502 Bad Gateway`.
+const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+/// Headers that describe one connection and are never forwarded.
+const HOP_BY_HOP: [&str; 8] = [
+  "connection",
+  "keep-alive",
+  "proxy-authenticate",
+  "proxy-authorization",
+  "te",
+  "trailer",
+  "transfer-encoding",
+  "upgrade",
+];
+
+/// Forwards requests to an `http://host:port[/base]` upstream.
+pub(crate) struct HttpProxy {
+  host: String,
+  port: u16,
+  /// `host[:port]`, sent as the `Host` header.
+  authority: String,
+  /// Path prefix of every forwarded request, without a trailing `/`.
+  base_path: String,
+  timeout: Duration,
+  headers: Vec<(HeaderName, HeaderValue)>,
+  max_body: u64,
+}
+
+#[derive(Debug)]
+enum ProxyError {
+  /// The upstream could not be reached.
+  Connect,
+  /// The upstream did not answer in time.
+  Timeout,
+  /// The upstream sent something that is not HTTP/1.x.
+  Protocol,
+  /// The response body is larger than `MAX_BODY`.
+  TooLarge,
+}
+
+impl HttpProxy {
+  pub(crate) fn new(
+    upstream: &str,
+    timeout: Duration,
+    headers: Vec<(String, String)>,
+  ) -> std::result::Result<Self, String> {
+    let uri: Uri = upstream
+      .parse()
+      .map_err(|e| format!("invalid upstream {:?}: {}", upstream, e))?;
+    if uri.scheme_str() != Some("http") {
+      return Err(format!("upstream {:?} must be an http:// URL", upstream));
+    }
+    let authority = uri
+      .authority()
+      .ok_or_else(|| format!("upstream {:?} has no host", upstream))?;
+    if uri.query().is_some() {
+      return Err(format!("upstream {:?} must not have a query", upstream));
+    }
+    let headers = headers
+      .into_iter()
+      .map(|(name, value)| {
+        let name = HeaderName::from_bytes(name.as_bytes())
+          .map_err(|_| format!("invalid header name {:?}", name))?;
+        let value =
+          HeaderValue::from_str(&value).map_err(|_| format!("invalid header value {:?}", value))?;
+        Ok((name, value))
+      })
+      .collect::<std::result::Result<_, String>>()?;
+    Ok(Self {
+      host: authority
+        .host()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_owned(),
+      port: authority.port_u16().unwrap_or(80),
+      authority: authority.as_str().to_owned(),
+      base_path: uri.path().trim_end_matches('/').to_owned(),
+      timeout,
+      headers,
+      max_body: MAX_BODY,
+    })
+  }
+
+  fn forward(
+    &self,
+    request: &Request<Vec<u8>>,
+  ) -> std::result::Result<ProtocolResponse, ProxyError> {
+    let mut stream = self.connect()?;
+    stream
+      .write_all(&self.request_head(request))
+      .and_then(|()| stream.write_all(request.body()))
+      .map_err(io_error)?;
+
+    let mut reader = BufReader::new(stream);
+    // Interim `1xx` responses (e.g. `103 Early Hints`) precede the real one.
+    let (status, headers) = loop {
+      let (status, headers) = read_head(&mut reader)?;
+      if !status.is_informational() {
+        break (status, headers);
+      }
+    };
+
+    let has_body = request.method() != wry::http::Method::HEAD
+      && status != StatusCode::NO_CONTENT
+      && status != StatusCode::NOT_MODIFIED;
+    let chunked = headers
+      .get_all(header::TRANSFER_ENCODING)
+      .iter()
+      .any(|value| {
+        value
+          .to_str()
+          .is_ok_and(|v| v.to_ascii_lowercase().contains("chunked"))
+      });
+    let content_length = headers
+      .get(header::CONTENT_LENGTH)
+      .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok());
+    let body = match (has_body, chunked, content_length) {
+      (false, _, _) => Vec::new(),
+      (true, true, _) => read_chunked(&mut reader, self.max_body)?,
+      (true, false, Some(len)) if len > self.max_body => return Err(ProxyError::TooLarge),
+      (true, false, Some(len)) => {
+        let mut body = Vec::new();
+        reader
+          .by_ref()
+          .take(len)
+          .read_to_end(&mut body)
+          .map_err(io_error)?;
+        if (body.len() as u64) < len {
+          return Err(ProxyError::Protocol);
+        }
+        body
+      }
+      (true, false, None) => {
+        let mut body = Vec::new();
+        reader
+          .take(self.max_body + 1)
+          .read_to_end(&mut body)
+          .map_err(io_error)?;
+        if body.len() as u64 > self.max_body {
+          return Err(ProxyError::TooLarge);
+        }
+        body
+      }
+    };
+
+    let mut response = Response::builder().status(status);
+    for (name, value) in &headers {
+      if !is_hop_by_hop(name) {
+        response = response.header(name, value);
+      }
+    }
+    let mut response: ProtocolResponse = response
+      .body(Cow::Owned(body))
+      .map_err(|_| ProxyError::Protocol)?;
+    if chunked && has_body {
+      let len = response.body().len();
+      response
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, len.into());
+    }
+    self.rewrite_location(request, &mut response);
+    Ok(response)
+  }
+
+  fn connect(&self) -> std::result::Result<TcpStream, ProxyError> {
+    let addrs = (self.host.as_str(), self.port)
+      .to_socket_addrs()
+      .map_err(|_| ProxyError::Connect)?;
+    let stream = addrs
+      .into_iter()
+      .find_map(|addr| TcpStream::connect_timeout(&addr, self.timeout).ok())
+      .ok_or(ProxyError::Connect)?;
+    stream
+      .set_read_timeout(Some(self.timeout))
+      .and_then(|()| stream.set_write_timeout(Some(self.timeout)))
+      .map_err(|_| ProxyError::Connect)?;
+    Ok(stream)
+  }
+
+  /// Request line and headers for `request`, addressed to the upstream.
+  fn request_head(&self, request: &Request<Vec<u8>>) -> Vec<u8> {
+    let path_and_query = request
+      .uri()
+      .path_and_query()
+      .map(|pq| pq.as_str())
+      .unwrap_or("/");
+    let mut head = format!(
+      "{} {}{} HTTP/1.1\r\nHost: {}\r\n",
+      request.method(),
+      self.base_path,
+      path_and_query,
+      self.authority
+    )
+    .into_bytes();
+    let forwarded = request
+      .headers()
+      .iter()
+      .filter(|(name, _)| {
+        !is_hop_by_hop(name)
+          && *name != header::HOST
+          && *name != header::CONTENT_LENGTH
+          && !self.headers.iter().any(|(extra, _)| extra == *name)
+      })
+      .chain(self.headers.iter().map(|(name, value)| (name, value)));
+    for (name, value) in forwarded {
+      head.extend_from_slice(name.as_str().as_bytes());
+      head.extend_from_slice(b": ");
+      head.extend_from_slice(value.as_bytes());
+      head.extend_from_slice(b"\r\n");
+    }
+    if !request.body().is_empty() || !matches!(request.method().as_str(), "GET" | "HEAD") {
+      head.extend_from_slice(format!("Content-Length: {}\r\n", request.body().len()).as_bytes());
+    }
+    head.extend_from_slice(b"Connection: close\r\n\r\n");
+    head
+  }
+
+  /// Point redirects to the upstream back at the custom scheme.
+  fn rewrite_location(&self, request: &Request<Vec<u8>>, response: &mut ProtocolResponse) {
+    let (Some(scheme), Some(authority)) = (request.uri().scheme_str(), request.uri().authority())
+    else {
+      return;
+    };
+    let upstream = format!("http://{}{}", self.authority, self.base_path);
+    let rewritten = response
+      .headers()
+      .get(header::LOCATION)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|location| location.strip_prefix(&upstream))
+      .filter(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+      .and_then(|rest| {
+        let rest = if rest.starts_with('/') {
+          rest.to_owned()
+        } else {
+          format!("/{rest}")
+        };
+        HeaderValue::from_str(&format!("{scheme}://{authority}{rest}")).ok()
+      });
+    if let Some(location) = rewritten {
+      response.headers_mut().insert(header::LOCATION, location);
+    }
+  }
+}
+
+impl NativeProtocol for HttpProxy {
+  fn respond(&self, request: &Request<Vec<u8>>) -> ProtocolResponse {
+    match self.forward(request) {
+      Ok(response) => response,
+      Err(ProxyError::Connect | ProxyError::Protocol | ProxyError::TooLarge) => {
+        text_response(StatusCode::BAD_GATEWAY)
+      }
+      Err(ProxyError::Timeout) => text_response(StatusCode::GATEWAY_TIMEOUT),
+    }
+  }
+}
+
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+  HOP_BY_HOP.contains(&name.as_str())
+}
+
+fn io_error(error: std::io::Error) -> ProxyError {
+  match error.kind() {
+    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => ProxyError::Timeout,
+    _ => ProxyError::Protocol,
+  }
+}
+
+/// Read one CRLF-terminated line, without the line ending.  Header values
+/// may hold obs-text, so the line is not required to be UTF-8.
+fn read_line(reader: &mut impl BufRead) -> std::result::Result<Vec<u8>, ProxyError> {
+  let mut line = Vec::new();
+  reader
+    .take(MAX_LINE as u64)
+    .read_until(b'\n', &mut line)
+    .map_err(io_error)?;
+  if !line.ends_with(b"\n") {
+    return Err(ProxyError::Protocol);
+  }
+  while line.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+    line.pop();
+  }
+  Ok(line)
+}
+
+/// Read a status line and the headers after it.
+fn read_head(
+  reader: &mut impl BufRead,
+) -> std::result::Result<(StatusCode, wry::http::HeaderMap), ProxyError> {
+  let status_line = read_line(reader)?;
+  let mut parts = status_line.splitn(3, |b| *b == b' ');
+  let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+    return Err(ProxyError::Protocol);
+  };
+  if !version.starts_with(b"HTTP/1.") {
+    return Err(ProxyError::Protocol);
+  }
+  let status = std::str::from_utf8(code)
+    .ok()
+    .and_then(|code| code.parse::<u16>().ok())
+    .and_then(|code| StatusCode::from_u16(code).ok())
+    .ok_or(ProxyError::Protocol)?;
+
+  let mut headers = wry::http::HeaderMap::new();
+  loop {
+    let line = read_line(reader)?;
+    if line.is_empty() {
+      break;
+    }
+    let colon = line
+      .iter()
+      .position(|b| *b == b':')
+      .ok_or(ProxyError::Protocol)?;
+    let (name, value) = (&line[..colon], &line[colon + 1..]);
+    let name = HeaderName::from_bytes(name.trim_ascii()).map_err(|_| ProxyError::Protocol)?;
+    let value = HeaderValue::from_bytes(value.trim_ascii()).map_err(|_| ProxyError::Protocol)?;
+    headers.append(name, value);
+  }
+  Ok((status, headers))
+}
+
+/// Decode a `Transfer-Encoding: chunked` body of at most `max` bytes,
+/// discarding trailers.
+fn read_chunked(reader: &mut impl BufRead, max: u64) -> std::result::Result<Vec<u8>, ProxyError> {
+  let mut body = Vec::new();
+  loop {
+    let line = read_line(reader)?;
+    let line = std::str::from_utf8(&line).map_err(|_| ProxyError::Protocol)?;
+    let size = line.split(';').next().unwrap_or_default().trim();
+    let size = usize::from_str_radix(size, 16).map_err(|_| ProxyError::Protocol)?;
+    if size == 0 {
+      // Trailers end with an empty line.
+      while !read_line(reader)?.is_empty() {}
+      return Ok(body);
+    }
+    if body.len().saturating_add(size) as u64 > max {
+      return Err(ProxyError::TooLarge);
+    }
+    let start = body.len();
+    body.resize(start + size, 0);
+    reader.read_exact(&mut body[start..]).map_err(io_error)?;
+    if !read_line(reader)?.is_empty() {
+      return Err(ProxyError::Protocol);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::net::TcpListener;
+
+  /// Serve one connection with `response`, returning what the proxy sent.
+  /// `PORT` in `response` is replaced with the listening port.
+  fn upstream(response: &'static str) -> (u16, std::thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let response = response.replace("PORT", &port.to_string());
+    let handle = std::thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let mut received = String::new();
+      let mut content_length = 0;
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+          content_length = len.trim().parse().unwrap();
+        }
+        received.push_str(&line);
+        if line == "\r\n" {
+          break;
+        }
+      }
+      let mut body = vec![0; content_length];
+      reader.read_exact(&mut body).unwrap();
+      received.push_str(&String::from_utf8(body).unwrap());
+      stream.write_all(response.as_bytes()).unwrap();
+      received
+    });
+    (port, handle)
+  }
+
+  fn proxy(upstream: &str) -> HttpProxy {
+    HttpProxy::new(
+      upstream,
+      Duration::from_secs(5),
+      vec![("x-forwarded-proto".to_owned(), "app".to_owned())],
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn forwards_method_headers_and_body() {
+    let (port, server) = upstream(
+      "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\nContent-Length: 11\r\nConnection: close\r\n\r\n{\"ok\":true}",
+    );
+    let request = Request::builder()
+      .method("POST")
+      .uri("app://localhost/api/items?x=1")
+      .header("content-type", "application/json")
+      .header("connection", "keep-alive")
+      .body(b"{\"name\":\"a\"}".to_vec())
+      .unwrap();
+    let response = proxy(&format!("http://127.0.0.1:{port}/base/")).respond(&request);
+
+    let sent = server.join().unwrap();
+    assert!(sent.starts_with(&format!(
+      "POST /base/api/items?x=1 HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n"
+    )));
+    assert!(sent.contains("content-type: application/json\r\n"));
+    assert!(sent.contains("x-forwarded-proto: app\r\n"));
+    assert!(!sent.contains("keep-alive"));
+    assert!(sent.ends_with("Connection: close\r\n\r\n{\"name\":\"a\"}"));
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert_eq!(
+      response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .count(),
+      2
+    );
+    assert!(!response.headers().contains_key(header::CONNECTION));
+    assert_eq!(response.body().as_ref(), b"{\"ok\":true}");
+  }
+
+  #[test]
+  fn decodes_chunked_bodies_after_interim_responses() {
+    let (port, server) = upstream(
+      "HTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n",
+    );
+    let request = Request::builder()
+      .uri("app://localhost/")
+      .body(Vec::new())
+      .unwrap();
+    let response = proxy(&format!("http://localhost:{port}")).respond(&request);
+    server.join().unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().as_ref(), b"hello world");
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], "11");
+    assert!(!response.headers().contains_key(header::TRANSFER_ENCODING));
+  }
+
+  #[test]
+  fn rewrites_redirects_to_the_upstream() {
+    let (port, server) = upstream(
+      "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:PORT/login?next=%2F\r\nContent-Length: 0\r\n\r\n",
+    );
+    let proxy = proxy(&format!("http://127.0.0.1:{port}"));
+    let request = Request::builder()
+      .uri("app://localhost/account")
+      .body(Vec::new())
+      .unwrap();
+    let response = proxy.respond(&request);
+    server.join().unwrap();
+
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+      response.headers()[header::LOCATION],
+      "app://localhost/login?next=%2F"
+    );
+  }
+
+  #[test]
+  fn reports_unreachable_and_invalid_upstreams() {
+    let port = TcpListener::bind("127.0.0.1:0")
+      .unwrap()
+      .local_addr()
+      .unwrap()
+      .port();
+    let request = Request::builder()
+      .uri("app://localhost/")
+      .body(Vec::new())
+      .unwrap();
+    assert_eq!(
+      proxy(&format!("http://127.0.0.1:{port}"))
+        .respond(&request)
+        .status(),
+      StatusCode::BAD_GATEWAY
+    );
+
+    assert!(HttpProxy::new("https://127.0.0.1:1", Duration::from_secs(1), Vec::new()).is_err());
+    assert!(HttpProxy::new("127.0.0.1:5173", Duration::from_secs(1), Vec::new()).is_err());
+    assert!(HttpProxy::new(
+      "http://127.0.0.1:1",
+      Duration::from_secs(1),
+      vec![("bad name".to_owned(), "x".to_owned())]
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn rejects_bodies_over_the_limit() {
+    let request = Request::builder()
+      .uri("app://localhost/")
+      .body(Vec::new())
+      .unwrap();
+    for response in [
+      "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world",
+      "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+      "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello world",
+    ] {
+      for (max_body, status) in [(10, StatusCode::BAD_GATEWAY), (11, StatusCode::OK)] {
+        let (port, server) = upstream(response);
+        let mut limited = proxy(&format!("http://127.0.0.1:{port}"));
+        limited.max_body = max_body;
+        assert_eq!(limited.respond(&request).status(), status);
+        server.join().unwrap();
+      }
+    }
+  }
+
+  #[test]
+  fn times_out_silent_upstreams() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let proxy = HttpProxy::new(
+      &format!("http://127.0.0.1:{port}"),
+      Duration::from_millis(100),
+      Vec::new(),
+    )
+    .unwrap();
+    let request = Request::builder()
+      .uri("app://localhost/")
+      .body(Vec::new())
+      .unwrap();
+    assert_eq!(
+      proxy.respond(&request).status(),
+      StatusCode::GATEWAY_TIMEOUT
+    );
+    drop(listener);
+  }
+
+  #[test]
+  fn accepts_header_values_that_are_not_utf8() {
+    let mut head = &b"HTTP/1.1 200 OK\r\nX-Name: caf\xe9\r\nX-Empty:\r\n\r\n"[..];
+    let (status, headers) = read_head(&mut head).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["x-name"].as_bytes(), b"caf\xe9");
+    assert_eq!(headers["x-empty"], "");
+    assert!(read_head(&mut &b"HTTP/1.1 200 OK\r\nX-Name caf\xe9\r\n\r\n"[..]).is_err());
+  }
+}
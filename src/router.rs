@@ -0,0 +1,365 @@
+//! Path-based routing of one custom scheme to several handlers.
+//!
+//! Routes are tried in registration order; the first whose host and path
+//! pattern match the request wins.  Native targets are answered off the main
+//! thread, so only requests routed to JS pay the round-trip through Node.
+//!
+//! Paths are matched percent-decoded, as native targets read them, so an
+//! escaped character cannot slip past a route.  Escaped separators and dot
+//! segments are refused rather than normalised.
+
+use std::{borrow::Cow, sync::Arc};
+
+use wry::http::{header, Request, Response, StatusCode, Uri};
+
+use crate::assets::percent_decode;
+use crate::protocol::{NativeProtocol, ProtocolResponse};
+
+/// What a matched route does with the request.
+pub(crate) enum RouteTarget {
+  /// Handed to JS handler number `n` of the scheme's dispatcher.
+  Js(u32),
+  /// Answered in Rust, with the route's literal prefix stripped from the path.
+  Native(Arc<dyn NativeProtocol>),
+  /// Answered with a redirect.  A trailing `*` in `location` is replaced by
+  /// the rest of the path.
+  Redirect {
+    location: String,
+    status: StatusCode,
+  },
+}
+
+/// A host and path pattern bound to a [`RouteTarget`].
+pub(crate) struct Route {
+  host: Option<String>,
+  /// Pattern segments; `**` matches any number of segments and `*` anything
+  /// within one segment.
+  segments: Vec<String>,
+  /// Leading segments without wildcards, stripped for native targets.
+  literal_prefix: usize,
+  target: RouteTarget,
+}
+
+impl Route {
+  /// Parse `pattern`, which must start with `/`.
+  ///
+  /// A pattern without wildcards, or ending in `/*`, matches that path and
+  /// everything below it.  Any other pattern must match the whole path.
+  pub(crate) fn new(
+    host: Option<String>,
+    pattern: &str,
+    target: RouteTarget,
+  ) -> std::result::Result<Self, String> {
+    if !pattern.starts_with('/') {
+      return Err(format!("route pattern {:?} must start with '/'", pattern));
+    }
+    let mut segments: Vec<String> = pattern
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .map(str::to_owned)
+      .collect();
+    if let Some(segment) = segments
+      .iter()
+      .find(|segment| segment.contains("**") && segment.as_str() != "**")
+    {
+      return Err(format!(
+        "route pattern {:?}: '**' must be a whole segment, found {:?}",
+        pattern, segment
+      ));
+    }
+    match segments.last().map(String::as_str) {
+      Some("*") => {
+        segments.pop();
+        segments.push("**".to_owned());
+      }
+      _ if !pattern.contains('*') => segments.push("**".to_owned()),
+      _ => {}
+    }
+    let literal_prefix = segments
+      .iter()
+      .take_while(|segment| !segment.contains('*'))
+      .count();
+    Ok(Self {
+      host: host.map(|host| host.to_ascii_lowercase()),
+      segments,
+      literal_prefix,
+      target,
+    })
+  }
+
+  fn matches(&self, host: &str, path: &[&str]) -> bool {
+    self
+      .host
+      .as_deref()
+      .is_none_or(|expected| expected.eq_ignore_ascii_case(host))
+      && glob_segments(&self.segments, path)
+  }
+}
+
+/// The routes of one scheme.
+pub(crate) struct ProtocolRouter {
+  routes: Vec<Route>,
+}
+
+impl ProtocolRouter {
+  pub(crate) fn new(routes: Vec<Route>) -> Self {
+    Self { routes }
+  }
+
+  /// Find the first route matching `host` and `path`.  Returns its target and
+  /// the path left after removing the route's literal prefix, still encoded.
+  /// Fails with `400` for a path that cannot be decoded, or that holds an
+  /// escaped separator or a dot segment, and with `404` when no route
+  /// matches.
+  pub(crate) fn route(
+    &self,
+    host: &str,
+    path: &str,
+  ) -> std::result::Result<(&RouteTarget, String), StatusCode> {
+    let raw: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let decoded = raw
+      .iter()
+      .map(|segment| match percent_decode(segment) {
+        Some(segment) if segment == "." || segment == ".." => None,
+        Some(segment) if segment.contains(['/', '\\']) => None,
+        segment => segment,
+      })
+      .collect::<Option<Vec<String>>>()
+      .ok_or(StatusCode::BAD_REQUEST)?;
+    let segments: Vec<&str> = decoded.iter().map(String::as_str).collect();
+    let route = self
+      .routes
+      .iter()
+      .find(|route| route.matches(host, &segments))
+      .ok_or(StatusCode::NOT_FOUND)?;
+    let mut rest = format!("/{}", raw[route.literal_prefix..].join("/"));
+    if path.ends_with('/') && rest.len() > 1 {
+      rest.push('/');
+    }
+    Ok((&route.target, rest))
+  }
+}
+
+/// Match path segments against pattern segments.
+fn glob_segments(pattern: &[String], path: &[&str]) -> bool {
+  match pattern.split_first() {
+    None => path.is_empty(),
+    Some((first, rest)) if first == "**" => {
+      (0..=path.len()).any(|skip| glob_segments(rest, &path[skip..]))
+    }
+    Some((first, rest)) => match path.split_first() {
+      Some((segment, tail)) => glob_segment(first, segment) && glob_segments(rest, tail),
+      None => false,
+    },
+  }
+}
+
+/// Match one segment against a pattern where `*` matches any run of
+/// characters.
+fn glob_segment(pattern: &str, text: &str) -> bool {
+  let mut parts = pattern.split('*');
+  let first = parts.next().unwrap_or_default();
+  let Some(mut text) = text.strip_prefix(first) else {
+    return false;
+  };
+  let parts: Vec<&str> = parts.collect();
+  let Some((last, middle)) = parts.split_last() else {
+    // No wildcard at all.
+    return text.is_empty();
+  };
+  for part in middle {
+    match text.find(part) {
+      Some(index) => text = &text[index + part.len()..],
+      None => return false,
+    }
+  }
+  text.len() >= last.len() && text.ends_with(last)
+}
+
+/// Rebuild `request` with its path replaced by `path`, keeping the scheme,
+/// host and query.
+pub(crate) fn with_path(request: Request<Vec<u8>>, path: &str) -> Request<Vec<u8>> {
+  let (mut parts, body) = request.into_parts();
+  let path_and_query = match parts.uri.query() {
+    Some(query) => format!("{}?{}", path, query),
+    None => path.to_owned(),
+  };
+  let mut uri = Uri::builder().path_and_query(path_and_query.as_str());
+  if let Some(scheme) = parts.uri.scheme() {
+    uri = uri.scheme(scheme.clone());
+  }
+  if let Some(authority) = parts.uri.authority() {
+    uri = uri.authority(authority.clone());
+  }
+  if let Ok(uri) = uri.build() {
+    parts.uri = uri;
+  }
+  Request::from_parts(parts, body)
+}
+
+/// The response of a [`RouteTarget::Redirect`] for a request whose path
+/// after the route prefix is `rest`.
+pub(crate) fn redirect_response(
+  location: &str,
+  status: StatusCode,
+  rest: &str,
+  query: Option<&str>,
+) -> ProtocolResponse {
+  let mut location = match location.strip_suffix('*') {
+    Some(base) => format!("{}{}", base, rest.trim_start_matches('/')),
+    None => location.to_owned(),
+  };
+  if let Some(query) = query.filter(|_| !location.contains('?')) {
+    location.push('?');
+    location.push_str(query);
+  }
+  Response::builder()
+    .status(status)
+    .header(header::LOCATION, location)
+    .body(Cow::Borrowed(&[][..]))
+    .unwrap_or_else(|_| crate::protocol::text_response(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn router(patterns: &[(Option<&str>, &str)]) -> ProtocolRouter {
+    ProtocolRouter::new(
+      patterns
+        .iter()
+        .enumerate()
+        .map(|(index, (host, pattern))| {
+          Route::new(
+            host.map(str::to_owned),
+            pattern,
+            RouteTarget::Js(index as u32),
+          )
+          .unwrap()
+        })
+        .collect(),
+    )
+  }
+
+  fn routed(router: &ProtocolRouter, host: &str, path: &str) -> Option<(u32, String)> {
+    router
+      .route(host, path)
+      .ok()
+      .map(|(target, rest)| match target {
+        RouteTarget::Js(index) => (*index, rest),
+        _ => unreachable!(),
+      })
+  }
+
+  #[test]
+  fn prefix_patterns_match_at_segment_boundaries() {
+    let router = router(&[(None, "/api"), (None, "/assets/*")]);
+    assert_eq!(routed(&router, "localhost", "/api"), Some((0, "/".into())));
+    assert_eq!(
+      routed(&router, "localhost", "/api/users/1"),
+      Some((0, "/users/1".into()))
+    );
+    assert_eq!(routed(&router, "localhost", "/apis"), None);
+    assert_eq!(
+      routed(&router, "localhost", "/assets/css/"),
+      Some((1, "/css/".into()))
+    );
+    assert_eq!(routed(&router, "localhost", "/other"), None);
+  }
+
+  #[test]
+  fn first_matching_route_wins() {
+    let router = router(&[(None, "/api/admin"), (None, "/api"), (None, "/")]);
+    assert_eq!(routed(&router, "h", "/api/admin/x").unwrap().0, 0);
+    assert_eq!(routed(&router, "h", "/api/users").unwrap().0, 1);
+    assert_eq!(
+      routed(&router, "h", "/index.html"),
+      Some((2, "/index.html".into()))
+    );
+  }
+
+  #[test]
+  fn hosts_are_matched_case_insensitively() {
+    let router = router(&[(Some("Docs"), "/"), (Some("localhost"), "/")]);
+    assert_eq!(routed(&router, "docs", "/a").unwrap().0, 0);
+    assert_eq!(routed(&router, "LOCALHOST", "/a").unwrap().0, 1);
+    assert_eq!(routed(&router, "other", "/a"), None);
+  }
+
+  #[test]
+  fn globs_match_within_and_across_segments() {
+    let router = router(&[(None, "/*.png"), (None, "/files/**/*.txt")]);
+    assert_eq!(
+      routed(&router, "h", "/logo.png"),
+      Some((0, "/logo.png".into()))
+    );
+    assert_eq!(routed(&router, "h", "/img/logo.png"), None);
+    assert_eq!(
+      routed(&router, "h", "/files/a/b/notes.txt"),
+      Some((1, "/a/b/notes.txt".into()))
+    );
+    assert_eq!(routed(&router, "h", "/files/notes.txt").unwrap().0, 1);
+    assert_eq!(routed(&router, "h", "/files/notes.md"), None);
+  }
+
+  #[test]
+  fn paths_are_matched_decoded() {
+    let api = router(&[(None, "/api")]);
+    assert_eq!(api.route("h", "/x").err(), Some(StatusCode::NOT_FOUND));
+    let router = router(&[(None, "/admin/*"), (None, "/")]);
+    assert_eq!(routed(&router, "h", "/%61dmin/x"), Some((0, "/x".into())));
+    assert_eq!(
+      routed(&router, "h", "/files/a%20b.txt"),
+      Some((1, "/files/a%20b.txt".into()))
+    );
+    for path in [
+      "/x/%2e%2e/admin/x",
+      "/./admin/x",
+      "/admin%2fx",
+      "/admin%5Cx",
+      "/%zz",
+      "/%ff",
+    ] {
+      assert_eq!(
+        router.route("h", path).err(),
+        Some(StatusCode::BAD_REQUEST),
+        "{}",
+        path
+      );
+    }
+  }
+
+  #[test]
+  fn glob_segment_wildcards() {
+    assert!(glob_segment("*", ""));
+    assert!(glob_segment("a*c", "abbc"));
+    assert!(glob_segment("a*b*c", "abc"));
+    assert!(!glob_segment("a*a", "a"));
+    assert!(!glob_segment("abc", "abcd"));
+  }
+
+  #[test]
+  fn invalid_patterns_are_rejected() {
+    assert!(Route::new(None, "api", RouteTarget::Js(0)).is_err());
+    assert!(Route::new(None, "/a**", RouteTarget::Js(0)).is_err());
+  }
+
+  #[test]
+  fn rewrites_the_request_path() {
+    let request = Request::builder()
+      .uri("app://localhost/assets/app.js?v=2")
+      .body(Vec::new())
+      .unwrap();
+    let request = with_path(request, "/app.js");
+    assert_eq!(request.uri().to_string(), "app://localhost/app.js?v=2");
+  }
+
+  #[test]
+  fn redirects_append_the_rest_of_the_path() {
+    let response = redirect_response("/v2/*", StatusCode::FOUND, "/users/1", Some("a=1"));
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[header::LOCATION], "/v2/users/1?a=1");
+    let response = redirect_response("/login", StatusCode::SEE_OTHER, "/x", None);
+    assert_eq!(response.headers()[header::LOCATION], "/login");
+  }
+}
@@ -0,0 +1,235 @@
+//! PNG captures of what a webview renders.
+//!
+//! WebKitGTK hands its snapshots over as Cairo image surfaces of the visible
+//! viewport, in device pixels.  The requested rectangle is cut out of that
+//! and scaled down here, then encoded with the `image` crate.  Other
+//! platforms have no capture yet.
+
+// Elsewhere only the options are validated.
+#![cfg_attr(
+  not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+  )),
+  allow(dead_code)
+)]
+
+use std::io::Cursor;
+
+use image::{imageops, ImageFormat, RgbaImage};
+
+use crate::types::{SnapshotOptions, WebviewBounds};
+
+/// A validated `Webview.snapshot()` request.
+pub(crate) struct SnapshotRequest {
+  /// Area to keep, in logical pixels of the viewport.
+  rect: Option<WebviewBounds>,
+  scale: f64,
+}
+
+impl SnapshotRequest {
+  pub(crate) fn new(options: Option<SnapshotOptions>) -> Result<Self, String> {
+    let (rect, scale) = match options {
+      Some(options) => (options.rect, options.scale.unwrap_or(1.0)),
+      None => (None, 1.0),
+    };
+    if !(scale > 0.0 && scale <= 1.0) {
+      return Err("scale must be greater than 0 and at most 1".to_owned());
+    }
+    if let Some(rect) = &rect {
+      let valid = [rect.x, rect.y, rect.width, rect.height]
+        .iter()
+        .all(|value| value.is_finite())
+        && rect.x >= 0.0
+        && rect.y >= 0.0
+        && rect.width > 0.0
+        && rect.height > 0.0;
+      if !valid {
+        return Err("rect must have a non-negative position and a positive size".to_owned());
+      }
+    }
+    Ok(Self { rect, scale })
+  }
+
+  /// Crop and scale a capture of the viewport with `device_scale` device
+  /// pixels per logical pixel, and encode it as PNG.
+  pub(crate) fn encode(&self, image: RgbaImage, device_scale: f64) -> Result<Vec<u8>, String> {
+    let image = match &self.rect {
+      Some(rect) => {
+        let to_pixels = |value: f64| (value * device_scale).round().max(0.0) as u32;
+        let (x, y) = (to_pixels(rect.x), to_pixels(rect.y));
+        let right = to_pixels(rect.x + rect.width).min(image.width());
+        let bottom = to_pixels(rect.y + rect.height).min(image.height());
+        if x >= right || y >= bottom {
+          return Err("rect is outside the viewport".to_owned());
+        }
+        imageops::crop_imm(&image, x, y, right - x, bottom - y).to_image()
+      }
+      None => image,
+    };
+    let image = if self.scale < 1.0 {
+      let scaled = |value: u32| ((value as f64 * self.scale).round() as u32).max(1);
+      imageops::resize(
+        &image,
+        scaled(image.width()),
+        scaled(image.height()),
+        imageops::FilterType::Triangle,
+      )
+    } else {
+      image
+    };
+    let mut png = Vec::new();
+    image
+      .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+      .map_err(|e| format!("Failed to encode the snapshot: {}", e))?;
+    Ok(png)
+  }
+}
+
+/// Straight RGBA pixels of a Cairo image: native-endian `0xAARRGGBB` words
+/// with premultiplied alpha, `stride` bytes per row.  `opaque` images
+/// (`RGB24`) leave the alpha byte undefined.
+pub(crate) fn cairo_pixels(
+  data: &[u8],
+  width: u32,
+  height: u32,
+  stride: usize,
+  opaque: bool,
+) -> Option<RgbaImage> {
+  let row_len = width as usize * 4;
+  if stride < row_len || data.len() < stride * height.saturating_sub(1) as usize + row_len {
+    return None;
+  }
+  let mut rgba = Vec::with_capacity(row_len * height as usize);
+  for row in data.chunks(stride).take(height as usize) {
+    for pixel in row[..row_len].chunks_exact(4) {
+      let word = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+      let alpha = if opaque { 255 } else { word >> 24 };
+      let channel = |shift: u32| {
+        let value = (word >> shift) & 0xff;
+        match alpha {
+          0 => 0,
+          255 => value,
+          _ => ((value * 255 + alpha / 2) / alpha).min(255),
+        }
+      };
+      rgba.extend([channel(16), channel(8), channel(0), alpha].map(|value| value as u8));
+    }
+  }
+  RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Capture the visible viewport of `webview` and hand the PNG to `done`.
+#[cfg(all(
+  unix,
+  not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+))]
+pub(crate) fn capture(
+  webview: &wry::WebView,
+  request: SnapshotRequest,
+  done: impl FnOnce(Result<Vec<u8>, String>) + 'static,
+) {
+  use gtk::{cairo, gio};
+  use webkit2gtk::{SnapshotOptions as WebKitSnapshotOptions, SnapshotRegion, WebViewExt};
+  use wry::WebViewExtUnix;
+
+  webview.webview().snapshot(
+    SnapshotRegion::Visible,
+    WebKitSnapshotOptions::NONE,
+    None::<&gio::Cancellable>,
+    move |result| {
+      let encoded = result
+        .map_err(|e| format!("Failed to capture the webview: {}", e))
+        .and_then(|surface| {
+          let (device_scale, _) = surface.device_scale();
+          let surface = cairo::ImageSurface::try_from(surface)
+            .map_err(|_| "The snapshot is not an image".to_owned())?;
+          let opaque = match surface.format() {
+            cairo::Format::ARgb32 => false,
+            cairo::Format::Rgb24 => true,
+            format => return Err(format!("Unexpected snapshot format {:?}", format)),
+          };
+          let (width, height) = (surface.width() as u32, surface.height() as u32);
+          let stride = surface.stride() as usize;
+          let mut pixels = None;
+          surface
+            .with_data(|data| pixels = cairo_pixels(data, width, height, stride, opaque))
+            .map_err(|e| format!("Failed to read the snapshot: {}", e))?;
+          let pixels = pixels.ok_or_else(|| "The snapshot is truncated".to_owned())?;
+          request.encode(pixels, device_scale)
+        });
+      done(encoded);
+    },
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use image::GenericImageView;
+
+  use super::*;
+
+  fn request(
+    rect: Option<(f64, f64, f64, f64)>,
+    scale: Option<f64>,
+  ) -> Result<SnapshotRequest, String> {
+    SnapshotRequest::new(Some(SnapshotOptions {
+      rect: rect.map(|(x, y, width, height)| WebviewBounds {
+        x,
+        y,
+        width,
+        height,
+      }),
+      scale,
+    }))
+  }
+
+  #[test]
+  fn cairo_pixels_are_unpremultiplied() {
+    let words: [u32; 3] = [0xff10_2030, 0x8040_2000, 0x0000_0000];
+    // A padded row: stride 16 for 3 pixels.
+    let mut data: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    data.extend([0xaa; 4]);
+    let image = cairo_pixels(&data, 3, 1, 16, false).unwrap();
+    assert_eq!(
+      image.into_raw(),
+      [0x10, 0x20, 0x30, 0xff, 0x80, 0x40, 0x00, 0x80, 0, 0, 0, 0]
+    );
+    let opaque = cairo_pixels(&0x0010_2030u32.to_ne_bytes(), 1, 1, 4, true).unwrap();
+    assert_eq!(opaque.into_raw(), [0x10, 0x20, 0x30, 0xff]);
+    assert!(cairo_pixels(&data, 3, 2, 16, false).is_none());
+  }
+
+  #[test]
+  fn requests_crop_in_device_pixels_and_scale_down() {
+    let viewport = RgbaImage::new(200, 100);
+    let png = request(Some((10.0, 10.0, 40.0, 20.0)), Some(0.5))
+      .unwrap()
+      .encode(viewport.clone(), 2.0)
+      .unwrap();
+    let decoded = image::load_from_memory(&png).unwrap();
+    assert_eq!(decoded.dimensions(), (40, 20));
+
+    let clipped = request(Some((180.0, 0.0, 50.0, 50.0)), None)
+      .unwrap()
+      .encode(viewport.clone(), 1.0)
+      .unwrap();
+    let decoded = image::load_from_memory(&clipped).unwrap();
+    assert_eq!(decoded.dimensions(), (20, 50));
+
+    assert!(request(Some((250.0, 0.0, 10.0, 10.0)), None)
+      .unwrap()
+      .encode(viewport, 1.0)
+      .is_err());
+  }
+
+  #[test]
+  fn requests_reject_invalid_options() {
+    assert!(request(None, Some(0.0)).is_err());
+    assert!(request(None, Some(1.5)).is_err());
+    assert!(request(None, Some(f64::NAN)).is_err());
+    assert!(request(Some((0.0, 0.0, 0.0, 10.0)), None).is_err());
+    assert!(request(Some((-1.0, 0.0, 10.0, 10.0)), None).is_err());
+    assert!(SnapshotRequest::new(None).is_ok());
+  }
+}
@@ -167,6 +167,9 @@ pub struct IpcMessage {
   pub method: String,
   pub headers: Vec<HeaderData>,
   pub uri: String,
+  /// Whether the page posted binary data with `webviewjs.postMessage()`
+  /// rather than a string.
+  pub binary: bool,
 }
 
 /// Kept for backward compat; no longer used internally.
@@ -200,6 +203,25 @@ pub struct ApplicationRunOptions {
   pub ref_: Option<bool>,
 }
 
+/// Which webviews `Application.broadcast()` reaches.  Every live webview
+/// when both are omitted.
+#[napi(object)]
+pub struct BroadcastOptions {
+  /// Only webviews of these windows, by `BrowserWindow.id()`.
+  pub window_ids: Option<Vec<u32>>,
+  /// Only pages matching one of these origin patterns, as in `ipcOrigins`.
+  pub origins: Option<Vec<String>>,
+}
+
+/// A webview that received a broadcast event.
+#[napi(object)]
+pub struct BroadcastReceipt {
+  pub window_id: u32,
+  pub webview_id: String,
+  /// URL of the page the event was dispatched to.
+  pub url: Option<String>,
+}
+
 // ── browser_window types ──────────────────────────────────────────────────────
 
 #[napi]
@@ -363,10 +385,15 @@ pub enum Theme {
 /// Incoming request delivered to a custom-protocol handler.
 #[napi(object)]
 pub struct CustomProtocolRequest {
+  /// Identifier passed back to `_completeProtocol`.
+  pub id: f64,
   pub url: String,
   pub method: String,
   pub headers: Vec<HeaderData>,
   pub body: Option<Buffer>,
+  /// Index of the matched JS route, for schemes registered with
+  /// `registerProtocolRouter()`.
+  pub route: Option<u32>,
 }
 
 /// Response returned by a custom-protocol handler.
@@ -382,13 +409,219 @@ pub struct CustomProtocolResponse {
   pub mime_type: Option<String>,
 }
 
+/// A custom-protocol response read from disk by the native side, so large
+/// files never pass through JS.
+#[napi(object)]
+pub struct ProtocolFileResponse {
+  /// File to serve.
+  pub path: String,
+  /// First byte of the file to serve.  Defaults to 0.
+  pub offset: Option<f64>,
+  /// Number of bytes to serve from `offset`.  Defaults to the rest of the
+  /// file.
+  pub length: Option<f64>,
+  /// HTTP status code.  Defaults to 200, which also answers `Range` and
+  /// `If-None-Match` requests.
+  pub status_code: Option<u16>,
+  /// Extra response headers.
+  pub headers: Option<Vec<HeaderData>>,
+  /// MIME type.  Defaults to one derived from the file extension.
+  pub mime_type: Option<String>,
+}
+
+/// Options shared by the `BrowserWindow.register*Protocol()` methods.
+#[napi(object)]
+pub struct ProtocolOptions {
+  /// Milliseconds a request may stay unanswered by JS before it is answered
+  /// with `504 Gateway Timeout`.  No timeout by default.
+  pub timeout: Option<u32>,
+  /// Headers added to every response of the scheme.
+  pub policy: Option<ProtocolHeaderPolicy>,
+}
+
+/// Security headers applied to every response of a custom protocol.  Headers
+/// set by the handler itself take precedence.
+#[napi(object)]
+pub struct ProtocolHeaderPolicy {
+  /// Allow cross-origin requests; `OPTIONS` preflights are answered
+  /// automatically.
+  pub cors: Option<ProtocolCorsOptions>,
+  /// `Content-Security-Policy` header value.
+  pub content_security_policy: Option<String>,
+  /// `Cross-Origin-Opener-Policy` header value, e.g. `"same-origin"`.
+  pub cross_origin_opener_policy: Option<String>,
+  /// `Cross-Origin-Embedder-Policy` header value, e.g. `"require-corp"`.
+  pub cross_origin_embedder_policy: Option<String>,
+  /// `Cross-Origin-Resource-Policy` header value, e.g. `"cross-origin"`.
+  pub cross_origin_resource_policy: Option<String>,
+  /// Shorthand for `same-origin` COOP and `require-corp` COEP, which make
+  /// `SharedArrayBuffer` available to the page.
+  pub cross_origin_isolated: Option<bool>,
+}
+
+/// Cross-origin access rules of a custom protocol.
+#[napi(object)]
+pub struct ProtocolCorsOptions {
+  /// Allowed origins, e.g. `"app://localhost"` or `"http://app.localhost"`,
+  /// or `"*"` for any origin.
+  pub origins: Vec<String>,
+  /// Allowed methods.  Defaults to `GET`, `HEAD`, `POST`, `PUT`, `PATCH` and
+  /// `DELETE`.
+  pub methods: Option<Vec<String>>,
+  /// Allowed request headers.  Defaults to the headers a preflight asks for.
+  pub allowed_headers: Option<Vec<String>>,
+  /// Response headers readable by the page.
+  pub exposed_headers: Option<Vec<String>>,
+  /// Allow cookies and other credentials.  Defaults to `false`.
+  pub credentials: Option<bool>,
+  /// Seconds a preflight answer may be cached.
+  pub max_age: Option<u32>,
+}
+
+/// An in-flight custom-protocol request, as listed by
+/// `BrowserWindow.pendingProtocolRequests()`.
+#[napi(object)]
+pub struct ProtocolRequestInfo {
+  pub id: f64,
+  pub scheme: String,
+  pub url: String,
+  pub method: String,
+  /// Milliseconds since the request reached the handler.
+  pub elapsed_ms: f64,
+}
+
+/// Options for `BrowserWindow.registerArchiveProtocol()`.
+#[napi(object)]
+pub struct ArchiveProtocolOptions {
+  /// Path of a `.zip`, `.tar` or `.tar.gz` file.  Exactly one of `path` and
+  /// `data` must be set.
+  pub path: Option<String>,
+  /// Archive contents, e.g. embedded in a single-file executable.
+  pub data: Option<Buffer>,
+  /// Directory inside the archive to serve, e.g. `"dist"`.  Defaults to the
+  /// archive root.
+  pub root: Option<String>,
+  /// File served for directory requests.  Defaults to `"index.html"`.
+  pub index: Option<String>,
+  /// Serve the index file for extension-less paths that do not exist, so
+  /// client-side routers work.  Defaults to `true`.
+  pub spa_fallback: Option<bool>,
+  /// Headers added to every response (e.g. a `Content-Security-Policy`).
+  pub headers: Option<Vec<HeaderData>>,
+}
+
+/// Options for `BrowserWindow.registerProxyProtocol()`.
+#[napi(object)]
+pub struct ProxyProtocolOptions {
+  /// Local HTTP server to forward to, e.g. `"http://127.0.0.1:5173"`.  A path
+  /// is prepended to every forwarded path.
+  pub upstream: String,
+  /// Headers added to every forwarded request, replacing the page's.
+  pub headers: Option<Vec<HeaderData>>,
+  /// Milliseconds to wait for the upstream to connect and for each read.
+  /// Defaults to 30 seconds.
+  pub timeout: Option<u32>,
+}
+
+/// A route of `BrowserWindow.registerProtocolRouter()`.  Exactly one of
+/// `handler`, `directory`, `archive`, `proxy` and `redirect` must be set.
+#[napi(object)]
+pub struct ProtocolRoute {
+  /// Host to match, e.g. `"localhost"`.  Matches any host by default.
+  pub host: Option<String>,
+  /// Path pattern, e.g. `"/api"`, `"/assets/*"` or `"/*.png"`.
+  pub path: String,
+  /// Index of the JS handler, assigned by the JS wrapper.
+  pub handler: Option<u32>,
+  /// Serve a local directory below the route prefix.
+  pub directory: Option<StaticProtocolOptions>,
+  /// Serve an archive below the route prefix.
+  pub archive: Option<ArchiveProtocolOptions>,
+  /// Forward requests below the route prefix to a local HTTP server.
+  pub proxy: Option<ProxyProtocolOptions>,
+  /// Redirect target.  A trailing `*` is replaced by the rest of the path.
+  pub redirect: Option<String>,
+  /// Redirect status code.  Defaults to 302.
+  pub status: Option<u16>,
+}
+
+/// Options for `BrowserWindow.registerStaticProtocol()`.
+#[napi(object)]
+pub struct StaticProtocolOptions {
+  /// Directory to serve.  Requests can never resolve outside of it.
+  pub root: String,
+  /// File served for directory requests.  Defaults to `"index.html"`.
+  pub index: Option<String>,
+  /// Serve the index file for extension-less paths that do not exist, so
+  /// client-side routers work.  Defaults to `true`.
+  pub spa_fallback: Option<bool>,
+  /// Headers added to every response (e.g. a `Content-Security-Policy`).
+  pub headers: Option<Vec<HeaderData>>,
+}
+
 /// Data sent to the expose handler when the page calls a proxied function.
 #[napi(object)]
 pub struct ExposeCallData {
   pub ns: String,
   pub method: String,
+  /// Native call id, passed back to `Webview._exposeReply()`.
   pub id: f64,
   pub args_json: String,
+  /// Set for `next` and `return` calls on a stream opened with
+  /// `Webview._exposeStream()`; a `return` call needs no reply.
+  pub stream: Option<f64>,
+  /// Ids of the page functions in `args_json`, for
+  /// `Webview._invokeCallback()`.
+  pub callbacks: Option<Vec<f64>>,
+}
+
+/// Error an exposed call is rejected with in the page.
+#[napi(object)]
+pub struct ExposeCallError {
+  /// `Error.name` in the page.  Defaults to `"Error"`.
+  pub name: Option<String>,
+  pub message: String,
+}
+
+/// An event emitted by the page with `window.webviewjs.emit()`.
+#[napi(object)]
+pub struct PageEventData {
+  pub event: String,
+  /// JSON payload; absent when the page emitted `undefined`.
+  pub payload_json: Option<String>,
+}
+
+/// How a script run with `Webview._evaluateInternal()` ended.
+#[napi(object)]
+pub struct EvaluationData {
+  /// JSON of the value it completed with; absent for `undefined` and
+  /// errors.
+  pub value_json: Option<String>,
+  /// Set when it threw or its promise rejected.
+  pub error: Option<EvaluationError>,
+  /// Set when the page navigated or the webview was disposed first.
+  pub aborted: Option<String>,
+}
+
+/// An exception thrown in the page.
+#[napi(object)]
+pub struct EvaluationError {
+  /// `Error.name` in the page.
+  pub name: String,
+  pub message: String,
+  /// `Error.stack` in the page, when there is one.
+  pub stack: Option<String>,
+}
+
+/// A message relayed by a `WebviewChannel`, or its closing.
+#[napi(object)]
+pub struct ChannelEventData {
+  /// `"message"` or `"close"`.
+  pub event: String,
+  /// Which webview posted a message: 0 for the first, 1 for the second.
+  pub port: Option<u32>,
+  /// JSON data of a message.
+  pub data_json: Option<String>,
 }
 
 #[napi(object)]
@@ -411,6 +644,103 @@ pub struct WebviewBounds {
   pub height: f64,
 }
 
+/// Options of `Webview.snapshot()`.
+#[napi(object)]
+pub struct SnapshotOptions {
+  /// Area to capture, in logical pixels relative to the viewport.  Defaults
+  /// to the whole visible viewport.
+  pub rect: Option<WebviewBounds>,
+  /// Factor to scale the image down by, greater than 0 and at most 1.
+  /// Defaults to 1, one image pixel per device pixel.
+  pub scale: Option<f64>,
+}
+
+/// How a `Webview._snapshotInternal()` capture ended.
+#[napi(object)]
+pub struct SnapshotData {
+  /// The PNG image.
+  pub png: Option<Buffer>,
+  pub error: Option<String>,
+}
+
+/// Page margins of `Webview.printToPdf()`, in millimetres.  Each defaults
+/// to 10.
+#[napi(object)]
+#[derive(Default)]
+pub struct PdfMargins {
+  pub top: Option<f64>,
+  pub right: Option<f64>,
+  pub bottom: Option<f64>,
+  pub left: Option<f64>,
+}
+
+/// Options of `Webview.printToPdf()`.
+#[napi(object)]
+#[derive(Default)]
+pub struct PdfOptions {
+  /// File to write the PDF to.  Without it the PDF is returned.
+  pub path: Option<String>,
+  /// `"a3"`, `"a4"`, `"a5"`, `"letter"`, `"legal"` or `"tabloid"`.
+  /// Defaults to `"a4"`.
+  pub page_size: Option<String>,
+  /// Paper width in millimetres, instead of `page_size`.
+  pub page_width: Option<f64>,
+  /// Paper height in millimetres, instead of `page_size`.
+  pub page_height: Option<f64>,
+  pub margins: Option<PdfMargins>,
+  pub landscape: Option<bool>,
+  /// Between 0.1 and 2.  Defaults to 1.
+  pub scale: Option<f64>,
+  /// Print background colours and images.  Defaults to `false`.
+  pub print_background: Option<bool>,
+  /// HTML repeated at the top of every page.  Elements with the class
+  /// `title`, `url` or `date` are filled in.
+  pub header_template: Option<String>,
+  /// HTML repeated at the bottom of every page, like `header_template`.
+  pub footer_template: Option<String>,
+  /// Height of the header in millimetres, added to the top margin.
+  /// Defaults to 10 with a `header_template`.
+  pub header_height: Option<f64>,
+  /// Height of the footer in millimetres, added to the bottom margin.
+  /// Defaults to 10 with a `footer_template`.
+  pub footer_height: Option<f64>,
+}
+
+/// How a `Webview._printToPdfInternal()` print ended.
+#[napi(object)]
+pub struct PdfData {
+  /// The PDF, unless it was written to `path`.
+  pub pdf: Option<Buffer>,
+  pub error: Option<String>,
+}
+
+/// Options of `Webview.find()`.
+#[napi(object)]
+#[derive(Default)]
+pub struct FindOptions {
+  /// Defaults to `false`.
+  pub case_sensitive: Option<bool>,
+  /// Continue from the other end of the page after the last match.
+  /// Defaults to `true`.
+  pub wrap: Option<bool>,
+}
+
+/// An entry of a webview's back-forward list.
+#[napi(object)]
+pub struct HistoryEntry {
+  pub url: String,
+  pub title: Option<String>,
+}
+
+/// A webview's back-forward list, oldest entry first.
+#[napi(object)]
+pub struct WebviewHistory {
+  pub entries: Vec<HistoryEntry>,
+  /// Position of the current entry in `entries`, or -1 before the first
+  /// page loaded.
+  pub index: i32,
+}
+
 /// Event types fired by a Webview and surfaced as EventEmitter events in JS.
 #[napi]
 #[derive(Default)]
@@ -425,6 +755,12 @@ pub enum WebviewEventType {
   /// Fired when a page attempts to open a new browser window
   /// (`window.open`, `target="_blank"`, etc.).
   NewWindowRequested,
+  /// Fired when page messages run into the webview's `ipcLimits`.
+  IpcLimitExceeded,
+  /// Fired when the back-forward list or the current entry changes.
+  HistoryChanged,
+  /// Fired when the matches of a `find()` search were counted.
+  FoundInPage,
 }
 
 /// Payload delivered to the webview event dispatch callback.
@@ -438,6 +774,34 @@ pub struct WebviewEventPayload {
   pub title: Option<String>,
   /// Download success flag for `DownloadCompleted` events.
   pub success: Option<bool>,
+  /// Limit hit by `IpcLimitExceeded` events: `"size"` or `"rate"`.
+  pub reason: Option<String>,
+  /// Messages discarded since the last `IpcLimitExceeded` event for the same
+  /// limit.
+  pub count: Option<u32>,
+  /// Whether there is an entry to go back to, for `HistoryChanged` events.
+  pub can_go_back: Option<bool>,
+  /// Whether there is an entry to go forward to, for `HistoryChanged` events.
+  pub can_go_forward: Option<bool>,
+  /// Search text for `FoundInPage` events.
+  pub text: Option<String>,
+  /// Matches of the search for `FoundInPage` events.
+  pub matches: Option<u32>,
+}
+
+/// Limits on the IPC messages a webview's pages send to Node.
+#[napi(object)]
+pub struct IpcLimitOptions {
+  /// Largest message accepted, in bytes.
+  pub max_message_size: Option<u32>,
+  /// Messages delivered per second.  Bursts of up to this many messages are
+  /// delivered at once.
+  pub max_messages_per_second: Option<u32>,
+  /// Messages over the rate that wait for delivery (default: 0).
+  pub max_queued_messages: Option<u32>,
+  /// `"drop"` discards the oldest queued message when the queue is full,
+  /// `"reject"` the new one (default: `"drop"`).
+  pub overflow: Option<String>,
 }
 
 #[napi(object)]
@@ -461,6 +825,13 @@ pub struct WebviewOptions {
   pub back_forward_navigation_gestures: Option<bool>,
   /// Custom name for the IPC global injected by wry (default: `"ipc"`).
   pub ipc_name: Option<String>,
+  /// Origin or URL patterns of the pages whose IPC messages and events reach
+  /// Node, e.g. `["app://localhost", "https://*.example.com"]`.  Also the
+  /// default for `expose()`.  Every page is allowed when omitted.
+  pub ipc_origins: Option<Vec<String>>,
+  /// Size and rate limits on IPC messages, reported with `IpcLimitExceeded`
+  /// events.  Unlimited when omitted.
+  pub ipc_limits: Option<IpcLimitOptions>,
   /// Whether to automatically normalize URL passed to `loadUrl` or `loadUrlWithHeaders` on windows (default: true).
   pub auto_normalize_load_url: Option<bool>,
   /// Determines whether the custom protocols should use `https://<scheme>.path/to/page` instead of the default `http://<scheme>.path/to/page`.
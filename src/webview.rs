@@ -1,25 +1,41 @@
 use std::{
   cell::{Cell, Ref, RefCell},
   rc::Rc,
+  time::{Duration, Instant},
 };
 // wry::WebView is not Send, so Rc (not Arc) is correct here — everything
 // runs on the main thread.
 
 use napi::{
-  bindgen_prelude::FunctionRef,
+  bindgen_prelude::{FunctionRef, Uint8Array},
   threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
-  Env, Result,
+  Either, Env, Result,
 };
 use napi_derive::*;
 use std::sync::Arc;
 use tao::window::Window;
 use wry::{
-  http::Request, NewWindowFeatures, NewWindowResponse, PageLoadEvent, Rect, WebViewBuilder,
+  http::{Request, StatusCode},
+  NewWindowFeatures, NewWindowResponse, PageLoadEvent, Rect, WebViewBuilder,
 };
 
+use crate::bridge::{ExposeOutcome, PageBridge, PageMessage, MESSAGE_SCHEME};
 use crate::browser_window::next_protocol_id;
+use crate::custom_protocol_workaround::UriWorkAround;
+use crate::find;
+use crate::history::{self, Step};
+use crate::ipc_limits::{IpcLimits, IpcQueue, IpcQueues};
+use crate::origins::OriginPolicy;
+use crate::pdf::PdfRequest;
+use crate::protocol::{
+  fail_requests, text_response, NativeProtocol, PendingProtocol, PendingRequest, ProtocolHandler,
+  ProtocolResponder,
+};
+use crate::router::{redirect_response, with_path, RouteTarget};
+use crate::snapshot::SnapshotRequest;
 use crate::types::*;
 use crate::web_context::JsWebContext;
+use crate::workers::WorkerPool;
 
 /// Shared reference to the webview event dispatch callback.
 /// The `Arc<ThreadsafeFunction>` wrapper lets us cheaply clone the pointer into
@@ -27,6 +43,8 @@ use crate::web_context::JsWebContext;
 pub(crate) type WebviewEventHandlerRef =
   Rc<RefCell<Option<Arc<ThreadsafeFunction<WebviewEventPayload>>>>>;
 pub(crate) type WebviewResource = Rc<RefCell<Option<Rc<wry::WebView>>>>;
+/// The page bridges of a window's webviews, with their ids.
+pub(crate) type PageBridges = Rc<RefCell<Vec<(String, Rc<PageBridge>)>>>;
 
 /// Shared reference to a sync bool-returning JS function (navigation guard).
 /// `with_navigation_handler` doesn't require `Send`, so `FunctionRef` is fine.
@@ -51,14 +69,155 @@ fn call_bool_handler(handler: &WebviewBoolHandlerRef, env: Env, url: String) ->
     .unwrap_or(true)
 }
 
-/// Internal type alias for async protocol pending-responder maps.
+/// Call the user's IPC handler, if any, with `req`.
+fn call_ipc_handler(
+  handler: &Rc<RefCell<Option<FunctionRef<IpcMessage, ()>>>>,
+  env: Env,
+  req: Request<Vec<u8>>,
+  binary: bool,
+) {
+  let borrowed = RefCell::borrow(handler);
+  let Some(on_ipc_msg) = borrowed
+    .as_ref()
+    .and_then(|func| func.borrow_back(&env).ok())
+  else {
+    return;
+  };
+  let headers = req
+    .headers()
+    .iter()
+    .map(|(k, v)| HeaderData {
+      key: k.as_str().to_string(),
+      value: v.to_str().ok().map(|s| s.to_string()),
+    })
+    .collect::<Vec<_>>();
+  let (parts, body) = req.into_parts();
+  let _ = on_ipc_msg.call(IpcMessage {
+    body: body.into(),
+    headers,
+    method: parts.method.to_string(),
+    uri: parts.uri.to_string(),
+    binary,
+  });
+}
+
+/// Scheme custom protocols are served under on Windows and Android, as
+/// `{scheme}://{protocol}.{host}`; `None` where they keep their own scheme.
+fn work_around_scheme(use_https_scheme: Option<bool>) -> Option<&'static str> {
+  if cfg!(target_os = "windows") && use_https_scheme.unwrap_or(false) {
+    Some("https")
+  } else if cfg!(any(target_os = "windows", target_os = "android")) {
+    Some("http")
+  } else {
+    None
+  }
+}
+
+/// Internal type alias for async protocol pending-request maps.
 pub(crate) type ProtocolPendingMap =
-  Rc<RefCell<std::collections::HashMap<u64, wry::RequestAsyncResponder>>>;
+  Rc<RefCell<std::collections::HashMap<u64, crate::protocol::PendingRequest>>>;
 /// Internal type alias for async protocol JS handler.
-pub(crate) type ProtocolHandlerRef = Rc<RefCell<Option<FunctionRef<String, ()>>>>;
+pub(crate) type ProtocolHandlerRef = Rc<RefCell<Option<FunctionRef<CustomProtocolRequest, ()>>>>;
 /// Internal type alias for async protocol ID counter.
 pub(crate) type ProtocolCounterRef = Rc<RefCell<u64>>;
 
+/// Hands custom-protocol requests to a JS function.  Everything runs on the
+/// main thread: wry's asynchronous protocol closure is not required to be
+/// `Send`, so the `Rc<RefCell<>>` state is safe to share.
+struct JsProtocolDispatch {
+  env: Env,
+  handler: ProtocolHandlerRef,
+  requests: ProtocolPendingMap,
+  counter: ProtocolCounterRef,
+  scheme: String,
+  timeout: Option<Duration>,
+}
+
+impl JsProtocolDispatch {
+  /// Record `req` as pending and call the JS function with it.  `route` is
+  /// the index of the matched JS route of a routed scheme.
+  fn dispatch(
+    &self,
+    webview_id: &str,
+    req: Request<Vec<u8>>,
+    responder: ProtocolResponder,
+    route: Option<u32>,
+  ) {
+    // Assign a unique ID for this request
+    let id = next_protocol_id(&self.counter);
+    let (parts, body) = req.into_parts();
+    let started = Instant::now();
+    self.requests.borrow_mut().insert(
+      id,
+      PendingRequest {
+        responder,
+        scheme: self.scheme.clone(),
+        url: parts.uri.to_string(),
+        method: parts.method.to_string(),
+        headers: parts.headers.clone(),
+        webview_id: webview_id.to_owned(),
+        started,
+        deadline: self.timeout.map(|timeout| started + timeout),
+      },
+    );
+
+    let request = CustomProtocolRequest {
+      id: id as f64,
+      url: parts.uri.to_string(),
+      method: parts.method.to_string(),
+      headers: parts
+        .headers
+        .iter()
+        .map(|(k, v)| HeaderData {
+          key: k.as_str().to_owned(),
+          value: v.to_str().ok().map(str::to_owned),
+        })
+        .collect(),
+      body: (!body.is_empty()).then(|| body.into()),
+      route,
+    };
+
+    // Call the JS handler — safe because we're on the main thread
+    let borrowed = self.handler.borrow();
+    let callback_result = borrowed
+      .as_ref()
+      .ok_or("Protocol handler is not registered")
+      .and_then(|func_ref| {
+        func_ref
+          .borrow_back(&self.env)
+          .map_err(|_| "Protocol handler is unavailable")
+      })
+      .and_then(|func| {
+        func
+          .call(request)
+          .map_err(|_| "Protocol handler invocation failed")
+      });
+
+    if let Err(message) = callback_result {
+      let pending = self.requests.borrow_mut().remove(&id);
+      if let Some(pending) = pending {
+        let response = wry::http::Response::builder()
+          .status(500)
+          .header("Content-Type", "text/plain")
+          .body(std::borrow::Cow::Owned(message.as_bytes().to_vec()))
+          .expect("static protocol fallback response is valid");
+        pending.respond(response);
+      }
+    }
+  }
+}
+
+/// Answer `req` with `native` on a worker thread so I/O stays off the main
+/// thread; the responder is `Send`.
+fn spawn_native_response(
+  workers: &WorkerPool,
+  native: Arc<dyn NativeProtocol>,
+  req: Request<Vec<u8>>,
+  responder: ProtocolResponder,
+) {
+  workers.execute(move || responder.respond(native.respond(&req)));
+}
+
 impl Default for WebviewOptions {
   fn default() -> Self {
     Self {
@@ -80,6 +239,8 @@ impl Default for WebviewOptions {
       autoplay: Some(true),
       back_forward_navigation_gestures: Some(true),
       ipc_name: None,
+      ipc_origins: None,
+      ipc_limits: None,
       auto_normalize_load_url: Some(true),
       use_https_scheme: Some(false),
     }
@@ -92,12 +253,18 @@ pub struct JsWebview {
   // even if JS garbage-collects this handle.
   pub(crate) webview_inner: WebviewResource,
   ipc_state: Rc<RefCell<Option<FunctionRef<IpcMessage, ()>>>>,
-  // expose() handlers: namespace → JS function that receives ExposeCallData.
-  expose_handlers: Rc<RefCell<std::collections::HashMap<String, FunctionRef<ExposeCallData, ()>>>>,
+  // expose() namespaces, their pending calls and messages to the page.
+  bridge: Rc<PageBridge>,
   disposed: Rc<Cell<bool>>,
   auto_normalize_load_url: bool,
   protocols: Vec<String>,
   https_scheme_enabled: bool,
+  // In-flight custom-protocol requests of the owning window; the ones issued
+  // by this webview are answered with 503 on dispose.
+  protocol_requests: ProtocolPendingMap,
+  // Messages held back by `ipcLimits`, drained by the application.
+  ipc_queue: Option<Rc<IpcQueue>>,
+  webview_id: String,
 }
 
 #[napi]
@@ -107,13 +274,9 @@ impl JsWebview {
     window: &Arc<Window>,
     options: WebviewOptions,
     web_context: Option<&mut crate::web_context::JsWebContext>,
-    // (scheme, js_handler_ref, pending_responders, id_counter)
-    protocols: &[(
-      String,
-      ProtocolHandlerRef,
-      ProtocolPendingMap,
-      ProtocolCounterRef,
-    )],
+    protocols: &[PendingProtocol],
+    protocol_requests: &ProtocolPendingMap,
+    ipc_queues: &IpcQueues,
     event_handler: WebviewEventHandlerRef,
     nav_handler: WebviewBoolHandlerRef,
   ) -> Result<Self> {
@@ -182,6 +345,38 @@ impl JsWebview {
       webview = webview.with_incognito(incognito);
     }
 
+    // Filled in once the webview is built; handlers below only run after that.
+    let webview_inner: WebviewResource = Rc::new(RefCell::new(None));
+    let message_origin = match work_around_scheme(options.use_https_scheme) {
+      Some(scheme) => format!("{}://{}.localhost", scheme, MESSAGE_SCHEME),
+      None => format!("{}://localhost", MESSAGE_SCHEME),
+    };
+    // On Windows and Android pages are loaded from `http(s)://{scheme}.{host}`
+    // URLs.
+    let work_around = work_around_scheme(options.use_https_scheme).map(|http_or_https| {
+      let protocols = protocols.iter().map(|p| p.name.clone()).collect();
+      Arc::new(UriWorkAround::new(http_or_https, protocols))
+    });
+    let ipc_origins = OriginPolicy::new(options.ipc_origins.as_deref())
+      .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))?;
+    let ipc_limits = options
+      .ipc_limits
+      .as_ref()
+      .map(IpcLimits::new)
+      .transpose()
+      .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))?
+      .flatten();
+    let bridge = Rc::new(PageBridge::new(
+      *env,
+      Rc::clone(&webview_inner),
+      &message_origin,
+      ipc_origins,
+      work_around.clone(),
+    ));
+    // Page bridge runtime, ahead of preload scripts so they can await
+    // `window.webviewjs.ready`.
+    webview = webview.with_initialization_script(bridge.runtime());
+
     if let Some(preload) = options.preload {
       webview = webview.with_initialization_script(&preload);
     }
@@ -262,9 +457,14 @@ impl JsWebview {
     // ── Page-load handler ─────────────────────────────────────────────────────
     {
       let ev_rc = Rc::clone(&event_handler);
+      let bridge = Rc::clone(&bridge);
       webview = webview.with_on_page_load_handler(move |event: PageLoadEvent, url: String| {
         let ev_type = match event {
-          PageLoadEvent::Started => WebviewEventType::PageLoadStarted,
+          PageLoadEvent::Started => {
+            // Calls of the outgoing document can never be answered.
+            bridge.navigated();
+            WebviewEventType::PageLoadStarted
+          }
           PageLoadEvent::Finished => WebviewEventType::PageLoadFinished,
         };
         dispatch_event(
@@ -356,150 +556,145 @@ impl JsWebview {
     // ── Custom protocols (async) ──────────────────────────────────────────────
     // wry's with_asynchronous_custom_protocol closure is NOT required to be
     // Send, so Rc<RefCell<>> is safe — everything runs on the main thread.
-    let env_copy = *env;
-    for (name, handler_ref, responders_rc, counter_rc) in protocols {
-      let handler_rc = Rc::clone(handler_ref);
-      let resp_rc = Rc::clone(responders_rc);
-      let ctr_rc = Rc::clone(counter_rc);
-      let env_c = env_copy;
-
-      webview =
-        webview.with_asynchronous_custom_protocol(name.clone(), move |_id, req, responder| {
-          // Assign a unique ID for this request
-          let id = next_protocol_id(&ctr_rc);
-          resp_rc.borrow_mut().insert(id, responder);
-
-          // Build JSON payload for the JS handler
-          let headers_json = req
-            .headers()
-            .iter()
-            .map(|(k, v)| serde_json::json!({ "key": k.as_str(), "value": v.to_str().ok() }))
-            .collect::<Vec<_>>();
-
-          let body_bytes = req.body();
-          let body_value = if body_bytes.is_empty() {
-            serde_json::Value::Null
-          } else {
-            serde_json::Value::Array(
-              body_bytes
-                .iter()
-                .map(|&b| serde_json::Value::Number(b.into()))
-                .collect(),
-            )
-          };
-
-          let payload = serde_json::json!({
-            "id":      id as f64,
-            "url":     req.uri().to_string(),
-            "method":  req.method().to_string(),
-            "headers": headers_json,
-            "body":    body_value,
+    // wry reverts the work around in request URIs but not in headers.
+    for protocol in protocols {
+      let js = |handler: &ProtocolHandlerRef| JsProtocolDispatch {
+        env: *env,
+        handler: Rc::clone(handler),
+        requests: Rc::clone(&protocol.responders),
+        counter: Rc::clone(&protocol.counter),
+        scheme: protocol.name.clone(),
+        timeout: protocol.timeout,
+      };
+      let handler: Box<dyn Fn(&str, Request<Vec<u8>>, ProtocolResponder)> = match &protocol.handler
+      {
+        ProtocolHandler::Js(handler_ref) => {
+          let dispatch = js(handler_ref);
+          Box::new(move |webview_id, req, responder| {
+            dispatch.dispatch(webview_id, req, responder, None)
+          })
+        }
+        ProtocolHandler::Native(native) => {
+          let native = Arc::clone(native);
+          let workers = Arc::clone(&protocol.workers);
+          Box::new(move |_id, req, responder| {
+            spawn_native_response(&workers, Arc::clone(&native), req, responder)
           })
-          .to_string();
-
-          // Call the JS handler — safe because we're on the main thread
-          let borrowed = handler_rc.borrow();
-          let callback_result = borrowed
-            .as_ref()
-            .ok_or("Protocol handler is not registered")
-            .and_then(|func_ref| {
-              func_ref
-                .borrow_back(&env_c)
-                .map_err(|_| "Protocol handler is unavailable")
-            })
-            .and_then(|func| {
-              func
-                .call(payload)
-                .map_err(|_| "Protocol handler invocation failed")
-            });
-
-          if let Err(message) = callback_result {
-            if let Some(responder) = resp_rc.borrow_mut().remove(&id) {
-              let response = wry::http::Response::builder()
-                .status(500)
-                .header("Content-Type", "text/plain")
-                .body(std::borrow::Cow::Owned(message.as_bytes().to_vec()))
-                .expect("static protocol fallback response is valid");
-              responder.respond(response);
+        }
+        ProtocolHandler::Router(router, handler_ref) => {
+          let dispatch = js(handler_ref);
+          let router = Arc::clone(router);
+          let workers = Arc::clone(&protocol.workers);
+          Box::new(move |webview_id, req, responder| {
+            let host = req.uri().host().unwrap_or_default().to_owned();
+            let (target, rest) = match router.route(&host, req.uri().path()) {
+              Ok(route) => route,
+              Err(status) => return responder.respond(text_response(status)),
+            };
+            match target {
+              RouteTarget::Js(index) => dispatch.dispatch(webview_id, req, responder, Some(*index)),
+              RouteTarget::Native(native) => spawn_native_response(
+                &workers,
+                Arc::clone(native),
+                with_path(req, &rest),
+                responder,
+              ),
+              RouteTarget::Redirect { location, status } => responder.respond(redirect_response(
+                location,
+                *status,
+                &rest,
+                req.uri().query(),
+              )),
             }
+          })
+        }
+      };
+      let policy = protocol.policy.clone();
+      let work_around = work_around.clone();
+      webview = webview.with_asynchronous_custom_protocol(
+        protocol.name.clone(),
+        move |webview_id, mut req, responder| {
+          if let Some(work_around) = &work_around {
+            work_around.revert_request_headers(req.headers_mut());
+          }
+          let responder =
+            ProtocolResponder::new(responder, &req, policy.clone(), work_around.clone());
+          // CORS preflights are answered here, whatever the handler.
+          if let Some(preflight) = responder.preflight(&req) {
+            responder.respond(preflight);
+            return;
           }
-        });
+          handler(webview_id, req, responder)
+        },
+      );
     }
 
-    // ── IPC (with expose routing) ─────────────────────────────────────────────
+    // ── IPC (with expose routing, origin checks and limits) ───────────────────
     let ipc_state = Rc::new(RefCell::new(None::<FunctionRef<IpcMessage, ()>>));
     let ipc_state_clone = ipc_state.clone();
-    let expose_handlers: Rc<
-      RefCell<std::collections::HashMap<String, FunctionRef<ExposeCallData, ()>>>,
-    > = Rc::new(RefCell::new(std::collections::HashMap::new()));
-    let expose_handlers_clone = Rc::clone(&expose_handlers);
+    let bridge_ipc = Rc::clone(&bridge);
     let env_copy = *env;
 
-    let ipc_handler = move |req: Request<String>| {
-      let body_str = req.body().as_str();
-
-      // Check for expose() proxy calls before forwarding to user handler.
-      // The page-side script always sets __e:true for these messages.
-      if let Ok(v) = serde_json::from_str::<serde_json::Value>(body_str) {
-        if v.get("__e").and_then(|x| x.as_bool()) == Some(true) {
-          let ns = v
-            .get("ns")
-            .and_then(|x| x.as_str())
-            .unwrap_or("")
-            .to_string();
-          let method = v
-            .get("method")
-            .and_then(|x| x.as_str())
-            .unwrap_or("")
-            .to_string();
-          let id = v.get("id").and_then(|x| x.as_f64()).unwrap_or(0.0);
-          let args_json = v
-            .get("args")
-            .map(|x| x.to_string())
-            .unwrap_or_else(|| "[]".to_string());
-
-          let borrowed = expose_handlers_clone.borrow();
-          if let Some(func_ref) = borrowed.get(&ns) {
-            if let Ok(func) = func_ref.borrow_back(&env_copy) {
-              let _ = func.call(ExposeCallData {
-                ns,
-                method,
-                id,
-                args_json,
-              });
-            }
-          }
-          return; // consume — do not forward to user handler
-        }
-      }
-
-      // User IPC handler
-      let borrowed = RefCell::borrow(&ipc_state_clone);
-      if let Some(func) = borrowed.as_ref() {
-        let Ok(on_ipc_msg) = func.borrow_back(&env_copy) else {
-          return;
-        };
+    let deliver = move |req: Request<String>| {
+      let (parts, body) = req.into_parts();
+      call_ipc_handler(
+        &ipc_state_clone,
+        env_copy,
+        Request::from_parts(parts, body.into_bytes()),
+        false,
+      );
+    };
 
-        let body = req.body().as_bytes().to_vec().into();
-        let headers = req
-          .headers()
-          .iter()
-          .map(|(k, v)| HeaderData {
-            key: k.as_str().to_string(),
-            value: v.to_str().ok().map(|s| s.to_string()),
-          })
-          .collect::<Vec<_>>();
-
-        let _ = on_ipc_msg.call(IpcMessage {
-          body,
-          headers,
-          method: req.method().to_string(),
-          uri: req.uri().to_string(),
-        });
+    let ipc_queue = ipc_limits.map(|limits| {
+      let ev_rc = Rc::clone(&event_handler);
+      Rc::new(IpcQueue::new(limits, deliver.clone(), move |violation| {
+        dispatch_event(
+          &ev_rc,
+          WebviewEventPayload {
+            event: WebviewEventType::IpcLimitExceeded,
+            url: Some(violation.url),
+            reason: Some(violation.limit.as_str().to_owned()),
+            count: Some(violation.count),
+            ..Default::default()
+          },
+        );
+      }))
+    });
+    let queue_ipc = ipc_queue.clone();
+    webview = webview.with_ipc_handler(move |req| {
+      // Bridge messages are consumed here, ahead of the limits, and never
+      // reach the user handler.
+      let url = req.uri().to_string();
+      if bridge_ipc.handle_ipc(req.body(), &url) || !bridge_ipc.allows_ipc(&url) {
+        return;
       }
-    };
+      match &queue_ipc {
+        Some(queue) => queue.post(req),
+        None => deliver(req),
+      }
+    });
 
-    webview = webview.with_ipc_handler(ipc_handler);
+    // ── Exposed calls and binary messages ─────────────────────────────────────
+    {
+      let bridge = Rc::clone(&bridge);
+      let ipc_state = Rc::clone(&ipc_state);
+      let ipc_queue = ipc_queue.clone();
+      let env_copy = *env;
+      webview = webview.with_asynchronous_custom_protocol(
+        MESSAGE_SCHEME.to_owned(),
+        move |_webview_id, req, responder| {
+          bridge.message_request(
+            req,
+            Box::new(move |response| responder.respond(response)),
+            |message| match &ipc_queue {
+              Some(queue) => queue.admit_now(message.body().len(), &message.uri().to_string()),
+              None => Ok(()),
+            },
+            |message| call_ipc_handler(&ipc_state, env_copy, message, true),
+          )
+        },
+      );
+    }
 
     let err = |e| {
       napi::Error::new(
@@ -519,17 +714,67 @@ impl JsWebview {
       webview.build(window).map_err(err)
     }?;
 
+    // WebKitGTK refuses cross-origin requests to custom schemes unless they
+    // are CORS-enabled, and the message scheme is cross-origin to every page.
+    #[cfg(all(
+      unix,
+      not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+    ))]
+    {
+      use webkit2gtk::{SecurityManagerExt, WebContextExt, WebViewExt};
+      use wry::WebViewExtUnix;
+      if let Some(security) = built
+        .webview()
+        .context()
+        .and_then(|context| context.security_manager())
+      {
+        security.register_uri_scheme_as_cors_enabled(MESSAGE_SCHEME);
+      }
+
+      let ev_rc = Rc::clone(&event_handler);
+      history::watch(&built, move |state| {
+        dispatch_event(
+          &ev_rc,
+          WebviewEventPayload {
+            event: WebviewEventType::HistoryChanged,
+            url: state.url,
+            can_go_back: Some(state.can_go_back),
+            can_go_forward: Some(state.can_go_forward),
+            ..Default::default()
+          },
+        );
+      });
+
+      let ev_rc = Rc::clone(&event_handler);
+      find::watch(&built, move |text, matches| {
+        dispatch_event(
+          &ev_rc,
+          WebviewEventPayload {
+            event: WebviewEventType::FoundInPage,
+            text: Some(text),
+            matches: Some(matches),
+            ..Default::default()
+          },
+        );
+      });
+    }
+
+    let webview_id = built.id().to_owned();
+    *webview_inner.borrow_mut() = Some(Rc::new(built));
+    if let Some(queue) = &ipc_queue {
+      ipc_queues.borrow_mut().push(Rc::clone(queue));
+    }
     Ok(Self {
-      webview_inner: Rc::new(RefCell::new(Some(Rc::new(built)))),
+      webview_inner,
       ipc_state,
-      expose_handlers,
+      bridge,
       disposed: Rc::new(Cell::new(false)),
       auto_normalize_load_url: options.auto_normalize_load_url.unwrap_or(true),
-      protocols: protocols
-        .iter()
-        .map(|(name, _, _, _)| name.clone())
-        .collect(),
+      protocols: protocols.iter().map(|p| p.name.clone()).collect(),
       https_scheme_enabled: options.use_https_scheme.unwrap_or(false),
+      protocol_requests: Rc::clone(protocol_requests),
+      ipc_queue,
+      webview_id,
     })
   }
 
@@ -550,6 +795,10 @@ impl JsWebview {
     Rc::clone(&self.disposed)
   }
 
+  pub(crate) fn bridge(&self) -> &Rc<PageBridge> {
+    &self.bridge
+  }
+
   fn webview(&self) -> Ref<'_, Rc<wry::WebView>> {
     match Ref::filter_map(self.webview_inner.borrow(), Option::as_ref) {
       Ok(webview) => webview,
@@ -562,11 +811,19 @@ impl JsWebview {
     if self.disposed.replace(true) {
       return;
     }
+    fail_requests(
+      &self.protocol_requests,
+      wry::http::StatusCode::SERVICE_UNAVAILABLE,
+      |request| request.webview_id == self.webview_id,
+    );
+    self.bridge.dispose();
+    if let Some(queue) = &self.ipc_queue {
+      queue.close();
+    }
     if let Some(webview) = self.webview_inner.borrow_mut().take() {
       let _ = webview.set_visible(false);
     }
     self.ipc_state.borrow_mut().take();
-    self.expose_handlers.borrow_mut().clear();
   }
 
   #[napi]
@@ -574,17 +831,25 @@ impl JsWebview {
     self.disposed.get()
   }
 
+  /// Identifier of the webview, unique within the application.
+  #[napi]
+  pub fn id(&self) -> String {
+    self.webview_id.clone()
+  }
+
   // ── expose() support ─────────────────────────────────────────────────────────
 
   /// Low-level method used by the JS `expose()` wrapper.
   ///
-  /// Injects a page script that creates `window[name]` as an object with:
+  /// Creates `window[name]` in the page as an object with:
   /// - static values from `statics_json` (a JSON object string)
   /// - async function stubs for each name in `func_names`
   ///
-  /// When the page calls one of the stubs the call is routed back here via
-  /// the internal IPC channel and dispatched to `handler`.  `handler` is
-  /// responsible for calling `evaluateScript` to send the response.
+  /// The namespace is installed again in every document the webview loads
+  /// whose URL matches `origins`, which defaults to the webview's
+  /// `ipcOrigins`.  When the page calls one of the stubs the call is routed
+  /// back here via the internal IPC channel and dispatched to `handler`,
+  /// which answers it with `_exposeReply`.
   #[napi(js_name = "_exposeInternal")]
   pub fn expose_internal(
     &mut self,
@@ -592,58 +857,127 @@ impl JsWebview {
     statics_json: String,
     func_names: Vec<String>,
     handler: FunctionRef<ExposeCallData, ()>,
+    origins: Option<Vec<String>>,
   ) -> Result<()> {
-    // Register the handler so the IPC router can find it
+    let origins = origins
+      .map(|origins| OriginPolicy::new(Some(&origins)))
+      .transpose()
+      .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))?;
     self
-      .expose_handlers
-      .borrow_mut()
-      .insert(name.clone(), handler);
-
-    // Generate the page-side bootstrap script.
-    // We create window.__webviewjs__ once (idempotent) and then build the
-    // namespace proxy for this specific `name`.
-    let name_json = serde_json::to_string(&name)
-      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e.to_string()))?;
-    let funcs_json = serde_json::to_string(&func_names)
-      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e.to_string()))?;
-
-    let script = format!(
-      r#"(function(){{
-  if(!window.__webviewjs__){{
-    let __id=0;
-    const __p=new Map();
-    window.__webviewjs__={{
-      resolve:function(id,val){{const e=__p.get(id);if(e){{__p.delete(id);e[0](val);}}}},
-      reject:function(id,message,name){{const e=__p.get(id);if(e){{__p.delete(id);const err=new Error(message);err.name=name||'Error';e[1](err);}}}},
-      call:function(ns,method,args){{
-        let argsJson;
-        try{{argsJson=JSON.stringify(args);if(argsJson===undefined)throw new Error('not serialisable');}}
-        catch{{const err=new Error('Arguments are not JSON-serialisable');err.name='SerializationError';return Promise.reject(err);}}
-        return new Promise(function(res,rej){{
-          const id=++__id;
-          __p.set(id,[res,rej]);
-          window.ipc.postMessage(JSON.stringify({{__e:true,ns:ns,method:method,id:id,args:JSON.parse(argsJson)}}));
-        }});
-      }}
-    }};
-  }}
-  const __statics={statics};
-  const __funcs={funcs};
-  const __ns=Object.assign({{}},__statics);
-  for(const fn of __funcs){{
-    (function(m){{__ns[m]=function(){{return window.__webviewjs__.call({name},m,Array.from(arguments));}};}})(fn);
-  }}
-  window[{name}]=__ns;
-}})();"#,
-      statics = statics_json,
-      funcs = funcs_json,
-      name = name_json,
-    );
+      .bridge
+      .expose(name, statics_json, func_names, origins, handler)
+      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e))
+  }
 
+  /// Low-level method used by the JS `unexpose()` wrapper.  Deletes
+  /// `window[name]` and rejects its pending calls; returns `false` when the
+  /// namespace was not exposed.
+  #[napi(js_name = "_unexposeInternal")]
+  pub fn unexpose_internal(&mut self, name: String) -> Result<bool> {
     self
-      .webview()
-      .evaluate_script(&script)
-      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e.to_string()))
+      .bridge
+      .unexpose(&name)
+      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e))
+  }
+
+  /// Send a message to the page, dispatched as a `message` event on
+  /// `window.webviewjs`.  Strings arrive as strings and binary data as an
+  /// `ArrayBuffer`; messages arrive in the order they were sent.
+  #[napi]
+  pub fn post_message(&self, data: Either<String, Uint8Array>) -> Result<()> {
+    let message = match data {
+      Either::A(text) => PageMessage::Text(text),
+      Either::B(bytes) => PageMessage::Binary(bytes.to_vec()),
+    };
+    self
+      .bridge
+      .post_message(message)
+      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e))
+  }
+
+  /// Answer exposed call `id` with a JSON value, or reject it with `error`.
+  ///
+  /// Returns `false` when the call is no longer pending, e.g. because the
+  /// page navigated away.  Invalid `value_json` rejects the call with a
+  /// `SerializationError` and throws.
+  #[napi(js_name = "_exposeReply")]
+  pub fn expose_reply(
+    &self,
+    id: f64,
+    value_json: Option<String>,
+    error: Option<ExposeCallError>,
+  ) -> Result<bool> {
+    let id = id as u64;
+    let outcome = match (error, value_json) {
+      (Some(error), _) => ExposeOutcome::Error {
+        name: error.name.unwrap_or_else(|| "Error".to_owned()),
+        message: error.message,
+      },
+      (None, None) => ExposeOutcome::Value(None),
+      (None, Some(json)) => match serde_json::from_str(&json) {
+        Ok(value) => ExposeOutcome::Value(Some(value)),
+        Err(e) => {
+          self.bridge.reply(
+            id,
+            ExposeOutcome::error("SerializationError", "Return value is not valid JSON"),
+          );
+          return Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            format!("Invalid reply to exposed call {}: {}", id, e),
+          ));
+        }
+      },
+    };
+    Ok(self.bridge.reply(id, outcome))
+  }
+
+  /// Answer exposed call `id` with an async iterator in the page.  Returns
+  /// the id of the stream, which the page pulls from with `next` calls, or
+  /// `null` when the call is no longer pending.
+  #[napi(js_name = "_exposeStream")]
+  pub fn expose_stream(&self, id: f64) -> Option<f64> {
+    self
+      .bridge
+      .open_stream(id as u64)
+      .map(|stream| stream as f64)
+  }
+
+  /// Run a page function passed to an exposed function, with `args_json`
+  /// as its arguments.  Returns `false` once it was released or its page
+  /// navigated away.
+  #[napi(js_name = "_invokeCallback")]
+  pub fn invoke_callback(&self, id: f64, args_json: String) -> Result<bool> {
+    self
+      .bridge
+      .invoke_callback(id as u64, &args_json)
+      .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))
+  }
+
+  /// Let the page drop a function passed to an exposed function.  Returns
+  /// `false` when it was already released.
+  #[napi(js_name = "_releaseCallback")]
+  pub fn release_callback(&self, id: f64) -> bool {
+    self.bridge.release_callback(id as u64)
+  }
+
+  // ── Events ───────────────────────────────────────────────────────────────────
+
+  /// Low-level method used by the JS `emitToPage()` wrapper.  Dispatches
+  /// `event` to the page's `window.webviewjs.listen()` callbacks, in order
+  /// with messages.  `payload_json` must be valid JSON.
+  #[napi(js_name = "_emitInternal")]
+  pub fn emit_internal(&self, event: String, payload_json: Option<String>) -> Result<()> {
+    self
+      .bridge
+      .emit(&event, payload_json.as_deref())
+      .map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))
+  }
+
+  /// Low-level method used by the JS `listen()` wrapper.  `handler`
+  /// receives every event emitted with `window.webviewjs.emit()`.
+  #[napi(js_name = "_setPageEventHandler")]
+  pub fn set_page_event_handler(&self, handler: FunctionRef<PageEventData, ()>) {
+    self.bridge.set_event_handler(handler);
   }
 
   #[napi]
@@ -765,6 +1099,30 @@ impl JsWebview {
       .map_err(|e| napi::Error::new(napi::Status::GenericFailure, format!("{}", e)))
   }
 
+  /// Low-level method used by the JS `evaluate()` wrapper.  Runs `js` in
+  /// the page and hands `handler` its JSON value, once any promise it
+  /// returned settled, or the exception it ended with.  Returns the id of
+  /// the evaluation.
+  #[napi(js_name = "_evaluateInternal")]
+  pub fn evaluate_internal(
+    &self,
+    js: String,
+    handler: FunctionRef<EvaluationData, ()>,
+  ) -> Result<f64> {
+    self
+      .bridge
+      .evaluate_script(&js, handler)
+      .map(|id| id as f64)
+      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e))
+  }
+
+  /// Stop waiting for evaluation `id`, e.g. after a timeout.  The script
+  /// keeps running.  Returns `false` when it already ended.
+  #[napi(js_name = "_cancelEvaluation")]
+  pub fn cancel_evaluation(&self, id: f64) -> bool {
+    self.bridge.cancel_evaluation(id as u64)
+  }
+
   #[napi]
   pub fn reload(&self) -> Result<()> {
     self.webview().reload().map_err(|e| {
@@ -783,6 +1141,42 @@ impl JsWebview {
     self.webview().url().ok()
   }
 
+  /// Go back one entry in the history.  Returns `false` when there is none.
+  #[napi]
+  pub fn go_back(&self) -> Result<bool> {
+    history::go(&self.webview(), Step::Back)
+  }
+
+  /// Go forward one entry in the history.  Returns `false` when there is
+  /// none.
+  #[napi]
+  pub fn go_forward(&self) -> Result<bool> {
+    history::go(&self.webview(), Step::Forward)
+  }
+
+  #[napi]
+  pub fn can_go_back(&self) -> Result<bool> {
+    history::can_go(&self.webview(), Step::Back)
+  }
+
+  #[napi]
+  pub fn can_go_forward(&self) -> Result<bool> {
+    history::can_go(&self.webview(), Step::Forward)
+  }
+
+  /// Stop loading the current page.
+  #[napi]
+  pub fn stop(&self) -> Result<()> {
+    history::stop(&self.webview())
+  }
+
+  /// The back-forward list.  Only supported on Linux (WebKitGTK), like the
+  /// other history methods.
+  #[napi]
+  pub fn history(&self) -> Result<WebviewHistory> {
+    history::history(&self.webview())
+  }
+
   /// Webview width in logical pixels (same coordinate space as `set_bounds`).
   #[napi(getter)]
   pub fn width(&self) -> Option<f64> {
@@ -843,6 +1237,33 @@ impl JsWebview {
       .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e.to_string()))
   }
 
+  // ── Find in page ─────────────────────────────────────────────────────────────
+
+  /// Highlight the matches of `text` and select the first one.  A
+  /// `found-in-page` event reports their number.
+  #[napi]
+  pub fn find(&self, text: String, options: Option<FindOptions>) -> Result<()> {
+    find::find(&self.webview(), &text, options)
+  }
+
+  /// Select the next match.  Returns `false` when no search is active.
+  #[napi]
+  pub fn find_next(&self) -> Result<bool> {
+    find::find_again(&self.webview(), Step::Forward)
+  }
+
+  /// Select the previous match.  Returns `false` when no search is active.
+  #[napi]
+  pub fn find_previous(&self) -> Result<bool> {
+    find::find_again(&self.webview(), Step::Back)
+  }
+
+  /// End the search and remove its highlights.
+  #[napi]
+  pub fn stop_find(&self) -> Result<()> {
+    find::stop_find(&self.webview())
+  }
+
   // ── Cookies ──────────────────────────────────────────────────────────────────
 
   /// Return all cookies currently stored for `url`, or every cookie if `url`
@@ -962,6 +1383,103 @@ impl JsWebview {
       .focus_parent()
       .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e.to_string()))
   }
+
+  // ── Capture ───────────────────────────────────────────────────────────────────
+
+  /// Low-level method used by the JS `snapshot()` wrapper.  Captures the
+  /// viewport, or `options.rect` of it, and hands `handler` the PNG.  Only
+  /// WebKitGTK can capture; elsewhere this throws.
+  #[napi(js_name = "_snapshotInternal")]
+  pub fn snapshot_internal(
+    &self,
+    env: Env,
+    options: Option<SnapshotOptions>,
+    handler: FunctionRef<SnapshotData, ()>,
+  ) -> Result<()> {
+    let request =
+      SnapshotRequest::new(options).map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))?;
+    #[cfg(all(
+      unix,
+      not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+    ))]
+    {
+      crate::snapshot::capture(&self.webview(), request, move |result| {
+        let data = match result {
+          Ok(png) => SnapshotData {
+            png: Some(png.into()),
+            error: None,
+          },
+          Err(e) => SnapshotData {
+            png: None,
+            error: Some(e),
+          },
+        };
+        if let Ok(handler) = handler.borrow_back(&env) {
+          let _ = handler.call(data);
+        }
+      });
+      Ok(())
+    }
+    #[cfg(not(all(
+      unix,
+      not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+    )))]
+    {
+      let _ = (env, request, handler);
+      Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        "Snapshots are not supported on this platform",
+      ))
+    }
+  }
+
+  /// Low-level method used by the JS `printToPdf()` wrapper.  Prints the
+  /// page without a dialog and hands `handler` the PDF, or nothing once it
+  /// was written to `options.path`.  Only WebKitGTK can print to PDF;
+  /// elsewhere this throws.
+  #[napi(js_name = "_printToPdfInternal")]
+  pub fn print_to_pdf_internal(
+    &self,
+    env: Env,
+    options: Option<PdfOptions>,
+    handler: FunctionRef<PdfData, ()>,
+  ) -> Result<()> {
+    let request =
+      PdfRequest::new(options).map_err(|e| napi::Error::new(napi::Status::InvalidArg, e))?;
+    #[cfg(all(
+      unix,
+      not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+    ))]
+    {
+      crate::pdf::print_to_pdf(&self.webview(), request, move |result| {
+        let data = match result {
+          Ok(pdf) => PdfData {
+            pdf: pdf.map(Into::into),
+            error: None,
+          },
+          Err(e) => PdfData {
+            pdf: None,
+            error: Some(e),
+          },
+        };
+        if let Ok(handler) = handler.borrow_back(&env) {
+          let _ = handler.call(data);
+        }
+      })
+      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, e))
+    }
+    #[cfg(not(all(
+      unix,
+      not(any(target_os = "macos", target_os = "android", target_os = "ios"))
+    )))]
+    {
+      let _ = (env, request, handler);
+      Err(napi::Error::new(
+        napi::Status::GenericFailure,
+        "Printing to PDF is not supported on this platform",
+      ))
+    }
+  }
 }
 
 // ── Cookie helpers ────────────────────────────────────────────────────────────
@@ -0,0 +1,88 @@
+//! A fixed set of threads for blocking work kept off the main thread.
+//!
+//! Native protocol responses read files and wait on dev servers.  Every
+//! window of an application queues them on the application's pool, so a page
+//! loading hundreds of assets does not start hundreds of threads.  The
+//! threads start with the first job and exit once the pool is dropped and
+//! the queue drained.
+
+use std::sync::{
+  mpsc::{self, Receiver, Sender},
+  Arc, Mutex, OnceLock,
+};
+
+/// Threads of a pool.  Proxied requests may wait on a slow upstream, so
+/// there are a few more than a static site needs.
+const WORKERS: usize = 8;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub(crate) struct WorkerPool {
+  queue: OnceLock<Sender<Job>>,
+}
+
+impl WorkerPool {
+  pub(crate) fn new() -> Self {
+    Self {
+      queue: OnceLock::new(),
+    }
+  }
+
+  /// Run `job` on one of the pool's threads.
+  pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+    let queue = self.queue.get_or_init(|| {
+      let (sender, receiver) = mpsc::channel::<Job>();
+      let receiver = Arc::new(Mutex::new(receiver));
+      for index in 0..WORKERS {
+        let receiver = Arc::clone(&receiver);
+        let _ = std::thread::Builder::new()
+          .name(format!("webview-worker-{}", index))
+          .spawn(move || work(&receiver));
+      }
+      sender
+    });
+    let _ = queue.send(Box::new(job));
+  }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+  loop {
+    // Not locked while the job runs.
+    let job = match receiver.lock() {
+      Ok(receiver) => receiver.recv(),
+      Err(_) => return,
+    };
+    match job {
+      Ok(job) => job(),
+      Err(_) => return,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashSet, time::Duration};
+
+  use super::*;
+
+  #[test]
+  fn jobs_share_a_fixed_set_of_threads() {
+    let pool = WorkerPool::new();
+    let (done, results) = mpsc::channel();
+    for job in 0..100 {
+      let done = done.clone();
+      pool.execute(move || {
+        let _ = done.send((job, std::thread::current().id()));
+      });
+    }
+    let mut jobs = HashSet::new();
+    let mut threads = HashSet::new();
+    for _ in 0..100 {
+      let (job, thread) = results.recv_timeout(Duration::from_secs(5)).unwrap();
+      jobs.insert(job);
+      threads.insert(thread);
+    }
+    assert_eq!(jobs.len(), 100);
+    assert!(threads.len() <= WORKERS);
+  }
+}